@@ -1,4 +1,5 @@
-use clap::{crate_version, Arg, Command};
+use clap::{crate_version, value_parser, Arg, ArgAction, Command};
+use std::ffi::OsString;
 
 pub fn version() -> String {
 	crate_version!()[..].to_owned()
@@ -8,16 +9,25 @@ pub fn clap_cmd() -> Command {
 	Command::new("binst")
 		.version(crate_version!())
 		.about("Decentralized binary install and deployment")
+		.arg(arg_verbose())
+		.arg(arg_root())
+		.arg(arg_system())
 		.subcommand(sub_setup())
 		.subcommand(sub_info())
 		.subcommand(sub_publish())
 		.subcommand(sub_install())
 		.subcommand(sub_update())
+		.subcommand(sub_cache())
+		.subcommand(sub_target())
+		.subcommand(sub_config())
+		.subcommand(sub_sync())
+		.subcommand(sub_run())
+		.subcommand(sub_repo())
 }
 
 // region:    Subcommands
 fn sub_setup() -> Command {
-	Command::new("self").about("Self installing binst into ~/.binst/bin/binst")
+	Command::new("self").about("Self installing binst into ~/.binst/bin/binst (or the --root / --system / BINST_HOME dir)")
 }
 
 fn sub_info() -> Command {
@@ -45,6 +55,13 @@ fn sub_install() -> Command {
 		.arg(arg_bin_name())
 		.arg(arg_stream())
 		.arg(arg_profile())
+		.arg(arg_target())
+		.arg(
+			Arg::new("offline")
+				.long("offline")
+				.action(ArgAction::SetTrue)
+				.help("Install only from the local package cache (no network)"),
+		)
 }
 
 fn sub_update() -> Command {
@@ -53,11 +70,132 @@ fn sub_update() -> Command {
 		.arg(arg_bin_name())
 		.arg(arg_repo().required(false)) // turn off require for upteate
 		.arg(arg_profile())
+		.arg(arg_target())
+}
+
+fn sub_cache() -> Command {
+	Command::new("cache")
+		.about("Manage the local package cache (~/.binst/cache or BINST_CACHE_DIR)")
+		.subcommand_required(true)
+		.subcommand(Command::new("clean").about("Remove all the cached packages"))
+}
+
+fn sub_target() -> Command {
+	Command::new("target").about("Print the detected platform target (and why)")
+}
+
+fn sub_sync() -> Command {
+	Command::new("sync")
+		.about("Install or update the tools of the nearest binst.toml, as locked in its binst.lock")
+		.arg(
+			Arg::new("locked")
+				.long("locked")
+				.action(ArgAction::SetTrue)
+				.help("Fail if the binst.lock is missing or does not match the binst.toml (e.g., for CI)"),
+		)
+		.arg(
+			Arg::new("offline")
+				.long("offline")
+				.action(ArgAction::SetTrue)
+				.help("Install only from the local package cache (no network)"),
+		)
+}
+
+fn sub_run() -> Command {
+	Command::new("run")
+		.about("Run a package version without installing it, e.g., binst run cool-cli@0.4.0-rc.1 -- --help")
+		.arg(Arg::new("bin_name").required(true).help("Name of the bin package, with an optional @version"))
+		.arg(arg_repo())
+		.arg(arg_stream())
+		.arg(arg_profile())
+		.arg(arg_target())
+		.arg(
+			Arg::new("args")
+				.num_args(0..)
+				.last(true)
+				.allow_hyphen_values(true)
+				.value_parser(value_parser!(OsString))
+				.help("Arguments of the bin (after --)"),
+		)
+}
+
+fn sub_repo() -> Command {
+	Command::new("repo")
+		.about("Manage the repos, e.g., mirror a repo for an air-gapped network")
+		.subcommand_required(true)
+		.subcommand(
+			Command::new("mirror")
+				.about("Copy the packages of a repo into another one (only the changed files), the latest.toml files last")
+				.arg(
+					Arg::new("from")
+						.long("from")
+						.num_args(1)
+						.required(true)
+						.help("The repo to copy from (name or url), e.g., s3://binst-repo"),
+				)
+				.arg(
+					Arg::new("to")
+						.long("to")
+						.num_args(1)
+						.required(true)
+						.help("The mirror repo (name or url), e.g., /mnt/mirror"),
+				)
+				.arg(Arg::new("bin_name").long("bin").num_args(1).help("Only the packages of this bin"))
+				.arg(Arg::new("stream").long("stream").short('s').num_args(1).help("Only this release stream"))
+				.arg(arg_target().help("Only this platform target, e.g., x86_64-unknown-linux-musl"))
+				.arg(arg_profile()),
+		)
+}
+
+fn sub_config() -> Command {
+	Command::new("config")
+		.about("Get or set the global config (~/.binst/config.toml), e.g., repos.internal.url")
+		.subcommand_required(true)
+		.subcommand(Command::new("list").about("List all the config values"))
+		.subcommand(
+			Command::new("get")
+				.about("Get a config value")
+				.arg(Arg::new("key").required(true).help("Dot separated key, e.g., defaults.install_repo")),
+		)
+		.subcommand(
+			Command::new("set")
+				.about("Set a config value")
+				.arg(Arg::new("key").required(true).help("Dot separated key, e.g., repos.internal.url"))
+				.arg(
+					Arg::new("value")
+						.required(true)
+						.help(r#"The value, as toml if valid (e.g., '["x86_64-unknown-linux-musl"]'), otherwise as string"#),
+				),
+		)
 }
 // endregion: Subcommands
 
 // region:    Common Args
 
+fn arg_verbose() -> Arg {
+	Arg::new("verbose")
+		.long("verbose")
+		.short('v')
+		.global(true)
+		.action(ArgAction::SetTrue)
+		.help("Verbose output (e.g., network retries)")
+}
+
+fn arg_root() -> Arg {
+	Arg::new("root").long("root").num_args(1).global(true).help(
+		"Alternate binst dir (instead of BINST_HOME or ~/.binst), e.g., ./image-root to prepare an image",
+	)
+}
+
+fn arg_system() -> Arg {
+	Arg::new("system")
+		.long("system")
+		.global(true)
+		.action(ArgAction::SetTrue)
+		.conflicts_with("root")
+		.help("System wide binst dir /opt/binst, with the bin symlinks in /usr/local/bin")
+}
+
 fn arg_bin_name() -> Arg {
 	Arg::new("bin_name").required(true).help("Name of the bin package")
 }
@@ -2,9 +2,6 @@ pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
-	#[error("No Home Dir")]
-	NoHomeDir,
-
 	#[error("Install command must have a binary name in argument")]
 	NoBinName,
 
@@ -17,9 +14,39 @@ pub enum Error {
 	#[error("Version could not be found from bin path {0}")]
 	NoVersionFromBinPath(String),
 
+	#[error("Config command must have a key (and a value for set) in argument")]
+	NoConfigKey,
+
+	#[error("Config key '{0}' not found")]
+	ConfigKeyNotFound(String),
+
+	#[error("Tool {0} is not locked (or its lock does not match binst.toml) in {1}. Run 'binst sync' without --locked to update it")]
+	SyncLockOutdated(String, String),
+
+	#[error("Tool {0} version requirement '{1}' does not match the latest version {2}. Use an exact version (e.g., '=1.2.3') to install a previous one")]
+	SyncNoMatchingVersion(String, String, String),
+
+	#[error("No version selected for {0} (no env, .binst-version, binst.toml, or active version)")]
+	ShimNoVersion(String),
+
+	#[error("Invalid version '{0}' in {1}")]
+	ShimInvalidVersion(String, String),
+
+	#[error("{0} version {1} (from {2}) is not installed. Run 'binst sync' or 'binst install'")]
+	ShimVersionNotInstalled(String, String, String),
+
+	#[error("No installed {0} version matches '{1}' (from {2}). Run 'binst sync'")]
+	ShimNoMatchingInstalled(String, String, String),
+
+	#[error("Invalid version in '{0}', must be <bin>@<version> (e.g., cool-cli@0.4.0-rc.1)")]
+	RunInvalidVersion(String),
+
 	#[error("Cargo.toml has an invalid semver version {0}")]
 	CargoInvalidVersion(String),
 
+	#[error("Timeout waiting for the binst lock {0} held by process {1}.\n  Make sure no other binst is running, or increase BINST_LOCK_TIMEOUT (seconds).")]
+	LockTimeout(String, String),
+
 	#[error(transparent)]
 	BinRepo(#[from] crate::repo::Error),
 
@@ -34,4 +61,10 @@ pub enum Error {
 
 	#[error(transparent)]
 	Utils(#[from] crate::utils::Error),
+
+	#[error(transparent)]
+	Config(#[from] crate::config::Error),
+
+	#[error(transparent)]
+	Project(#[from] crate::project::Error),
 }
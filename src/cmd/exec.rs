@@ -1,18 +1,31 @@
 use crate::cmd::clap_cmd::clap_cmd;
+use crate::cmd::lock::{lock_binst_dir, lock_binst_dir_blocking};
+use crate::cmd::repo::exec_repo;
+use crate::cmd::run::exec_run;
 use crate::cmd::setup::exec_setup;
+use crate::cmd::shim::{exec_shim, parse_shim_args};
+use crate::cmd::sync::exec_sync;
 use crate::cmd::{Error, InstalledBinInfo, Result, CARGO_TOML};
-use crate::paths::binst_bin_dir;
-use crate::repo::{BinRepo, MAIN_STREAM};
-use crate::utils::{clean_path, get_toml_value_as_string};
+use crate::config::{masked_value, parse_config_value, Config};
+use crate::paths::{binst_bin_dir, binst_package_dir, detect_target, set_binst_root, set_binst_system};
+use crate::repo::{clean_cache, BinRepo, ACTIVE_LINK, MAIN_STREAM};
+use crate::utils::{clean_path, get_toml_value_as_string, set_verbose};
 use clap::ArgMatches;
 use semver::Version;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use toml::Value;
 
 // region:    --- CMD Executor
 pub fn cmd_exec() -> Result<()> {
+	// Note: the shim runs are not parsed by clap, so that the bin args are passed as is (see `cmd/shim.rs`)
+	if let Some(shim_args) = parse_shim_args(&std::env::args_os().collect::<Vec<_>>()) {
+		return exec_shim(shim_args);
+	}
+
 	let cmd = clap_cmd().get_matches();
+	set_verbose(cmd.get_flag("verbose"));
+	set_root(&cmd)?;
 
 	match cmd.subcommand() {
 		Some(("self", _)) => exec_setup()?,
@@ -20,6 +33,12 @@ pub fn cmd_exec() -> Result<()> {
 		Some(("install", sub_cmd)) => exec_install(sub_cmd)?,
 		Some(("update", sub_cmd)) => exec_update(sub_cmd)?,
 		Some(("info", sub_cmd)) => exec_info(sub_cmd)?,
+		Some(("cache", sub_cmd)) => exec_cache(sub_cmd)?,
+		Some(("target", _)) => exec_target(),
+		Some(("config", sub_cmd)) => exec_config(sub_cmd)?,
+		Some(("sync", sub_cmd)) => exec_sync(sub_cmd)?,
+		Some(("run", sub_cmd)) => exec_run(sub_cmd)?,
+		Some(("repo", sub_cmd)) => exec_repo(sub_cmd)?,
 		_ => {
 			// needs cmd_app version as the orginal got consumed by get_matches
 			clap_cmd().print_long_help()?;
@@ -36,12 +55,13 @@ pub fn cmd_exec() -> Result<()> {
 #[tokio::main]
 pub async fn exec_install(argm: &ArgMatches) -> Result<()> {
 	let bin_name = argm.get_one::<String>("bin_name").ok_or(Error::NoBinName)?;
-	let bin_repo = BinRepo::new(bin_name, argm, false)?;
+	let bin_repo = BinRepo::new(bin_name, argm)?;
+	let _lock = lock_binst_dir().await?;
 
-	let stream = argm
-		.get_one::<String>("stream")
-		.map(|s| s.to_string())
-		.unwrap_or_else(|| MAIN_STREAM.to_string());
+	let stream = match argm.get_one::<String>("stream") {
+		Some(stream) => stream.to_string(),
+		None => default_stream()?,
+	};
 	bin_repo.install(stream).await?;
 	Ok(())
 }
@@ -52,7 +72,7 @@ pub async fn exec_publish(argm: &ArgMatches) -> Result<()> {
 	let toml: Value = toml::from_str(&toml)?;
 	let bin_name = get_toml_value_as_string(&toml, &["package", "name"])?;
 
-	let bin_repo = BinRepo::new(&bin_name, argm, true)?;
+	let bin_repo = BinRepo::new(&bin_name, argm)?;
 	let at_path = argm.get_one::<String>("path").map(clean_path);
 
 	Ok(bin_repo.publish(at_path).await?)
@@ -61,14 +81,29 @@ pub async fn exec_publish(argm: &ArgMatches) -> Result<()> {
 #[tokio::main]
 pub async fn exec_update(argm: &ArgMatches) -> Result<()> {
 	let bin_name = argm.get_one::<String>("bin_name").ok_or(Error::NoBinName)?;
+	let _lock = lock_binst_dir().await?;
 
 	let InstalledBinInfo {
 		stream,
 		repo_raw,
 		version: installed_version,
+		target: installed_target,
 	} = extract_installed_bin_info(bin_name)?;
 
-	let repo = BinRepo::new(bin_name, argm, false)?;
+	// update from the installed repo (e.g., a github repo), unless overridden with -r
+	let repo = match argm.get_one::<String>("repo") {
+		Some(_) => BinRepo::new(bin_name, argm)?,
+		None => {
+			let profile = argm.get_one::<String>("profile").map(|s| s.as_str());
+			let target = argm.get_one::<String>("target").map(|s| s.to_string());
+			BinRepo::from_repo(bin_name, Some(&repo_raw), profile, target, false)?
+		}
+	};
+	// update with the installed target, unless overridden with --target
+	let repo = match (&repo.target, installed_target) {
+		(None, Some(installed_target)) => repo.with_target(installed_target),
+		_ => repo,
+	};
 	let origin_toml = repo.get_origin_latest_toml_content(&stream).await?;
 
 	let origin_toml: Value = toml::from_str(&origin_toml)?;
@@ -95,17 +130,19 @@ pub async fn exec_update(argm: &ArgMatches) -> Result<()> {
 
 #[tokio::main]
 pub async fn exec_info(argm: &ArgMatches) -> Result<()> {
-	let stream = MAIN_STREAM;
+	let stream = &default_stream()?;
 
 	let bin_name = argm.get_one::<String>("bin_name").ok_or(Error::NoBinName)?;
-	let bin_repo = BinRepo::new(bin_name, argm, false)?;
+	let bin_repo = BinRepo::new(bin_name, argm)?;
 
 	let version = bin_repo.get_origin_latest_version(stream).await?;
-	let url = bin_repo.get_origin_url(stream, &version)?;
+	let url = bin_repo.get_origin_url(stream, &version).await?;
+	let target = bin_repo.bin_target();
 
 	println!(
 		r#"Info for binary: {bin_name}
  Latest Version: {version}
+         Target: {target}
      Latest URL: {url}
 	"#,
 	);
@@ -113,22 +150,70 @@ pub async fn exec_info(argm: &ArgMatches) -> Result<()> {
 	Ok(())
 }
 
+pub fn exec_cache(argm: &ArgMatches) -> Result<()> {
+	if let Some(("clean", _)) = argm.subcommand() {
+		let _lock = lock_binst_dir_blocking()?;
+		let freed = clean_cache()?;
+		println!("Cache cleaned - {} MB freed", freed / (1024 * 1024));
+	}
+
+	Ok(())
+}
+
+pub fn exec_target() {
+	let target_info = detect_target();
+	println!("Detected target: {}", target_info.target);
+	for reason in target_info.reasons {
+		println!("  - {}", reason);
+	}
+}
+
+pub fn exec_config(argm: &ArgMatches) -> Result<()> {
+	match argm.subcommand() {
+		Some(("list", _)) => {
+			for (key, value) in Config::load()?.list() {
+				println!("{key} = {}", masked_value(&key, &value));
+			}
+		}
+		Some(("get", sub_cmd)) => {
+			let key = sub_cmd.get_one::<String>("key").ok_or(Error::NoConfigKey)?;
+			match Config::load()?.get(key)? {
+				Some(value) => println!("{}", masked_value(key, value)),
+				None => return Err(Error::ConfigKeyNotFound(key.to_string())),
+			}
+		}
+		Some(("set", sub_cmd)) => {
+			let key = sub_cmd.get_one::<String>("key").ok_or(Error::NoConfigKey)?;
+			let value = sub_cmd.get_one::<String>("value").ok_or(Error::NoConfigKey)?;
+			let _lock = lock_binst_dir_blocking()?;
+			let mut config = Config::load()?;
+			config.set(key, parse_config_value(value))?;
+			config.save()?;
+			println!("{key} = {}", masked_value(key, &parse_config_value(value)));
+		}
+		_ => (),
+	}
+
+	Ok(())
+}
+
 // endregion: --- Exec Functions
 
 // region:    --- Utils
 
-fn extract_installed_bin_info(bin_name: &str) -> Result<InstalledBinInfo> {
-	let version_dir = get_version_dir_from_symlink(bin_name)?;
+/// Returns the info of the active version of the bin.
+pub(super) fn extract_installed_bin_info(bin_name: &str) -> Result<InstalledBinInfo> {
+	let version_dir = get_active_version_dir(bin_name)?;
+	extract_version_dir_info(&version_dir)
+}
 
+/// Returns the info of the installed package version dir (e.g., `packages/cool_cli/0.4.1`).
+pub(super) fn extract_version_dir_info(version_dir: &Path) -> Result<InstalledBinInfo> {
 	// extract the version from the dir path
-	let version =
-		version_dir
-			.file_name()
-			.map(|f| f.to_string_lossy().to_string())
-			.and_then(|f| match Version::parse(&f) {
-				Ok(version) => Some(version),
-				Err(_) => None,
-			});
+	let version = version_dir
+		.file_name()
+		.map(|f| f.to_string_lossy().to_string())
+		.and_then(|f| Version::parse(&f).ok());
 	let version = version.ok_or(Error::NoVersionFromBinPath(version_dir.to_string_lossy().to_string()))?;
 
 	let install_toml_path = version_dir.join("install.toml");
@@ -151,15 +236,45 @@ fn extract_installed_bin_info(bin_name: &str) -> Result<InstalledBinInfo> {
 		}
 	};
 
+	// get the target (if recorded)
+	let target = get_toml_value_as_string(&install_toml, &["install", "target"]).ok();
+
 	Ok(InstalledBinInfo {
 		version,
 		stream,
 		repo_raw,
+		target,
 	})
 }
 
+/// The `[defaults] stream` of the config, otherwise main
+pub(super) fn default_stream() -> Result<String> {
+	Ok(Config::load()?.default_stream().unwrap_or_else(|| MAIN_STREAM.to_string()))
+}
+
+/// Set the alternate binst dir if `--root` or `--system`
+fn set_root(argm: &ArgMatches) -> Result<()> {
+	if let Some(root) = argm.get_one::<String>("root") {
+		set_binst_root(Path::new(root))?;
+	} else if argm.get_flag("system") {
+		set_binst_system();
+	}
+	Ok(())
+}
+
+/// The active version dir, from the `packages/<bin>/active` link,
+/// or from the `bin/<bin>` symlink (packages installed before the active link).
+fn get_active_version_dir(bin_name: &str) -> Result<PathBuf> {
+	let active_link = binst_package_dir(bin_name)?.join(ACTIVE_LINK);
+	if active_link.is_dir() {
+		return Ok(fs::canonicalize(&active_link)?);
+	}
+
+	get_version_dir_from_symlink(bin_name)
+}
+
 fn get_version_dir_from_symlink(bin_name: &str) -> Result<PathBuf> {
-	let bin_dir = binst_bin_dir();
+	let bin_dir = binst_bin_dir()?;
 	let bin_symlink = bin_dir.join(bin_name);
 	let path = fs::canonicalize(&bin_symlink)?;
 	let package = path.parent().and_then(|f| f.parent());
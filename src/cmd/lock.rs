@@ -0,0 +1,118 @@
+//! Advisory lock on the `~/.binst/.lock` file, held by the commands mutating the binst dir
+//! (install, update, self, ...), so that parallel runs do not race on the same package dirs and symlinks.
+
+use crate::cmd::{Error, Result};
+use crate::paths::binst_dir;
+use fs2::FileExt;
+use std::env;
+use std::fs::{create_dir_all, read_to_string, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const LOCK_FILE: &str = ".lock";
+const ENV_LOCK_TIMEOUT: &str = "BINST_LOCK_TIMEOUT";
+const DEFAULT_LOCK_TIMEOUT_SEC: u64 = 300;
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The lock is released when dropped (file closed).
+pub struct BinstLock {
+	_file: File,
+}
+
+/// Acquire the binst dir lock, waiting up to `BINST_LOCK_TIMEOUT` seconds (default 300)
+/// if another binst process holds it.
+/// Note: For the async commands, the wait does not block the runtime (see `lock_binst_dir_blocking` for the others).
+pub async fn lock_binst_dir() -> Result<BinstLock> {
+	let mut waiter = LockWaiter::open()?;
+	while !waiter.try_lock()? {
+		tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+	}
+	waiter.into_lock()
+}
+
+/// Same as `lock_binst_dir`, for the commands without async runtime (e.g., `binst cache`).
+pub fn lock_binst_dir_blocking() -> Result<BinstLock> {
+	let mut waiter = LockWaiter::open()?;
+	while !waiter.try_lock()? {
+		std::thread::sleep(LOCK_POLL_INTERVAL);
+	}
+	waiter.into_lock()
+}
+
+/// The lock file being acquired, with the wait state.
+struct LockWaiter {
+	file: File,
+	lock_path: PathBuf,
+	timeout: Duration,
+	start: Instant,
+	waiting_printed: bool,
+}
+
+impl LockWaiter {
+	fn open() -> Result<Self> {
+		let binst_dir = binst_dir()?;
+	if !binst_dir.is_dir() {
+		create_dir_all(&binst_dir)?;
+	}
+		let lock_path = binst_dir.join(LOCK_FILE);
+		// Note: not truncated on open, as the content is the current holder pid.
+		let file = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.create(true)
+			.truncate(false)
+			.open(&lock_path)?;
+
+		Ok(LockWaiter {
+			file,
+			lock_path,
+			timeout: lock_timeout(),
+			start: Instant::now(),
+			waiting_printed: false,
+		})
+	}
+
+	/// Returns true if acquired, false if held by another process (to try again after the poll interval),
+	/// or the timeout error.
+	fn try_lock(&mut self) -> Result<bool> {
+		if self.file.try_lock_exclusive().is_ok() {
+			return Ok(true);
+		}
+
+		let holder = read_to_string(&self.lock_path).unwrap_or_default().trim().to_string();
+		let holder = if holder.is_empty() { "unknown".to_string() } else { holder };
+
+		if self.start.elapsed() >= self.timeout {
+			return Err(Error::LockTimeout(self.lock_path.to_string_lossy().to_string(), holder));
+		}
+		if !self.waiting_printed {
+			println!(
+				"Waiting for the binst lock held by process {} ({})",
+				holder,
+				self.lock_path.to_string_lossy()
+			);
+			self.waiting_printed = true;
+		}
+		Ok(false)
+	}
+
+	/// The acquired lock (after `try_lock` returned true).
+	fn into_lock(self) -> Result<BinstLock> {
+		let mut file = self.file;
+		// record the holder pid (informational only)
+		file.set_len(0)?;
+		file.write_all(std::process::id().to_string().as_bytes())?;
+		file.flush()?;
+
+		Ok(BinstLock { _file: file })
+	}
+}
+
+fn lock_timeout() -> Duration {
+	let sec = env::var(ENV_LOCK_TIMEOUT)
+		.ok()
+		.and_then(|v| v.trim().parse::<u64>().ok())
+		.unwrap_or(DEFAULT_LOCK_TIMEOUT_SEC);
+	Duration::from_secs(sec)
+}
@@ -9,12 +9,19 @@ use semver::Version;
 pub mod clap_cmd;
 pub mod error;
 pub mod exec;
+pub mod lock;
+pub mod repo;
+pub mod run;
 pub mod setup;
+pub mod shim;
+pub mod sync;
 
 struct InstalledBinInfo {
 	stream: String,
 	version: Version,
 	repo_raw: String,
+	/// Target of the installed package (not recorded before the `--target` install support)
+	target: Option<String>,
 }
 
 pub const CARGO_TOML: &str = "Cargo.toml";
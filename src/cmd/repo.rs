@@ -0,0 +1,33 @@
+//! `binst repo mirror --from <repo> --to <repo>`, copies the packages of a repo into another one (see `repo/mirror.rs`).
+
+use crate::cmd::Result;
+use crate::repo::{mirror_repo, MirrorFilter, RepoInfo};
+use clap::ArgMatches;
+
+pub fn exec_repo(argm: &ArgMatches) -> Result<()> {
+	match argm.subcommand() {
+		Some(("mirror", sub_cmd)) => exec_mirror(sub_cmd),
+		_ => Ok(()),
+	}
+}
+
+#[tokio::main]
+async fn exec_mirror(argm: &ArgMatches) -> Result<()> {
+	let arg = |name: &str| argm.get_one::<String>(name).map(|s| s.to_string());
+
+	// Note: "from" and "to" are required by clap, and the profile is for both (the s3 ones)
+	let profile = argm.get_one::<String>("profile").map(|s| s.as_str());
+	let from = RepoInfo::from_repo_string(&arg("from").unwrap_or_default(), profile)?;
+	let to = RepoInfo::from_repo_string(&arg("to").unwrap_or_default(), profile)?;
+	let filter = MirrorFilter {
+		bin_name: arg("bin_name"),
+		target: arg("target"),
+		stream: arg("stream"),
+	};
+
+	println!("Mirroring repo {} to {}", from.url(), to.url());
+	let report = mirror_repo(&from, &to, &filter).await?;
+	println!("  Mirrored: {} copied, {} unchanged", report.copied, report.unchanged);
+
+	Ok(())
+}
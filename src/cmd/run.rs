@@ -0,0 +1,51 @@
+//! `binst run <bin>[@<version>] -- ...args`, runs a package version without installing it
+//! (unpacked under `~/.binst/run/`, the active version and `bin/` are untouched).
+
+use crate::cmd::exec::default_stream;
+use crate::cmd::lock::lock_binst_dir;
+use crate::cmd::{Error, Result};
+use crate::repo::{extract_stream, BinRepo};
+use clap::ArgMatches;
+use semver::Version;
+use std::ffi::OsString;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+#[tokio::main]
+pub async fn exec_run(argm: &ArgMatches) -> Result<()> {
+	let bin_spec = argm.get_one::<String>("bin_name").ok_or(Error::NoBinName)?;
+	let args: Vec<OsString> = argm.get_many::<OsString>("args").map(|a| a.cloned().collect()).unwrap_or_default();
+
+	let (bin_name, version) = parse_bin_spec(bin_spec)?;
+	let bin_repo = BinRepo::new(bin_name, argm)?;
+
+	// the stream of the version if defined, otherwise the --stream or default one
+	let stream = match (&version, argm.get_one::<String>("stream")) {
+		(Some(version), _) => extract_stream(version),
+		(None, Some(stream)) => stream.to_string(),
+		(None, None) => default_stream()?,
+	};
+
+	let bin_path = {
+		let _lock = lock_binst_dir().await?;
+		let (version, bin_path) = bin_repo.fetch_run_bin(&stream, version.as_ref()).await?;
+		// Note: on stderr, the stdout is the one of the bin (e.g., piped)
+		eprintln!("Running {bin_name} {version} ({})", bin_repo.bin_target());
+		bin_path
+	};
+
+	// Note: only returns on error (the exit code is the one of the bin)
+	let err = Command::new(&bin_path).arg0(bin_name).args(args).exec();
+	Err(err.into())
+}
+
+/// Parse the `<bin>[@<version>]` (e.g., `cool-cli@0.4.0-rc.1`)
+fn parse_bin_spec(bin_spec: &str) -> Result<(&str, Option<Version>)> {
+	match bin_spec.split_once('@') {
+		Some((bin_name, version)) => {
+			let version = Version::parse(version).map_err(|_| Error::RunInvalidVersion(bin_spec.to_string()))?;
+			Ok((bin_name, Some(version)))
+		}
+		None => Ok((bin_spec, None)),
+	}
+}
@@ -1,6 +1,7 @@
 use crate::cmd::clap_cmd::version;
+use crate::cmd::lock::lock_binst_dir_blocking;
 use crate::cmd::{Error, Result};
-use crate::repo::create_bin_symlink;
+use crate::repo::activate_package;
 use crate::{paths::*, repo::create_install_toml};
 use semver::Version;
 use std::fs::{copy, create_dir_all, write};
@@ -9,25 +10,26 @@ const SELF_REPO: &str = "https://binst.io/self";
 const SELF_STREAM: &str = "main";
 
 pub fn exec_setup() -> Result<()> {
-	let home_dir = dirs::home_dir().expect("No home dir");
 	let bin_name = "binst";
 
-	if !home_dir.is_dir() {
-		Err(Error::NoHomeDir)?;
-	}
-
 	// create the binst as needed
-	let binst_dir = binst_dir();
+	let binst_dir = binst_dir()?;
 	println!("Self installing binst under {}", binst_dir.to_string_lossy());
 	if !binst_dir.is_dir() {
 		create_dir_all(binst_dir.as_path())?;
 	}
+	let _lock = lock_binst_dir_blocking()?;
 
-	// create the ~/.binst/env as needed
-	let env_path = binst_env();
+	// create the ~/.binst/env as needed (with the actual bin dir when not the default one)
+	let bin_dir = binst_bin_dir()?;
+	let env_path = binst_env()?;
 	if !env_path.is_file() {
 		let env = include_bytes!("../assets/env");
 		let env: String = String::from_utf8_lossy(env).into();
+		let env = match dirs::home_dir() {
+			Some(home_dir) if home_dir.join(".binst/bin") == bin_dir => env,
+			_ => env.replace("$HOME/.binst/bin", &bin_dir.to_string_lossy()),
+		};
 		write(&env_path, env)?;
 	}
 
@@ -47,10 +49,10 @@ pub fn exec_setup() -> Result<()> {
 	let binst_path = unpacked_dir.join(bin_name);
 	copy(&exec_path, &binst_path)?;
 
-	create_install_toml(&package_dir, SELF_REPO, SELF_STREAM, &version)?;
+	create_install_toml(&package_dir, SELF_REPO, SELF_STREAM, &version, &os_target())?;
 
 	// create the binary
-	create_bin_symlink(bin_name, &binst_path)?;
+	activate_package(bin_name, &version)?;
 
 	println!(
 		"  Done - You can now delete this {} file, it has been copied to {}",
@@ -58,13 +60,15 @@ pub fn exec_setup() -> Result<()> {
 		binst_path.to_string_lossy()
 	);
 
+	let env_path = env_path.to_string_lossy();
 	println!(
 		r#"
-  IMPORTANT: Add '~/.binst/bin/' to your PATH environment. 
-    You can add the 'source "$HOME/.binst/env"' in your sh file
-      1) On mac: echo '\nsource "$HOME/.binst/env"' >> ~/.zshenv
-      2) On linux: echo 'source "$HOME/.binst/env"' >> ~/.bashrc
-"#
+  IMPORTANT: Add '{}' to your PATH environment. 
+    You can add the 'source "{env_path}"' in your sh file
+      1) On mac: echo '\nsource "{env_path}"' >> ~/.zshenv
+      2) On linux: echo 'source "{env_path}"' >> ~/.bashrc
+"#,
+		bin_dir.to_string_lossy()
 	);
 
 	Ok(())
@@ -0,0 +1,182 @@
+//! Shim mode (config `defaults.shims = true`), `bin/<bin>` is a shim script running `binst shim-exec <bin> ...args`,
+//! which selects the package version, and execs its `packages/<bin>/<version>/unpacked/<bin>`.
+//! The shim runs are parsed from the raw args (not with clap), so that the args are passed as is to the bin.
+//!
+//! The version is, in order:
+//! - `BINST_VERSION_<BIN>` env (e.g., `BINST_VERSION_COOL_CLI=0.4.1`)
+//! - the nearest `.binst-version` (lines of `<bin> <version>`) or `binst.toml` (locked, pinned,
+//!   or the latest installed matching version), from the current dir up
+//! - the active version (`packages/<bin>/active`)
+
+use crate::cmd::{Error, Result};
+use crate::paths::{binst_package_dir, set_binst_root, set_binst_system};
+use crate::project::{LockFile, Manifest, MANIFEST_FILE};
+use crate::repo::{get_version_part, ACTIVE_LINK};
+use semver::{Version, VersionReq};
+use std::env;
+use std::ffi::OsString;
+use std::fs::{canonicalize, read_dir, read_to_string};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const VERSION_FILE: &str = ".binst-version";
+const ENV_VERSION_PREFIX: &str = "BINST_VERSION_";
+
+/// A `binst [-v] [--root <dir> | --system] shim-exec <bin> ...args` run (the `bin/<bin>` shim scripts).
+pub struct ShimArgs {
+	root: Option<PathBuf>,
+	system: bool,
+	bin_name: String,
+	/// The bin args, as is (e.g., `-v`, `--root`, or `--` are the bin ones)
+	args: Vec<OsString>,
+}
+
+/// Returns the shim run of the raw args (with the program first), None if not a shim run.
+pub fn parse_shim_args(raw_args: &[OsString]) -> Option<ShimArgs> {
+	let mut rest = raw_args.get(1..)?;
+	let (mut root, mut system) = (None, false);
+	loop {
+		match rest.first()?.to_str()? {
+			"--root" => {
+				root = Some(PathBuf::from(rest.get(1)?));
+				rest = &rest[2..];
+			}
+			"--system" => {
+				system = true;
+				rest = &rest[1..];
+			}
+			"-v" | "--verbose" => rest = &rest[1..],
+			"shim-exec" => break,
+			_ => return None,
+		}
+	}
+	let bin_name = rest.get(1)?.to_str()?.to_string();
+
+	Some(ShimArgs {
+		root,
+		system,
+		bin_name,
+		args: rest[2..].to_vec(),
+	})
+}
+
+pub fn exec_shim(shim_args: ShimArgs) -> Result<()> {
+	let ShimArgs {
+		root,
+		system,
+		bin_name,
+		args,
+	} = shim_args;
+	match (root, system) {
+		(Some(root), _) => set_binst_root(&root)?,
+		(None, true) => set_binst_system(),
+		(None, false) => (),
+	}
+
+	let (version, source) = select_version(&bin_name)?;
+	let bin_path = binst_package_dir(&bin_name)?
+		.join(get_version_part(&version))
+		.join("unpacked")
+		.join(&bin_name);
+	if !bin_path.is_file() {
+		return Err(Error::ShimVersionNotInstalled(bin_name, version.to_string(), source));
+	}
+
+	// Note: only returns on error
+	let err = Command::new(&bin_path).arg0(&bin_name).args(args).exec();
+	Err(err.into())
+}
+
+/// Returns the selected version and where it comes from (for the error messages).
+fn select_version(bin_name: &str) -> Result<(Version, String)> {
+	// -- from the env
+	let env_name = format!("{ENV_VERSION_PREFIX}{}", bin_name.to_uppercase().replace('-', "_"));
+	if let Ok(version) = env::var(&env_name) {
+		let version = Version::parse(version.trim()).map_err(|_| Error::ShimInvalidVersion(version, env_name.clone()))?;
+		return Ok((version, env_name));
+	}
+
+	// -- from the nearest .binst-version or binst.toml
+	let current_dir = env::current_dir()?;
+	for dir in current_dir.ancestors() {
+		let version_file = dir.join(VERSION_FILE);
+		if let Some(version) = version_from_version_file(bin_name, &version_file)? {
+			return Ok((version, version_file.to_string_lossy().to_string()));
+		}
+
+		let manifest_path = dir.join(MANIFEST_FILE);
+		if let Some(version) = version_from_manifest(bin_name, &manifest_path)? {
+			return Ok((version, manifest_path.to_string_lossy().to_string()));
+		}
+	}
+
+	// -- the active version
+	let active_link = binst_package_dir(bin_name)?.join(ACTIVE_LINK);
+	let version = canonicalize(&active_link)
+		.ok()
+		.and_then(|dir| dir.file_name().and_then(|n| Version::parse(&n.to_string_lossy()).ok()))
+		.ok_or_else(|| Error::ShimNoVersion(bin_name.to_string()))?;
+	Ok((version, active_link.to_string_lossy().to_string()))
+}
+
+/// Returns the version of the bin in the `.binst-version` file if present (lines of `<bin> <version>`, `#` for comments).
+fn version_from_version_file(bin_name: &str, path: &Path) -> Result<Option<Version>> {
+	if !path.is_file() {
+		return Ok(None);
+	}
+	let content = read_to_string(path)?;
+	for line in content.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+		let mut parts = line.split_whitespace();
+		if let (Some(name), Some(version)) = (parts.next(), parts.next()) {
+			if name == bin_name {
+				let version = Version::parse(version)
+					.map_err(|_| Error::ShimInvalidVersion(version.to_string(), path.to_string_lossy().to_string()))?;
+				return Ok(Some(version));
+			}
+		}
+	}
+	Ok(None)
+}
+
+/// Returns the version of the bin from the `binst.toml` if it has the tool,
+/// the `binst.lock` one if locked, otherwise the pinned one, otherwise the latest installed one matching.
+fn version_from_manifest(bin_name: &str, path: &Path) -> Result<Option<Version>> {
+	if !path.is_file() {
+		return Ok(None);
+	}
+	let manifest = Manifest::load(path)?;
+	let Some(spec) = manifest.tools.iter().find(|t| t.name == bin_name) else {
+		return Ok(None);
+	};
+
+	let lock_file = LockFile::load(&manifest.dir)?;
+	if let Some(locked) = lock_file.tools.get(bin_name).filter(|l| spec.version_req.matches(&l.version)) {
+		return Ok(Some(locked.version.clone()));
+	}
+	if let Some(version) = spec.pinned_version() {
+		return Ok(Some(version));
+	}
+
+	match latest_installed_matching(bin_name, &spec.version_req)? {
+		Some(version) => Ok(Some(version)),
+		None => Err(Error::ShimNoMatchingInstalled(
+			bin_name.to_string(),
+			spec.version_req.to_string(),
+			path.to_string_lossy().to_string(),
+		)),
+	}
+}
+
+fn latest_installed_matching(bin_name: &str, req: &VersionReq) -> Result<Option<Version>> {
+	let package_dir = binst_package_dir(bin_name)?;
+	if !package_dir.is_dir() {
+		return Ok(None);
+	}
+	let version = read_dir(package_dir)?
+		.filter_map(|entry| entry.ok())
+		.filter_map(|entry| Version::parse(&entry.file_name().to_string_lossy()).ok())
+		.filter(|version| req.matches(version))
+		.max();
+	Ok(version)
+}
@@ -0,0 +1,128 @@
+//! `binst sync` - install or update the tools of the nearest `binst.toml`, as locked in its `binst.lock`.
+
+use crate::cmd::exec::{default_stream, extract_version_dir_info};
+use crate::cmd::lock::lock_binst_dir;
+use crate::cmd::{Error, InstalledBinInfo, Result};
+use crate::paths::binst_package_dir;
+use crate::project::{LockFile, LockedTool, Manifest, ToolSpec, LOCK_FILE};
+use crate::repo::{activate_package, extract_stream, get_version_part, BinRepo};
+use crate::utils::sha256_file;
+use clap::ArgMatches;
+use semver::Version;
+
+#[tokio::main]
+pub async fn exec_sync(argm: &ArgMatches) -> Result<()> {
+	let locked_only = argm.get_flag("locked");
+	let offline = argm.get_flag("offline");
+
+	let manifest = Manifest::find_from_current_dir()?;
+	let lock_file = LockFile::load(&manifest.dir)?;
+	println!("Syncing tools of {}", manifest.dir.to_string_lossy());
+
+	// Note: one lock for all the tools (the install does not lock)
+	let _lock = lock_binst_dir().await?;
+
+	let mut new_lock_file = LockFile::default();
+	for spec in manifest.tools.iter() {
+		let locked = sync_tool(spec, lock_file.tools.get(&spec.name), locked_only, offline).await?;
+		new_lock_file.tools.insert(spec.name.to_string(), locked);
+	}
+
+	if new_lock_file != lock_file {
+		new_lock_file.save(&manifest.dir)?;
+		println!("  Updated {}", manifest.dir.join(LOCK_FILE).to_string_lossy());
+	}
+
+	Ok(())
+}
+
+/// Install the tool version (locked if still matching the spec), and returns its lock entry.
+/// Note: The lock entry has a sha256 per target, a target not locked yet (e.g., another platform) is added to it.
+async fn sync_tool(spec: &ToolSpec, locked: Option<&LockedTool>, locked_only: bool, offline: bool) -> Result<LockedTool> {
+	let name = &spec.name;
+	let pinned_version = spec.pinned_version();
+	let stream = match (&pinned_version, &spec.stream) {
+		(Some(version), _) => extract_stream(version),
+		(None, Some(stream)) => stream.to_string(),
+		(None, None) => default_stream()?,
+	};
+
+	let bin_repo = BinRepo::from_repo(name, spec.repo.as_deref(), spec.profile.as_deref(), None, offline)?;
+	let repo_url = bin_repo.install_repo.url().to_string();
+
+	// the lock entry, if still matching the manifest spec, and its first target of this host (in the targets order)
+	let locked = locked.filter(|l| l.repo == repo_url && l.stream == stream && spec.version_req.matches(&l.version));
+	let locked_target = locked.and_then(|l| {
+		bin_repo
+			.targets
+			.iter()
+			.find_map(|target| l.targets.get(target).map(|sha256| (target.to_string(), sha256.to_string())))
+	});
+	if locked_target.is_none() && locked_only {
+		return Err(Error::SyncLockOutdated(name.to_string(), LOCK_FILE.to_string()));
+	}
+
+	let (bin_repo, version, expected_sha256) = match (locked, &locked_target) {
+		(Some(locked), Some((target, sha256))) => (
+			bin_repo.with_target(target.to_string()),
+			locked.version.clone(),
+			Some(sha256.as_str()),
+		),
+		// locked version, but no target of this host locked yet
+		(Some(locked), None) => (bin_repo, locked.version.clone(), None),
+		(None, _) => {
+			let version = match pinned_version {
+				Some(version) => version,
+				None => bin_repo.get_origin_latest_version(&stream).await?,
+			};
+			if !spec.version_req.matches(&version) {
+				return Err(Error::SyncNoMatchingVersion(
+					name.to_string(),
+					spec.version_req.to_string(),
+					version.to_string(),
+				));
+			}
+			(bin_repo, version, None)
+		}
+	};
+	// the other targets of the locked version are kept
+	let mut targets = locked.map(|l| l.targets.clone()).unwrap_or_default();
+
+	// already installed (same package bytes, for a target of this host), just make it the active version
+	if let Some((target, sha256)) = installed_package(name, &version, &repo_url) {
+		if bin_repo.targets.contains(&target) && expected_sha256.is_none_or(|expected| expected == sha256) {
+			activate_package(name, &version)?;
+			println!("  {name} {version} up to date");
+			targets.insert(target, sha256);
+			return Ok(LockedTool {
+				version,
+				repo: repo_url,
+				stream,
+				targets,
+			});
+		}
+	}
+
+	let installed = bin_repo.install_version(stream.to_string(), Some(&version), expected_sha256).await?;
+	targets.insert(installed.target, installed.sha256);
+
+	Ok(LockedTool {
+		version: installed.version,
+		repo: repo_url,
+		stream,
+		targets,
+	})
+}
+
+/// Returns the (target, package sha256) of the installed package version, if installed (and from the same repo).
+fn installed_package(name: &str, version: &Version, repo_url: &str) -> Option<(String, String)> {
+	let version_dir = binst_package_dir(name).ok()?.join(get_version_part(version));
+	let InstalledBinInfo { repo_raw, target, .. } = extract_version_dir_info(&version_dir).ok()?;
+	if repo_raw != repo_url {
+		return None;
+	}
+
+	let sha256 = sha256_file(&version_dir.join(format!("{name}.tar.gz"))).ok()?;
+
+	Some((target?, sha256))
+}
@@ -0,0 +1,333 @@
+//! Global binst config (`~/.binst/config.toml`)
+//!
+//! ```toml
+//! [defaults]
+//! install_repo = "internal"   # repo name or url, used when no `-r`
+//! publish_repo = "internal"
+//! stream = "main"
+//! targets = ["x86_64-unknown-linux-musl", "x86_64-unknown-linux-gnu"]
+//! shims = true                # (optional) per directory version selection (see `cmd/shim.rs`)
+//! retry_attempts = 4          # (optional) network retries, overridden by the `BINST_RETRY_...` env (see `repo/retry.rs`)
+//! retry_delay_ms = 500
+//! retry_max_delay_ms = 8000
+//!
+//! [repos.internal]
+//! url = "s3://my-bucket/repo"
+//! profile = "my-profile"
+//! targets = ["x86_64-unknown-linux-musl"] # (optional) overrides the defaults targets for this repo
+//!
+//! [repos.artifacts]
+//! url = "https://artifacts.example.com/binst"
+//! token = "..."               # (optional) bearer auth, or `user` and `password` for basic auth
+//! mkcol = true                # (optional) create the WebDAV collections on publish
+//! mirrors = ["internal", "/mnt/mirror"] # (optional) tried in order when the repo fails on install
+//! trust_mirrors = true        # (optional) accept the mirror package sha256 when the repo is down (unlocked installs)
+//!
+//! [repos.ssh-host]
+//! url = "sftp://deploy@artifacts.example.com/srv/binst"
+//! key_file = "~/.ssh/deploy_key" # (optional) otherwise the ssh agent or default keys
+//! known_hosts = "/etc/binst/known_hosts" # (optional) otherwise the ssh default ones
+//!
+//! [repos.gitea-tools]
+//! url = "github:tools/cool_cli"
+//! api_url = "https://gitea.example.com/api/v1" # (optional) GitHub Enterprise or Gitea api
+//!
+//! [repos.git-repo]
+//! url = "git+ssh://git@git.example.com/team/binst-repo.git"
+//! branch = "binst"            # (optional) otherwise `main` (or the url `#branch`)
+//! ```
+
+use crate::paths::binst_config;
+use std::convert::TryFrom;
+use std::fs::{read_to_string, rename, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use toml::{Table, Value};
+
+/// The config keys holding secrets, masked when printed (e.g., `repos.artifacts.token = "****"`)
+const SECRET_NAMES: &[&str] = &["token", "password"];
+const SECRET_MASK: &str = "****";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("Invalid config key '{0}'. Must be dot separated, e.g., 'defaults.stream' or 'repos.internal.url'")]
+	InvalidKey(String),
+
+	#[error("Config key '{0}' is not a table (cannot set '{1}' under it)")]
+	NotATable(String, String),
+
+	#[error("Config file {0} is not valid toml. Cause: {1}")]
+	InvalidConfigFile(String, String),
+
+	#[error(transparent)]
+	TomlSer(#[from] toml::ser::Error),
+
+	#[error(transparent)]
+	IO(#[from] std::io::Error),
+}
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// A named repo of the `[repos.<name>]` config
+#[derive(Debug, Default)]
+pub struct RepoConfig {
+	pub url: String,
+	pub profile: Option<String>,
+	pub targets: Option<Vec<String>>,
+	/// Http repo basic auth
+	pub user: Option<String>,
+	pub password: Option<String>,
+	/// Http repo bearer auth
+	pub token: Option<String>,
+	/// Http repo is WebDAV, and needs the collections (dirs) to be created (MKCOL) on publish
+	pub mkcol: bool,
+	/// Sftp repo ssh private key file (otherwise the ssh agent or default keys)
+	pub key_file: Option<String>,
+	/// Sftp repo known hosts file (otherwise the ssh default ones)
+	pub known_hosts: Option<String>,
+	/// Github repo api base url, for GitHub Enterprise or Gitea (otherwise `https://api.github.com`)
+	pub api_url: Option<String>,
+	/// Git repo branch (otherwise `main`)
+	pub branch: Option<String>,
+	/// Install repo mirrors (names or urls), tried in order when the repo fails
+	pub mirrors: Vec<String>,
+	/// Accept the mirror package toml sha256 when the repo is down (otherwise only the locked installs use the mirrors)
+	pub trust_mirrors: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+	table: Table,
+}
+
+/// Builders
+impl Config {
+	/// Load the config file (empty config if the file does not exist).
+	pub fn load() -> Result<Config> {
+		let path = binst_config()?;
+		if !path.is_file() {
+			return Ok(Config::default());
+		}
+		let content = read_to_string(&path)?;
+		let table = content
+			.parse::<Table>()
+			.map_err(|ex| Error::InvalidConfigFile(path.to_string_lossy().to_string(), ex.to_string()))?;
+		Ok(Config { table })
+	}
+}
+
+/// Getters
+impl Config {
+	/// Returns the `[repos.<name>]` repo if defined (and has a `url`).
+	pub fn repo(&self, name: &str) -> Option<RepoConfig> {
+		let repo = self.table.get("repos")?.get(name)?;
+		let url = repo.get("url")?.as_str()?.to_string();
+		let get_str = |name: &str| repo.get(name).and_then(|v| v.as_str()).map(|v| v.to_string());
+		Some(RepoConfig {
+			url,
+			profile: get_str("profile"),
+			targets: repo.get("targets").and_then(as_string_vec),
+			user: get_str("user"),
+			password: get_str("password"),
+			token: get_str("token"),
+			mkcol: repo.get("mkcol").and_then(|v| v.as_bool()).unwrap_or(false),
+			key_file: get_str("key_file"),
+			known_hosts: get_str("known_hosts"),
+			api_url: get_str("api_url"),
+			branch: get_str("branch"),
+			mirrors: repo.get("mirrors").and_then(as_string_vec).unwrap_or_default(),
+			trust_mirrors: repo.get("trust_mirrors").and_then(|v| v.as_bool()).unwrap_or(false),
+		})
+	}
+
+	/// Returns the first `[repos.<name>]` repo with the url (e.g., the recorded repo of an install).
+	pub fn repo_with_url(&self, url: &str) -> Option<RepoConfig> {
+		let repos = self.table.get("repos")?.as_table()?;
+		repos
+			.keys()
+			.filter_map(|name| self.repo(name))
+			.find(|repo| repo.url.trim_end_matches('/') == url.trim_end_matches('/'))
+	}
+
+	pub fn default_install_repo(&self) -> Option<String> {
+		self.default_str("install_repo")
+	}
+
+	pub fn default_publish_repo(&self) -> Option<String> {
+		self.default_str("publish_repo")
+	}
+
+	pub fn default_stream(&self) -> Option<String> {
+		self.default_str("stream")
+	}
+
+	/// Shim mode (`defaults.shims = true`), `bin/<bin>` are shims selecting the version per directory.
+	pub fn shims(&self) -> bool {
+		let shims = self.table.get("defaults").and_then(|d| d.get("shims"));
+		shims.and_then(|v| v.as_bool()).unwrap_or(false)
+	}
+
+	/// The ordered install targets of the repo (if named and defined), otherwise of the `[defaults]`.
+	pub fn targets(&self, repo_name: Option<&str>) -> Option<Vec<String>> {
+		repo_name
+			.and_then(|name| self.repo(name))
+			.and_then(|repo| repo.targets)
+			.or_else(|| self.table.get("defaults")?.get("targets").and_then(as_string_vec))
+	}
+
+	/// Returns the `[defaults]` positive number value (e.g., `defaults.retry_attempts`), ignoring the invalid ones.
+	pub fn default_u64(&self, name: &str) -> Option<u64> {
+		let value = self.table.get("defaults")?.get(name)?;
+		match value.as_integer().and_then(|v| u64::try_from(v).ok()) {
+			Some(v) => Some(v),
+			None => {
+				println!("WARNING - ignoring invalid defaults.{} value (must be a positive number)", name);
+				None
+			}
+		}
+	}
+
+	fn default_str(&self, name: &str) -> Option<String> {
+		self.table.get("defaults")?.get(name)?.as_str().map(|v| v.to_string())
+	}
+}
+
+/// Key based access (for the `binst config ...` command)
+impl Config {
+	/// Returns the value at the dot separated key (e.g., `repos.internal.url`).
+	pub fn get(&self, key: &str) -> Result<Option<&Value>> {
+		let names = split_key(key)?;
+		let mut value: Option<&Value> = None;
+		for (idx, name) in names.iter().enumerate() {
+			value = match idx {
+				0 => self.table.get(*name),
+				_ => value.and_then(|v| v.get(*name)),
+			};
+		}
+		Ok(value)
+	}
+
+	/// Set the value at the dot separated key, creating the intermediate tables as needed.
+	pub fn set(&mut self, key: &str, value: Value) -> Result<()> {
+		let names = split_key(key)?;
+		let (last, parents) = names.split_last().ok_or_else(|| Error::InvalidKey(key.to_string()))?;
+
+		let mut table = &mut self.table;
+		for (idx, name) in parents.iter().enumerate() {
+			let entry = table.entry(name.to_string()).or_insert_with(|| Value::Table(Table::new()));
+			table = match entry {
+				Value::Table(table) => table,
+				_ => return Err(Error::NotATable(names[..=idx].join("."), key.to_string())),
+			};
+		}
+		table.insert(last.to_string(), value);
+		Ok(())
+	}
+
+	/// Returns all the leaf values as `(dot_separated_key, value)`.
+	pub fn list(&self) -> Vec<(String, Value)> {
+		let mut entries = Vec::new();
+		collect_leaves("", &self.table, &mut entries);
+		entries
+	}
+
+	/// Save the config file (written to a temporary file, and then renamed in place).
+	/// Note: Readable by the user only (0600), as it can hold the repo secrets.
+	pub fn save(&self) -> Result<()> {
+		let path = binst_config()?;
+		let tmp_path = path.with_file_name(format!(".config.toml.tmp-{}", std::process::id()));
+		let mut file = OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&tmp_path)?;
+		file.write_all(toml::to_string(&self.table)?.as_bytes())?;
+		drop(file);
+		rename(&tmp_path, &path)?;
+		Ok(())
+	}
+}
+
+/// Returns the value to print for the key, with the secrets (e.g., `token`, `password`) masked, at any depth.
+pub fn masked_value(key: &str, value: &Value) -> Value {
+	let name = key.rsplit('.').next().unwrap_or_default();
+	match value {
+		_ if SECRET_NAMES.contains(&name) => Value::String(SECRET_MASK.to_string()),
+		Value::Table(table) => Value::Table(
+			table
+				.iter()
+				.map(|(name, value)| (name.to_string(), masked_value(name, value)))
+				.collect(),
+		),
+		_ => value.clone(),
+	}
+}
+
+/// Parse a command line config value, as toml if valid (e.g., `["a", "b"]`, `3`, `true`), otherwise as a string.
+pub fn parse_config_value(value: &str) -> Value {
+	match format!("v = {value}").parse::<Table>() {
+		Ok(mut table) => table.remove("v").unwrap_or_else(|| Value::String(value.to_string())),
+		Err(_) => Value::String(value.to_string()),
+	}
+}
+
+// region:    --- Config Private Utils
+fn split_key(key: &str) -> Result<Vec<&str>> {
+	let names: Vec<&str> = key.split('.').collect();
+	if names.iter().any(|n| n.trim().is_empty()) {
+		return Err(Error::InvalidKey(key.to_string()));
+	}
+	Ok(names)
+}
+
+fn as_string_vec(value: &Value) -> Option<Vec<String>> {
+	let values = value.as_array()?;
+	Some(values.iter().filter_map(|v| v.as_str()).map(|v| v.to_string()).collect())
+}
+
+fn collect_leaves(prefix: &str, table: &Table, entries: &mut Vec<(String, Value)>) {
+	for (name, value) in table {
+		let key = if prefix.is_empty() {
+			name.to_string()
+		} else {
+			format!("{prefix}.{name}")
+		};
+		match value {
+			Value::Table(sub_table) => collect_leaves(&key, sub_table, entries),
+			_ => entries.push((key, value.clone())),
+		}
+	}
+}
+// endregion: --- Config Private Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_config_set_get() -> Result<()> {
+		let mut config = Config::default();
+		config.set("repos.internal.url", parse_config_value("s3://my-bucket/repo"))?;
+		config.set("repos.internal.targets", parse_config_value(r#"["x86_64-unknown-linux-musl"]"#))?;
+		config.set("repos.internal.mirrors", parse_config_value(r#"["/mnt/mirror", "backup"]"#))?;
+		config.set("defaults.install_repo", parse_config_value("internal"))?;
+
+		let repo = config.repo("internal").unwrap();
+		assert_eq!("s3://my-bucket/repo", repo.url);
+		assert_eq!(None, repo.profile);
+		assert_eq!(vec!["/mnt/mirror", "backup"], repo.mirrors);
+		assert_eq!(Some(vec!["x86_64-unknown-linux-musl".to_string()]), config.targets(Some("internal")));
+		assert_eq!(None, config.targets(Some("other")));
+		assert_eq!("s3://my-bucket/repo", config.repo_with_url("s3://my-bucket/repo/").unwrap().url);
+		assert!(config.repo_with_url("s3://other-bucket/repo").is_none());
+		assert_eq!(Some("internal".to_string()), config.default_install_repo());
+		assert_eq!(Some("internal"), config.get("defaults.install_repo")?.and_then(|v| v.as_str()));
+		assert_eq!(4, config.list().len());
+
+		config.set("repos.internal.token", parse_config_value("secret"))?;
+		assert_eq!(Value::String(SECRET_MASK.to_string()), masked_value("repos.internal.token", &parse_config_value("secret")));
+		let masked_repo = masked_value("repos.internal", config.get("repos.internal")?.unwrap());
+		assert_eq!(Some(SECRET_MASK), masked_repo.get("token").and_then(|v| v.as_str()));
+		assert_eq!(Some("s3://my-bucket/repo"), masked_repo.get("url").and_then(|v| v.as_str()));
+
+		assert!(matches!(config.set("repos.internal.url.sub", Value::Boolean(true)), Err(Error::NotATable(..))));
+		assert!(matches!(config.get("repos..url"), Err(Error::InvalidKey(_))));
+		Ok(())
+	}
+}
@@ -8,9 +8,11 @@ use crate::cmd::cmd_exec;
 
 // -- Sub-Modules
 mod cmd;
+mod config;
 mod error;
 mod paths;
 mod prelude;
+mod project;
 mod repo;
 mod utils;
 
@@ -1,26 +1,81 @@
 // -- Imports
+use crate::prelude::*;
 use crate::repo::get_version_part;
 use dirs::home_dir;
 use platform_info::{PlatformInfo, Uname};
 use semver::Version;
-use std::{fs::create_dir_all, path::PathBuf};
+use std::env;
+use std::fs::{create_dir_all, read_dir, File};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 const BINST_DIR: &str = ".binst";
-// const BINST_CFG: &str = "config";
+const BINST_CFG: &str = "config.toml";
 const BINST_ENV: &str = "env";
+const BINST_HOME_ENV: &str = "BINST_HOME";
+const BINST_CACHE_DIR_ENV: &str = "BINST_CACHE_DIR";
+const SYSTEM_BINST_DIR: &str = "/opt/binst";
+const SYSTEM_BIN_DIR: &str = "/usr/local/bin";
+const BINST_TARGETS_ENV: &str = "BINST_TARGETS";
+const ROSETTA_RUNTIME: &str = "/Library/Apple/usr/libexec/oah/libRosettaRuntime";
 // const BINST_CRD: &str = "credentials";
 // const BINST_BIN_DIR: &str = "bin";
 // const BINST_PKG_DIR: &str = "packages";
 
-pub fn binst_dir() -> PathBuf {
-	let home_dir = home_dir().expect("No home dir");
-	home_dir.join(BINST_DIR)
+/// Alternate binst dir layout (`--root` or `--system`), set once at the start of the command.
+static BINST_ROOT: OnceLock<BinstRoot> = OnceLock::new();
+
+struct BinstRoot {
+	dir: PathBuf,
+	/// bin dir when not `<dir>/bin` (e.g., `/usr/local/bin` for `--system`)
+	bin_dir: Option<PathBuf>,
+}
+
+/// Set the alternate binst dir (e.g., `--root ./image-root` to prepare an image without touching `~/.binst`).
+/// Note: Made absolute, as it is used for symlinks and renames.
+pub fn set_binst_root(root: &Path) -> Result<(), std::io::Error> {
+	let dir = absolute_path(root)?;
+	let _ = BINST_ROOT.set(BinstRoot { dir, bin_dir: None });
+	Ok(())
+}
+
+/// Set the system wide binst dir (`/opt/binst`, with the bin symlinks in `/usr/local/bin`).
+pub fn set_binst_system() {
+	let _ = BINST_ROOT.set(BinstRoot {
+		dir: PathBuf::from(SYSTEM_BINST_DIR),
+		bin_dir: Some(PathBuf::from(SYSTEM_BIN_DIR)),
+	});
+}
+
+/// The binst dir, in order:
+/// - the `--root` dir (or `/opt/binst` for `--system`)
+/// - the `BINST_HOME` dir
+/// - `~/.binst`
+///
+/// Returns an error if none can be found (e.g., service account without home dir).
+pub fn binst_dir() -> Result<PathBuf, std::io::Error> {
+	if let Some(root) = BINST_ROOT.get() {
+		return Ok(root.dir.clone());
+	}
+	if let Ok(binst_home) = env::var(BINST_HOME_ENV) {
+		if !binst_home.is_empty() {
+			return absolute_path(Path::new(&binst_home));
+		}
+	}
+	match home_dir() {
+		Some(home_dir) => Ok(home_dir.join(BINST_DIR)),
+		None => Err(std::io::Error::new(
+			ErrorKind::NotFound,
+			f!("No home dir to create the {BINST_DIR} dir. Set {BINST_HOME_ENV} or use --root"),
+		)),
+	}
 }
 
 pub fn binst_tmp_dir(folder: Option<&str>) -> Result<PathBuf, std::io::Error> {
 	let tmp_path = match folder {
-		Some(folder) => binst_dir().join("tmp").join(folder),
-		None => binst_dir().join("tmp"),
+		Some(folder) => binst_dir()?.join("tmp").join(folder),
+		None => binst_dir()?.join("tmp"),
 	};
 
 	if !tmp_path.is_dir() {
@@ -29,43 +84,250 @@ pub fn binst_tmp_dir(folder: Option<&str>) -> Result<PathBuf, std::io::Error> {
 	Ok(tmp_path)
 }
 
+/// The `~/.binst/packages/<bin_name>` dir (not created)
+pub fn binst_package_dir(bin_name: &str) -> Result<PathBuf, std::io::Error> {
+	Ok(binst_dir()?.join("packages").join(bin_name))
+}
+
 pub fn binst_package_bin_dir(bin_name: &str, version: &Version) -> Result<PathBuf, std::io::Error> {
-	let path = binst_dir().join("packages").join(bin_name).join(get_version_part(version));
+	let path = binst_package_dir(bin_name)?.join(get_version_part(version));
 	if !path.is_dir() {
 		create_dir_all(&path)?;
 	}
 	Ok(path)
 }
 
-pub fn binst_env() -> PathBuf {
-	binst_dir().join(BINST_ENV)
+pub fn binst_env() -> Result<PathBuf, std::io::Error> {
+	Ok(binst_dir()?.join(BINST_ENV))
+}
+
+/// The `~/.binst/config.toml` global config file (see `config.rs`)
+pub fn binst_config() -> Result<PathBuf, std::io::Error> {
+	Ok(binst_dir()?.join(BINST_CFG))
+}
+
+/// The dir of the bin symlinks, `~/.binst/bin` (or `/usr/local/bin` for `--system`)
+pub fn binst_bin_dir() -> Result<PathBuf, std::io::Error> {
+	match BINST_ROOT.get().and_then(|root| root.bin_dir.as_ref()) {
+		Some(bin_dir) => Ok(bin_dir.clone()),
+		None => Ok(binst_dir()?.join("bin")),
+	}
+}
+
+/// The `~/.binst/run` dir of the package versions run without install (`binst run`)
+pub fn binst_run_dir() -> Result<PathBuf, std::io::Error> {
+	Ok(binst_dir()?.join("run"))
+}
+
+/// The `~/.binst/git` dir, the local git mirrors of the git repos (not created)
+pub fn binst_git_dir() -> Result<PathBuf, std::io::Error> {
+	Ok(binst_dir()?.join("git"))
 }
 
-// pub fn binst_config() -> PathBuf {
-// 	binst_dir().join(BINST_CFG)
-// }
+/// The package cache dir, `BINST_CACHE_DIR` if set (e.g., shared volume), otherwise `~/.binst/cache`
+pub fn binst_cache_dir() -> Result<PathBuf, std::io::Error> {
+	match env::var(BINST_CACHE_DIR_ENV) {
+		Ok(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+		_ => Ok(binst_dir()?.join("cache")),
+	}
+}
 
-pub fn binst_bin_dir() -> PathBuf {
-	binst_dir().join("bin")
+fn absolute_path(path: &Path) -> Result<PathBuf, std::io::Error> {
+	if path.is_absolute() {
+		Ok(path.to_path_buf())
+	} else {
+		Ok(env::current_dir()?.join(path))
+	}
 }
 
 pub fn os_target() -> String {
+	detect_target().target
+}
+
+/// Returns the ordered list of targets to try for install, starting with the given target.
+/// - `BINST_TARGETS` (comma separated) if set.
+/// - The `preferred` targets (from the config) if defined.
+/// - Linux gnu falls back to musl (static, runs everywhere).
+/// - aarch64 mac falls back to the legacy `arm64-apple-darwin` name, and to x86_64 if Rosetta is installed.
+pub fn target_fallbacks(target: &str, preferred: Option<Vec<String>>) -> Vec<String> {
+	if let Ok(targets) = env::var(BINST_TARGETS_ENV) {
+		let targets: Vec<String> = targets
+			.split(',')
+			.map(|t| t.trim().to_string())
+			.filter(|t| !t.is_empty())
+			.collect();
+		if !targets.is_empty() {
+			return targets;
+		}
+	}
+
+	if let Some(preferred) = preferred.filter(|targets| !targets.is_empty()) {
+		return preferred;
+	}
+
+	let has_rosetta = Path::new(ROSETTA_RUNTIME).exists();
+	compatible_targets(target, has_rosetta)
+}
+
+fn compatible_targets(target: &str, has_rosetta: bool) -> Vec<String> {
+	let mut targets = vec![target.to_string()];
+
+	if let Some(arch) = target.strip_suffix("-unknown-linux-gnu") {
+		targets.push(format!("{}-unknown-linux-musl", arch));
+	} else if let Some(arch) = target.strip_suffix("-unknown-linux-gnueabihf") {
+		targets.push(format!("{}-unknown-linux-musleabihf", arch));
+	} else if target == "aarch64-apple-darwin" {
+		targets.push("arm64-apple-darwin".to_string());
+		if has_rosetta {
+			targets.push("x86_64-apple-darwin".to_string());
+		}
+	}
+
+	targets
+}
+
+/// The detected os target, with the reasons of the detection (for `binst target`)
+pub struct TargetInfo {
+	pub target: String,
+	pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Libc {
+	Gnu,
+	Musl,
+}
+
+pub fn detect_target() -> TargetInfo {
 	let platform = PlatformInfo::new().unwrap();
 	let machine = platform.machine().to_string();
-	let sysname = platform.sysname().to_string().to_lowercase();
-	let mut target = String::new();
-	target.push_str(&machine);
+	let sysname = platform.sysname().to_string();
+	let mut reasons = vec![format!("machine '{}' and sysname '{}' (uname)", machine, sysname)];
+
+	let libc = if sysname.to_lowercase().contains("linux") {
+		let (libc, reason) = detect_linux_libc();
+		let libc_name = match libc {
+			Libc::Gnu => "glibc",
+			Libc::Musl => "musl",
+		};
+		reasons.push(format!("libc {}: {}", libc_name, reason));
+		Some(libc)
+	} else {
+		None
+	};
+
+	let target = target_triple(&machine, &sysname, libc);
+	TargetInfo { target, reasons }
+}
+
+/// Build the rust target triple from the uname machine and sysname.
+fn target_triple(machine: &str, sysname: &str, libc: Option<Libc>) -> String {
+	let sysname = sysname.to_lowercase();
+	let arch = match machine {
+		"arm64" => "aarch64",
+		"amd64" => "x86_64",
+		"armv7l" | "armv7" | "armv8l" => "armv7",
+		"armv6l" => "arm",
+		"i386" | "i586" | "i686" => "i686",
+		other => other,
+	};
+
 	if sysname.contains("darwin") {
-		target.push_str("-apple-darwin");
+		format!("{}-apple-darwin", arch)
 	} else if sysname.contains("linux") {
-		// FIXME: right now assumg gnu, but might not be safe
-		target.push_str("-unknown-linux-gnu");
+		let env = match libc.unwrap_or(Libc::Gnu) {
+			Libc::Gnu => "gnu",
+			Libc::Musl => "musl",
+		};
+		// 32 bits arm targets are hard float
+		let abi = match arch {
+			"armv7" | "arm" => "eabihf",
+			_ => "",
+		};
+		format!("{}-unknown-linux-{}{}", arch, env, abi)
 	} else {
 		// TODO: add support for Windows
-		target.push_str("-not-supported");
+		format!("{}-not-supported", arch)
+	}
+}
+
+/// Detect the linux libc, with the reason of the detection.
+fn detect_linux_libc() -> (Libc, String) {
+	// 1) the dynamic loader of the current executable, and then of the system shell
+	let exes = [std::env::current_exe().ok(), Some(PathBuf::from("/bin/sh"))];
+	for exe in exes.iter().flatten() {
+		if let Some(interp) = elf_interpreter(exe) {
+			let libc = if interp.contains("musl") { Libc::Musl } else { Libc::Gnu };
+			return (libc, format!("dynamic loader of {} is {}", exe.to_string_lossy(), interp));
+		}
+	}
+
+	// 2) static executables, look for the musl loader
+	if let Ok(entries) = read_dir("/lib") {
+		for entry in entries.flatten() {
+			let name = entry.file_name().to_string_lossy().to_string();
+			if name.starts_with("ld-musl-") {
+				return (Libc::Musl, format!("found /lib/{}", name));
+			}
+		}
 	}
 
-	target
+	(Libc::Gnu, "no dynamic loader nor /lib/ld-musl-* found, assuming glibc".to_string())
+}
+
+/// Returns the ELF program interpreter (dynamic loader) of the file, if any.
+fn elf_interpreter(path: &Path) -> Option<String> {
+	const PT_INTERP: u32 = 3;
+
+	let mut file = File::open(path).ok()?;
+	let mut ident = [0u8; 64];
+	file.read_exact(&mut ident).ok()?;
+	if &ident[0..4] != b"\x7fELF" {
+		return None;
+	}
+	let is_64 = ident[4] == 2;
+	let is_le = ident[5] == 1;
+
+	let read_uint = |bytes: &[u8]| -> u64 {
+		let bytes = bytes.iter().enumerate();
+		match is_le {
+			true => bytes.fold(0u64, |acc, (i, b)| acc | (*b as u64) << (8 * i)),
+			false => bytes.fold(0u64, |acc, (_, b)| acc << 8 | *b as u64),
+		}
+	};
+
+	// (e_phoff, e_phentsize, e_phnum) and (p_offset, p_filesz) positions per class
+	let (phoff, phentsize, phnum) = match is_64 {
+		true => (read_uint(&ident[0x20..0x28]), read_uint(&ident[0x36..0x38]), read_uint(&ident[0x38..0x3A])),
+		false => (read_uint(&ident[0x1C..0x20]), read_uint(&ident[0x2A..0x2C]), read_uint(&ident[0x2C..0x2E])),
+	};
+	let ((off_start, off_end), (size_start, size_end)) = match is_64 {
+		true => ((0x08, 0x10), (0x20, 0x28)),
+		false => ((0x04, 0x08), (0x10, 0x14)),
+	};
+
+	// Note: malformed ELF (program header entries too small for the read fields) is not an ELF to read
+	if phentsize < size_end as u64 {
+		return None;
+	}
+
+	let mut ph = vec![0u8; phentsize as usize];
+	for idx in 0..phnum {
+		let ph_start = idx.checked_mul(phentsize).and_then(|rel| rel.checked_add(phoff))?;
+		file.seek(SeekFrom::Start(ph_start)).ok()?;
+		file.read_exact(&mut ph).ok()?;
+		if read_uint(&ph[0..4]) as u32 == PT_INTERP {
+			let offset = read_uint(&ph[off_start..off_end]);
+			let size = read_uint(&ph[size_start..size_end]).min(4096);
+			let mut interp = vec![0u8; size as usize];
+			file.seek(SeekFrom::Start(offset)).ok()?;
+			file.read_exact(&mut interp).ok()?;
+			let interp = String::from_utf8_lossy(&interp);
+			return Some(interp.trim_end_matches('\0').to_string());
+		}
+	}
+
+	None
 }
 
 #[cfg(test)]
@@ -78,4 +340,60 @@ mod tests {
 		println!("current target {}", target);
 		Ok(())
 	}
+
+	#[test]
+	fn paths_target_triple() {
+		assert_eq!("x86_64-unknown-linux-gnu", target_triple("x86_64", "Linux", Some(Libc::Gnu)));
+		assert_eq!("x86_64-unknown-linux-musl", target_triple("x86_64", "Linux", Some(Libc::Musl)));
+		assert_eq!("aarch64-unknown-linux-gnu", target_triple("aarch64", "Linux", Some(Libc::Gnu)));
+		assert_eq!("aarch64-unknown-linux-musl", target_triple("arm64", "Linux", Some(Libc::Musl)));
+		assert_eq!("armv7-unknown-linux-gnueabihf", target_triple("armv7l", "Linux", Some(Libc::Gnu)));
+		assert_eq!("armv7-unknown-linux-musleabihf", target_triple("armv7l", "Linux", Some(Libc::Musl)));
+		assert_eq!("aarch64-apple-darwin", target_triple("arm64", "Darwin", None));
+		assert_eq!("x86_64-apple-darwin", target_triple("x86_64", "Darwin", None));
+	}
+
+	#[test]
+	fn paths_compatible_targets() {
+		assert_eq!(
+			vec!["x86_64-unknown-linux-gnu", "x86_64-unknown-linux-musl"],
+			compatible_targets("x86_64-unknown-linux-gnu", false)
+		);
+		assert_eq!(
+			vec!["armv7-unknown-linux-gnueabihf", "armv7-unknown-linux-musleabihf"],
+			compatible_targets("armv7-unknown-linux-gnueabihf", false)
+		);
+		assert_eq!(
+			vec!["aarch64-apple-darwin", "arm64-apple-darwin", "x86_64-apple-darwin"],
+			compatible_targets("aarch64-apple-darwin", true)
+		);
+		assert_eq!(vec!["aarch64-apple-darwin", "arm64-apple-darwin"], compatible_targets("aarch64-apple-darwin", false));
+		assert_eq!(vec!["x86_64-unknown-linux-musl"], compatible_targets("x86_64-unknown-linux-musl", false));
+	}
+
+	#[test]
+	fn paths_elf_interpreter() {
+		// Note: Only on linux, where /bin/sh is a dynamic ELF executable.
+		if cfg!(target_os = "linux") {
+			let interp = elf_interpreter(Path::new("/bin/sh"));
+			assert!(interp.map(|i| i.contains("ld-")).unwrap_or(true));
+		}
+		assert!(elf_interpreter(Path::new("Cargo.toml")).is_none());
+
+		// malformed ELF headers (too small program header entries, overflowing offset) are not read
+		let path = std::env::temp_dir().join(format!("binst-test-elf-{}", std::process::id()));
+		let mut header = vec![0u8; 64];
+		header[0..6].copy_from_slice(b"\x7fELF\x02\x01");
+		header[0x38] = 2; // e_phnum
+		for phentsize in [2u8, 0x27] {
+			header[0x36] = phentsize;
+			std::fs::write(&path, &header).unwrap();
+			assert!(elf_interpreter(&path).is_none());
+		}
+		header[0x20..0x28].copy_from_slice(&u64::MAX.to_le_bytes()); // e_phoff
+		header[0x36] = 0x38;
+		std::fs::write(&path, &header).unwrap();
+		assert!(elf_interpreter(&path).is_none());
+		std::fs::remove_file(&path).unwrap();
+	}
 }
@@ -0,0 +1,261 @@
+//! Project tool set, `binst.toml` manifest and its `binst.lock`
+//!
+//! ```toml
+//! [tools.cool_cli]
+//! repo = "internal"   # (optional) repo name or url, default from the config
+//! stream = "main"     # (optional) default from the config, otherwise main
+//! version = "^0.4"    # (optional) semver requirement, `=0.4.1` to pin a version (default the latest)
+//! ```
+
+use semver::{Op, Version, VersionReq};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{read_to_string, rename, write};
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+pub const MANIFEST_FILE: &str = "binst.toml";
+pub const LOCK_FILE: &str = "binst.lock";
+const LOCK_HEADER: &str = "# Generated by `binst sync`. Do not edit.\n";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("No {MANIFEST_FILE} found in {0} or its parent dirs")]
+	ManifestNotFound(String),
+
+	#[error("Invalid {0}. Cause: {1}")]
+	InvalidFile(String, String),
+
+	#[error("Invalid version requirement '{1}' for tool {0}. Cause: {2}")]
+	InvalidVersionReq(String, String, String),
+
+	#[error(transparent)]
+	TomlSer(#[from] toml::ser::Error),
+
+	#[error(transparent)]
+	IO(#[from] std::io::Error),
+}
+
+type Result<T> = core::result::Result<T, Error>;
+
+// region:    --- Manifest
+
+/// A `[tools.<name>]` of the `binst.toml`
+#[derive(Debug)]
+pub struct ToolSpec {
+	pub name: String,
+	pub repo: Option<String>,
+	pub profile: Option<String>,
+	pub stream: Option<String>,
+	pub version_req: VersionReq,
+}
+
+impl ToolSpec {
+	/// Returns the version if the requirement is an exact version (e.g., `=0.4.1`).
+	pub fn pinned_version(&self) -> Option<Version> {
+		match self.version_req.comparators.as_slice() {
+			[comp] if comp.op == Op::Exact => Some(Version {
+				major: comp.major,
+				minor: comp.minor?,
+				patch: comp.patch?,
+				pre: comp.pre.clone(),
+				build: Default::default(),
+			}),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Manifest {
+	/// The project dir (where the `binst.toml` and `binst.lock` are)
+	pub dir: PathBuf,
+	/// Sorted by name
+	pub tools: Vec<ToolSpec>,
+}
+
+/// Builders
+impl Manifest {
+	/// Load the nearest `binst.toml` from the current dir (or its parent dirs).
+	pub fn find_from_current_dir() -> Result<Manifest> {
+		let current_dir = env::current_dir()?;
+		let path = find_manifest_path(&current_dir)
+			.ok_or_else(|| Error::ManifestNotFound(current_dir.to_string_lossy().to_string()))?;
+		Manifest::load(&path)
+	}
+
+	pub fn load(path: &Path) -> Result<Manifest> {
+		let table = read_table(path)?;
+		let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+
+		let mut tools = Vec::new();
+		if let Some(tools_table) = table.get("tools").and_then(|v| v.as_table()) {
+			for (name, tool) in tools_table {
+				let req = get_str(tool, "version").unwrap_or_else(|| "*".to_string());
+				let version_req = VersionReq::parse(&req)
+					.map_err(|ex| Error::InvalidVersionReq(name.to_string(), req.to_string(), ex.to_string()))?;
+				tools.push(ToolSpec {
+					name: name.to_string(),
+					repo: get_str(tool, "repo"),
+					profile: get_str(tool, "profile"),
+					stream: get_str(tool, "stream"),
+					version_req,
+				});
+			}
+		}
+
+		Ok(Manifest { dir, tools })
+	}
+}
+
+/// Returns the nearest `binst.toml` path, from the `dir` up to the root.
+pub fn find_manifest_path(dir: &Path) -> Option<PathBuf> {
+	dir.ancestors().map(|dir| dir.join(MANIFEST_FILE)).find(|path| path.is_file())
+}
+
+// endregion: --- Manifest
+
+// region:    --- Lock File
+
+/// A `[tools.<name>]` of the `binst.lock`, with the package `sha256` of each target installed
+/// (`[tools.<name>.targets.<target>]`, e.g., for a team on linux and macos).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedTool {
+	pub version: Version,
+	pub repo: String,
+	pub stream: String,
+	/// target -> package sha256
+	pub targets: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LockFile {
+	pub tools: BTreeMap<String, LockedTool>,
+}
+
+impl LockFile {
+	/// Load the `binst.lock` of the project dir (empty if it does not exist).
+	pub fn load(dir: &Path) -> Result<LockFile> {
+		let path = dir.join(LOCK_FILE);
+		if !path.is_file() {
+			return Ok(LockFile::default());
+		}
+
+		let table = read_table(&path)?;
+		let mut tools = BTreeMap::new();
+		if let Some(tools_table) = table.get("tools").and_then(|v| v.as_table()) {
+			for (name, tool) in tools_table {
+				let invalid = || Error::InvalidFile(path.to_string_lossy().to_string(), format!("invalid tools.{name}"));
+				let version = get_str(tool, "version").and_then(|v| Version::parse(&v).ok()).ok_or_else(invalid)?;
+				let mut targets = BTreeMap::new();
+				for (target, entry) in tool.get("targets").and_then(|v| v.as_table()).ok_or_else(invalid)? {
+					targets.insert(target.to_string(), get_str(entry, "sha256").ok_or_else(invalid)?);
+				}
+				let locked = LockedTool {
+					version,
+					repo: get_str(tool, "repo").ok_or_else(invalid)?,
+					stream: get_str(tool, "stream").ok_or_else(invalid)?,
+					targets,
+				};
+				tools.insert(name.to_string(), locked);
+			}
+		}
+
+		Ok(LockFile { tools })
+	}
+
+	/// Save the `binst.lock` in the project dir (written to a temporary file, and then renamed in place).
+	pub fn save(&self, dir: &Path) -> Result<()> {
+		let mut tools = Table::new();
+		for (name, locked) in &self.tools {
+			let mut tool = Table::new();
+			tool.insert("version".to_string(), Value::String(locked.version.to_string()));
+			tool.insert("repo".to_string(), Value::String(locked.repo.to_string()));
+			tool.insert("stream".to_string(), Value::String(locked.stream.to_string()));
+			let mut targets = Table::new();
+			for (target, sha256) in &locked.targets {
+				let mut entry = Table::new();
+				entry.insert("sha256".to_string(), Value::String(sha256.to_string()));
+				targets.insert(target.to_string(), Value::Table(entry));
+			}
+			tool.insert("targets".to_string(), Value::Table(targets));
+			tools.insert(name.to_string(), Value::Table(tool));
+		}
+		let mut root = Table::new();
+		root.insert("tools".to_string(), Value::Table(tools));
+
+		let content = format!("{LOCK_HEADER}{}", toml::to_string(&root)?);
+		let tmp_path = dir.join(format!(".{LOCK_FILE}.tmp-{}", std::process::id()));
+		write(&tmp_path, content)?;
+		rename(&tmp_path, dir.join(LOCK_FILE))?;
+		Ok(())
+	}
+}
+
+// endregion: --- Lock File
+
+// region:    --- Project Private Utils
+fn read_table(path: &Path) -> Result<Table> {
+	let content = read_to_string(path)?;
+	content
+		.parse::<Table>()
+		.map_err(|ex| Error::InvalidFile(path.to_string_lossy().to_string(), ex.to_string()))
+}
+
+fn get_str(value: &Value, name: &str) -> Option<String> {
+	value.get(name)?.as_str().map(|v| v.to_string())
+}
+// endregion: --- Project Private Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::{create_dir_all, remove_dir_all};
+
+	#[test]
+	fn test_project_manifest_and_lock() -> Result<()> {
+		let dir = env::temp_dir().join(format!("binst-test-project-{}", std::process::id()));
+		let _ = remove_dir_all(&dir);
+		create_dir_all(dir.join("sub/dir"))?;
+		write(
+			dir.join(MANIFEST_FILE),
+			r#"
+[tools.cool_cli]
+repo = "internal"
+version = "=0.4.0-rc.1"
+
+[tools.other_cli]
+version = "^1.2"
+"#,
+		)?;
+
+		// nearest manifest from a sub dir
+		let path = find_manifest_path(&dir.join("sub/dir")).unwrap();
+		let manifest = Manifest::load(&path)?;
+		assert_eq!(dir, manifest.dir);
+		assert_eq!(2, manifest.tools.len());
+		assert_eq!(Some("internal".to_string()), manifest.tools[0].repo);
+		assert_eq!(Some(Version::parse("0.4.0-rc.1").unwrap()), manifest.tools[0].pinned_version());
+		assert_eq!(None, manifest.tools[1].pinned_version());
+
+		// lock round trip
+		let mut lock = LockFile::default();
+		lock.tools.insert(
+			"cool_cli".to_string(),
+			LockedTool {
+				version: Version::parse("0.4.0-rc.1").unwrap(),
+				repo: "s3://bucket/repo".to_string(),
+				stream: "rc".to_string(),
+				targets: BTreeMap::from([
+					("x86_64-unknown-linux-musl".to_string(), "abc".to_string()),
+					("aarch64-apple-darwin".to_string(), "def".to_string()),
+				]),
+			},
+		);
+		lock.save(&dir)?;
+		assert_eq!(lock, LockFile::load(&dir)?);
+
+		remove_dir_all(&dir)?;
+		Ok(())
+	}
+}
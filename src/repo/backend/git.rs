@@ -0,0 +1,399 @@
+//! Git repo backend, with the `git` command
+//!
+//! The repo files are in the branch tree (e.g., `cool_cli/x86_64-unknown-linux-gnu/main/latest.toml`),
+//! read and written from a local bare mirror (`~/.binst/git/<remote hash>`), without checkout:
+//! - Reads fetch the branch once per process (shallow, and without the blobs when the server supports the filter),
+//!   and the blobs of the read files are fetched on demand.
+//! - Puts and deletes are pending until `flush`, which commits them on top of the branch and pushes
+//!   (fetched again and retried if the push is rejected, e.g., concurrent publish).
+
+use super::RepoBackend;
+use crate::paths::binst_git_dir;
+use crate::prelude::*;
+use crate::repo::{Error, GitInfo, Result};
+use crate::utils::{is_verbose, sha256_str};
+use async_trait::async_trait;
+use std::fs::{create_dir_all, remove_file, File};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+const REMOTE: &str = "origin";
+const PUSH_ATTEMPTS: u32 = 3;
+
+/// The mirror branches already fetched by this process (`<git dir>#<branch>`)
+static FETCHED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub struct GitBackend {
+	git_info: GitInfo,
+	/// The pending puts (key, blob id) and deletes (key, None), committed and pushed by `flush`
+	pending: Mutex<Vec<(String, Option<String>)>>,
+}
+
+impl GitBackend {
+	pub fn new(git_info: &GitInfo) -> Self {
+		GitBackend {
+			git_info: git_info.clone(),
+			pending: Mutex::new(Vec::new()),
+		}
+	}
+
+	fn git_dir(&self) -> Result<PathBuf> {
+		Ok(binst_git_dir()?.join(&sha256_str(&self.git_info.remote)[..16]))
+	}
+
+	fn remote_ref(&self) -> String {
+		f!("refs/remotes/{REMOTE}/{}", self.git_info.branch)
+	}
+
+	/// Run the git command in the mirror, and returns the stdout (as is).
+	/// The stdout goes to the `stdout_file` if defined (e.g., blob download).
+	async fn run_git(&self, args: &[&str], stdin: Option<&str>, envs: &[(&str, &str)], stdout_file: Option<File>) -> Result<String> {
+		let url = &self.git_info.url;
+		let mut cmd = Command::new("git");
+		cmd.arg("--git-dir").arg(self.git_dir()?).args(args).envs(envs.iter().copied());
+		// Note: no credential prompt (the ssh agent, key, or credential helper must be set)
+		cmd.env("GIT_TERMINAL_PROMPT", "0");
+		cmd.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+		cmd.stdout(match stdout_file {
+			Some(file) => Stdio::from(file),
+			None => Stdio::piped(),
+		});
+		cmd.stderr(Stdio::piped());
+
+		if is_verbose() {
+			println!("  git {}", args.join(" "));
+		}
+
+		let mut child = cmd.spawn().map_err(|ex| Error::GitFailed(url.to_string(), ex.to_string()))?;
+		if let (Some(content), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+			child_stdin.write_all(content.as_bytes()).await?;
+		}
+		let output = child.wait_with_output().await?;
+
+		if !output.status.success() {
+			let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+			return Err(Error::GitFailed(url.to_string(), stderr));
+		}
+		Ok(String::from_utf8_lossy(&output.stdout).to_string())
+	}
+
+	/// Run the git command in the mirror, and returns the trimmed stdout (e.g., object id).
+	async fn git(&self, args: &[&str]) -> Result<String> {
+		Ok(self.run_git(args, None, &[], None).await?.trim().to_string())
+	}
+
+	/// Create the local bare mirror if needed, as a partial clone of the remote (blobs fetched on demand).
+	async fn init_mirror(&self) -> Result<()> {
+		let git_dir = self.git_dir()?;
+		if !git_dir.join("HEAD").is_file() {
+			create_dir_all(&git_dir)?;
+			self.git(&["init", "-q", "--bare"]).await?;
+			self.git(&["config", "core.repositoryformatversion", "1"]).await?;
+			self.git(&["config", "extensions.partialClone", REMOTE]).await?;
+			self.git(&["config", &f!("remote.{REMOTE}.promisor"), "true"]).await?;
+			self.git(&["config", &f!("remote.{REMOTE}.partialclonefilter"), "blob:none"]).await?;
+		}
+		// Note: the remote url of a mirror is always the same (hash of the url), set for the eventual moved remote
+		self.git(&["config", &f!("remote.{REMOTE}.url"), &self.git_info.remote]).await?;
+		Ok(())
+	}
+
+	/// Returns the branch commit, or None if the branch does not exist yet (e.g., first publish).
+	/// The branch is fetched once per process, or again if `refetch`.
+	async fn branch_commit(&self, refetch: bool) -> Result<Option<String>> {
+		self.init_mirror().await?;
+		let remote_ref = self.remote_ref();
+		let fetched_key = f!("{}#{}", self.git_dir()?.to_string_lossy(), self.git_info.branch);
+
+		if refetch || !FETCHED.lock().unwrap().contains(&fetched_key) {
+			let refspec = f!("+refs/heads/{}:{remote_ref}", self.git_info.branch);
+			let args = ["fetch", "-q", "--depth", "1", "--filter=blob:none", REMOTE, &refspec];
+			match self.git(&args).await {
+				Ok(_) => (),
+				Err(Error::GitFailed(_, cause)) if cause.contains("couldn't find remote ref") => {
+					let _ = self.git(&["update-ref", "-d", &remote_ref]).await;
+				}
+				Err(ex) => return Err(ex),
+			}
+			FETCHED.lock().unwrap().push(fetched_key);
+		}
+
+		// Note: fails (no output) when the ref does not exist
+		let commit = self.git(&["rev-parse", "-q", "--verify", &f!("{remote_ref}^{{commit}}")]).await.ok();
+		Ok(commit.filter(|c| !c.is_empty()))
+	}
+
+	/// Returns the blob id of the key (pending put, or in the branch), or None if not found (or pending delete).
+	async fn blob_id(&self, key: &str) -> Result<Option<String>> {
+		if let Some((_, blob_id)) = self.pending.lock().unwrap().iter().rev().find(|(k, _)| k == key) {
+			return Ok(blob_id.clone());
+		}
+
+		let Some(commit) = self.branch_commit(false).await? else {
+			return Ok(None);
+		};
+		// e.g., `100644 blob <id>\t<key>` (only the trees are needed, not the blob)
+		let line = self.git(&["ls-tree", &commit, "--", key]).await?;
+		let blob_id = line.split_whitespace().nth(2).filter(|_| line.contains(" blob "));
+		Ok(blob_id.map(|id| id.to_string()))
+	}
+
+	fn add_pending(&self, key: &str, blob_id: Option<String>) {
+		let mut pending = self.pending.lock().unwrap();
+		pending.retain(|(k, _)| k != key);
+		pending.push((key.to_string(), blob_id));
+	}
+
+	/// Commit the pending changes on top of the parent (new root commit if None), and returns the commit id.
+	/// Note: Returns the parent if nothing changed (e.g., same package published again).
+	async fn commit(&self, parent: Option<&str>, pending: &[(String, Option<String>)]) -> Result<String> {
+		let index_file = self.git_dir()?.join(f!("binst-index-{}", std::process::id()));
+		let index_file_env = index_file.to_string_lossy().to_string();
+		let index_env = [("GIT_INDEX_FILE", index_file_env.as_str())];
+
+		let res: Result<String> = async {
+			match parent {
+				Some(parent) => self.run_git(&["read-tree", parent], None, &index_env, None).await?,
+				None => self.run_git(&["read-tree", "--empty"], None, &index_env, None).await?,
+			};
+			// Note: mode 0 removes the path from the index
+			let index_info: Vec<String> = pending
+				.iter()
+				.map(|(key, blob_id)| match blob_id {
+					Some(blob_id) => f!("100644 {blob_id}\t{key}"),
+					None => f!("0 {}\t{key}", "0".repeat(40)),
+				})
+				.collect();
+			let index_info = f!("{}\n", index_info.join("\n"));
+			self.run_git(&["update-index", "--index-info"], Some(&index_info), &index_env, None).await?;
+			Ok(self.run_git(&["write-tree"], None, &index_env, None).await?.trim().to_string())
+		}
+		.await;
+		let _ = remove_file(&index_file);
+		let tree = res?;
+
+		if let Some(parent) = parent {
+			if self.git(&["rev-parse", &f!("{parent}^{{tree}}")]).await? == tree {
+				return Ok(parent.to_string());
+			}
+		}
+
+		let message = commit_message(pending);
+		let mut args = vec!["commit-tree", &tree, "-m", &message];
+		if let Some(parent) = parent {
+			args.extend(["-p", parent]);
+		}
+		// Note: the user git identity if set, otherwise binst
+		let envs: &[(&str, &str)] = match self.git(&["config", "user.email"]).await {
+			Ok(_) => &[],
+			Err(_) => &[
+				("GIT_AUTHOR_NAME", "binst"),
+				("GIT_AUTHOR_EMAIL", "binst@localhost"),
+				("GIT_COMMITTER_NAME", "binst"),
+				("GIT_COMMITTER_EMAIL", "binst@localhost"),
+			],
+		};
+		Ok(self.run_git(&args, None, envs, None).await?.trim().to_string())
+	}
+}
+
+#[async_trait]
+impl RepoBackend for GitBackend {
+	fn key_url(&self, key: &str) -> String {
+		let (base, branch) = (f!("git+{}", self.git_info.remote), &self.git_info.branch);
+		f!("{base}/{key}#{branch}")
+	}
+
+	async fn get_text(&self, key: &str) -> Result<Option<String>> {
+		match self.blob_id(key).await? {
+			Some(blob_id) => Ok(Some(self.run_git(&["cat-file", "blob", &blob_id], None, &[], None).await?)),
+			None => Ok(None),
+		}
+	}
+
+	async fn get_file(&self, key: &str, dest: &Path) -> Result<()> {
+		let blob_id = self.blob_id(key).await?.ok_or_else(|| Error::RepoKeyNotFound(self.key_url(key)))?;
+		// Note: the blob is fetched from the remote if not in the mirror (partial clone)
+		self.run_git(&["cat-file", "blob", &blob_id], None, &[], Some(File::create(dest)?)).await?;
+		Ok(())
+	}
+
+	async fn put_file(&self, key: &str, file: &Path) -> Result<()> {
+		self.init_mirror().await?;
+		let blob_id = self.git(&["hash-object", "-w", "--", &file.to_string_lossy()]).await?;
+		self.add_pending(key, Some(blob_id));
+		Ok(())
+	}
+
+	async fn put_text(&self, key: &str, content: &str) -> Result<()> {
+		self.init_mirror().await?;
+		let blob_id = self.run_git(&["hash-object", "-w", "--stdin"], Some(content), &[], None).await?;
+		self.add_pending(key, Some(blob_id.trim().to_string()));
+		Ok(())
+	}
+
+	async fn exists(&self, key: &str) -> Result<bool> {
+		Ok(self.blob_id(key).await?.is_some())
+	}
+
+	async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+		let mut keys = Vec::new();
+		if let Some(commit) = self.branch_commit(false).await? {
+			let mut args = vec!["ls-tree", "-r", "--name-only", &commit];
+			// Note: only the trees of the prefix dir
+			let prefix_dir = prefix.rsplit_once('/').map(|(dir, _)| dir);
+			if let Some(prefix_dir) = prefix_dir {
+				args.extend(["--", prefix_dir]);
+			}
+			let content = self.git(&args).await?;
+			keys.extend(content.lines().filter(|key| key.starts_with(prefix)).map(|key| key.to_string()));
+		}
+
+		for (key, blob_id) in self.pending.lock().unwrap().iter().filter(|(key, _)| key.starts_with(prefix)) {
+			keys.retain(|k| k != key);
+			if blob_id.is_some() {
+				keys.push(key.to_string());
+			}
+		}
+		keys.sort();
+		Ok(keys)
+	}
+
+	/// Note: Same blob, nothing is downloaded.
+	async fn copy(&self, key: &str, to_key: &str) -> Result<()> {
+		let blob_id = self.blob_id(key).await?.ok_or_else(|| Error::RepoKeyNotFound(self.key_url(key)))?;
+		self.add_pending(to_key, Some(blob_id));
+		Ok(())
+	}
+
+	async fn delete(&self, key: &str) -> Result<()> {
+		if self.blob_id(key).await?.is_some() {
+			self.add_pending(key, None);
+		}
+		Ok(())
+	}
+
+	/// Commit the pending changes on the branch, and push.
+	async fn flush(&self) -> Result<()> {
+		let pending = self.pending.lock().unwrap().clone();
+		if pending.is_empty() {
+			return Ok(());
+		}
+
+		let mut attempt = 1;
+		loop {
+			// Note: on retry, from the new remote branch (e.g., concurrent publish)
+			let parent = self.branch_commit(attempt > 1).await?;
+			let commit = self.commit(parent.as_deref(), &pending).await?;
+			let refspec = f!("{commit}:refs/heads/{}", self.git_info.branch);
+			match self.git(&["push", "-q", REMOTE, &refspec]).await {
+				Ok(_) => {
+					self.git(&["update-ref", &self.remote_ref(), &commit]).await?;
+					self.pending.lock().unwrap().clear();
+					return Ok(());
+				}
+				Err(Error::GitFailed(_, cause)) if is_push_rejected(&cause) && attempt < PUSH_ATTEMPTS => {
+					if is_verbose() {
+						println!("  git push rejected, retry {attempt}/{} - {cause}", PUSH_ATTEMPTS - 1);
+					}
+					attempt += 1;
+				}
+				Err(ex) => return Err(ex),
+			}
+		}
+	}
+}
+
+// region:    --- Git Utils
+fn commit_message(pending: &[(String, Option<String>)]) -> String {
+	let lines: Vec<String> = pending
+		.iter()
+		.map(|(key, blob_id)| match blob_id {
+			Some(_) => f!("+ {key}"),
+			None => f!("- {key}"),
+		})
+		.collect();
+	f!("binst: update {} file(s)\n\n{}", pending.len(), lines.join("\n"))
+}
+
+/// The push was rejected because the remote branch changed (not a fast-forward).
+fn is_push_rejected(cause: &str) -> bool {
+	cause.contains("[rejected]") || cause.contains("non-fast-forward") || cause.contains("fetch first")
+}
+// endregion: --- Git Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn git_cmd(dir: &Path, args: &[&str]) -> String {
+		let output = std::process::Command::new("git").arg("-C").arg(dir).args(args).output().unwrap();
+		assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+		String::from_utf8_lossy(&output.stdout).to_string()
+	}
+
+	#[tokio::test]
+	async fn test_git_backend_publish() -> Result<()> {
+		let test_dir = super::super::tests::set_test_binst_root("git-backend")?;
+		let remote_dir = test_dir.join("remote.git");
+		create_dir_all(&remote_dir)?;
+		git_cmd(&remote_dir, &["init", "-q", "--bare"]);
+		git_cmd(&remote_dir, &["config", "uploadpack.allowFilter", "true"]);
+		let git_info = GitInfo::from_git_url(&format!("git+file://{}#binst", remote_dir.to_string_lossy()), None)?;
+
+		// -- first publish (new branch), nothing pushed before the flush
+		let backend = GitBackend::new(&git_info);
+		let gz_file = test_dir.join("cool_cli.tar.gz");
+		std::fs::write(&gz_file, "gz content")?;
+		assert!(!backend.exists("cool_cli/x86/main/0.1.0/cool_cli.tar.gz").await?);
+		backend.put_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &gz_file).await?;
+		backend.put_text("cool_cli/x86/main/latest.toml", "[latest]\nversion = \"0.1.0\"\n").await?;
+		assert!(backend.exists("cool_cli/x86/main/latest.toml").await?);
+		assert!(git_cmd(&remote_dir, &["branch", "--list"]).is_empty());
+		backend.flush().await?;
+		let remote_files = git_cmd(&remote_dir, &["ls-tree", "-r", "--name-only", "binst"]);
+		assert_eq!("cool_cli/x86/main/0.1.0/cool_cli.tar.gz\ncool_cli/x86/main/latest.toml\n", remote_files);
+
+		// -- install reads (other backend instance, the blobs fetched on demand)
+		let backend = GitBackend::new(&git_info);
+		let latest = backend.get_text("cool_cli/x86/main/latest.toml").await?;
+		assert_eq!(Some("[latest]\nversion = \"0.1.0\"\n".to_string()), latest);
+		let dest = test_dir.join("downloaded.tar.gz");
+		backend.get_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &dest).await?;
+		assert_eq!("gz content", std::fs::read_to_string(&dest)?);
+		assert_eq!(None, backend.get_text("cool_cli/x86/main/nope.toml").await?);
+		let res = backend.get_file("cool_cli/x86/main/nope.tar.gz", &dest).await;
+		assert!(matches!(res, Err(Error::RepoKeyNotFound(_))));
+
+		// -- concurrent publish (pushed from a clone after the fetch), the push is retried on the new branch
+		let work_dir = test_dir.join("work");
+		git_cmd(&test_dir, &["clone", "-q", "-b", "binst", &remote_dir.to_string_lossy(), "work"]);
+		std::fs::write(work_dir.join("other.toml"), "other")?;
+		git_cmd(&work_dir, &["add", "-A"]);
+		git_cmd(&work_dir, &["-c", "user.name=test", "-c", "user.email=test@test", "commit", "-q", "-m", "other"]);
+		git_cmd(&work_dir, &["push", "-q", "origin", "binst"]);
+
+		backend.copy("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", "cool_cli/x86/main/0.1.1/cool_cli.tar.gz").await?;
+		backend.delete("cool_cli/x86/main/0.1.0/cool_cli.tar.gz").await?;
+		backend.delete("cool_cli/x86/main/0.0.1/cool_cli.tar.gz").await?;
+		let expected_keys = vec!["cool_cli/x86/main/0.1.1/cool_cli.tar.gz", "cool_cli/x86/main/latest.toml"];
+		assert_eq!(expected_keys, backend.list("cool_cli/x86/").await?);
+		backend.flush().await?;
+		let remote_files = git_cmd(&remote_dir, &["ls-tree", "-r", "--name-only", "binst"]);
+		assert_eq!("cool_cli/x86/main/0.1.1/cool_cli.tar.gz\ncool_cli/x86/main/latest.toml\nother.toml\n", remote_files);
+		assert_eq!(expected_keys, GitBackend::new(&git_info).list("cool_cli/").await?);
+
+		// -- same content, no new commit
+		let commit_count = git_cmd(&remote_dir, &["rev-list", "--count", "binst"]);
+		backend.put_text("cool_cli/x86/main/latest.toml", "[latest]\nversion = \"0.1.0\"\n").await?;
+		backend.flush().await?;
+		assert_eq!(commit_count, git_cmd(&remote_dir, &["rev-list", "--count", "binst"]));
+
+		std::fs::remove_dir_all(&test_dir)?;
+		std::fs::remove_dir_all(binst_git_dir()?)?;
+		Ok(())
+	}
+}
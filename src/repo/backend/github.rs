@@ -0,0 +1,631 @@
+//! GitHub (or Gitea) releases repo backend, install only
+//!
+//! The binst keys are resolved from the releases of the `owner/repo`:
+//! - `<bin>/<target>/<stream>/latest.toml` is the highest release version of the stream (tag `v1.2.3` or `1.2.3`),
+//!   if it has an asset for the target (so that the next target is tried otherwise).
+//! - `<bin>/<target>/<stream>/<version>/<bin>.tar.gz` is the release asset matching the target (by name patterns),
+//!   repacked as a binst package (the `<bin>` file at the root).
+//! - `<bin>/<target>/<stream>/<version>/<bin>.toml` does not exist (the asset `digest`, if any, is verified on download).
+
+use super::http::check_http_status;
+use super::RepoBackend;
+use crate::prelude::*;
+use crate::repo::retry::RetryPolicy;
+use crate::repo::unpack::unpack_tar_gz;
+use crate::repo::{extract_stream, make_bin_temp_dir, Error, GithubInfo, HttpAuth, Result, LATEST_TOML, MAIN_STREAM};
+use crate::utils::sha256_file;
+use async_trait::async_trait;
+use libflate::gzip::{EncodeOptions, Encoder, HeaderBuilder};
+use reqwest::header::{ACCEPT, USER_AGENT};
+use reqwest::{Client, RequestBuilder};
+use semver::Version;
+use serde_json::Value;
+use std::fs::{read_dir, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tar::{Builder, Header};
+
+const API_MEDIA_TYPE: &str = "application/vnd.github+json";
+
+pub struct GithubBackend {
+	github_info: GithubInfo,
+	client: Client,
+	retry: RetryPolicy,
+}
+
+/// The binst key parts, `<bin>/<target>/<stream>/latest.toml` or `<bin>/<target>/<stream>/<version>/<file>`
+struct GithubKey<'a> {
+	bin: &'a str,
+	target: &'a str,
+	stream: &'a str,
+	/// None for the `latest.toml`
+	version: Option<Version>,
+	file: &'a str,
+}
+
+#[derive(Debug, PartialEq)]
+enum AssetKind {
+	TarGz,
+	/// The binary itself (e.g., `cool_cli-linux-amd64`)
+	Bin,
+}
+
+impl GithubBackend {
+	pub fn new(github_info: &GithubInfo) -> Self {
+		GithubBackend {
+			github_info: github_info.clone(),
+			client: Client::new(),
+			retry: RetryPolicy::load(),
+		}
+	}
+
+	fn request(&self, url: &str) -> RequestBuilder {
+		// Note: the GitHub api rejects the requests without user agent
+		let req = self.client.get(url).header(USER_AGENT, "binst");
+		match &self.github_info.auth {
+			Some(HttpAuth::Basic(user, password)) => req.basic_auth(user, password.as_ref()),
+			Some(HttpAuth::Bearer(token)) => req.bearer_auth(token),
+			None => req,
+		}
+	}
+
+	/// GET the api json, or None if not found.
+	async fn get_json(&self, path: &str) -> Result<Option<Value>> {
+		let url = f!("{}/repos/{}/{path}", self.github_info.api_url, self.github_info.owner_repo);
+		self.retry
+			.run(&f!("GET {url}"), || async {
+				let resp = self.request(&url).header(ACCEPT, API_MEDIA_TYPE).send().await?;
+				match check_http_status(resp, &url, Error::RepoKeyNotFound) {
+					Ok(resp) => Ok(Some(resp.json::<Value>().await?)),
+					Err(Error::RepoKeyNotFound(_)) => Ok(None),
+					Err(ex) => Err(ex),
+				}
+			})
+			.await
+	}
+
+	/// Returns the highest release version of the stream, with its release.
+	/// Note: The drafts are skipped, and the `main` stream skips the releases flagged as pre-release.
+	async fn latest_release(&self, stream: &str) -> Result<Option<(Version, Value)>> {
+		// Note: the most recent releases first, so the first page is enough for the latest
+		let releases = self.get_json("releases?per_page=100").await?.unwrap_or_default();
+		let releases = releases.as_array().cloned().unwrap_or_default();
+
+		let latest = releases
+			.into_iter()
+			.filter(|r| !(is_true(r, "draft") || stream == MAIN_STREAM && is_true(r, "prerelease")))
+			.filter_map(|r| tag_version(&r).map(|v| (v, r)))
+			.filter(|(version, _)| extract_stream(version) == stream)
+			.max_by(|(a, _), (b, _)| a.cmp(b));
+		Ok(latest)
+	}
+
+	/// Returns the release of the version (tag `v<version>` or `<version>`).
+	async fn release(&self, version: &Version) -> Result<Option<Value>> {
+		for tag in [f!("v{version}"), version.to_string()] {
+			if let Some(release) = self.get_json(&f!("releases/tags/{tag}")).await? {
+				return Ok(Some(release));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Download the url to the dest file (with retries).
+	async fn download(&self, url: &str, dest: &Path) -> Result<()> {
+		self.retry
+			.run(&f!("GET {url}"), || async {
+				let resp = self.request(url).header(ACCEPT, "application/octet-stream").send().await?;
+				let mut resp = check_http_status(resp, url, Error::RepoKeyNotFound)?;
+				let mut file = File::create(dest)?;
+				while let Some(chunk) = resp.chunk().await? {
+					file.write_all(&chunk)?;
+				}
+				file.flush()?;
+				Ok(())
+			})
+			.await
+	}
+
+	/// Download the release asset of the target, and repack it as the binst package gz (`dest`).
+	async fn get_package(&self, key: &GithubKey<'_>, version: &Version, dest: &Path) -> Result<()> {
+		let key_url = self.key_url(&key.to_string());
+		let release = self.release(version).await?.ok_or_else(|| Error::RepoKeyNotFound(key_url.to_string()))?;
+		let (asset, kind) = select_asset(&release, key.target).ok_or_else(|| {
+			let names: Vec<String> = assets(&release).iter().filter_map(|a| a["name"].as_str().map(String::from)).collect();
+			Error::GithubNoAssetForTarget(key_url.to_string(), key.target.to_string(), names.join(", "))
+		})?;
+		let name = asset["name"].as_str().unwrap_or_default();
+		// Note: the api asset url (with the octet-stream accept) for the private repos, as their browser url does not accept
+		//       the api token. Gitea has only the browser url (which accepts it).
+		let download_url = asset["url"]
+			.as_str()
+			.or_else(|| asset["browser_download_url"].as_str())
+			.ok_or_else(|| Error::RepoKeyNotFound(key_url))?;
+		println!("  release asset: {name}");
+
+		let tmp_dir = make_bin_temp_dir(key.bin)?;
+		let asset_file = tmp_dir.join("asset");
+		self.download(download_url, &asset_file).await?;
+
+		// Note: GitHub gives the asset `digest` (e.g., `sha256:...`), not all the servers do
+		if let Some(expected) = asset["digest"].as_str().and_then(|d| d.strip_prefix("sha256:")) {
+			let actual = sha256_file(&asset_file)?;
+			if actual != expected {
+				return Err(Error::ChecksumMismatch(download_url.to_string(), expected.to_string(), actual));
+			}
+		}
+
+		let bin_file = match kind {
+			AssetKind::TarGz => {
+				let unpacked_dir = tmp_dir.join("unpacked");
+				unpack_tar_gz(&asset_file, &unpacked_dir)?;
+				find_bin_file(&unpacked_dir, key.bin)?
+					.ok_or_else(|| Error::GithubBinNotInAsset(key.bin.to_string(), download_url.to_string()))?
+			}
+			AssetKind::Bin => asset_file,
+		};
+
+		pack_bin_tar_gz(&bin_file, key.bin, dest)
+	}
+}
+
+#[async_trait]
+impl RepoBackend for GithubBackend {
+	fn key_url(&self, key: &str) -> String {
+		f!("{}/{key}", self.github_info.url)
+	}
+
+	async fn get_text(&self, key: &str) -> Result<Option<String>> {
+		let Some(key) = GithubKey::parse(key) else {
+			return Ok(None);
+		};
+		if key.version.is_some() || key.file != LATEST_TOML {
+			return Ok(None);
+		}
+
+		let latest = match self.latest_release(key.stream).await? {
+			Some((version, release)) if select_asset(&release, key.target).is_some() => Some(version),
+			_ => None,
+		};
+		Ok(latest.map(|version| f!("[latest]\nversion = \"{version}\"")))
+	}
+
+	async fn get_file(&self, key: &str, dest: &Path) -> Result<()> {
+		let github_key = GithubKey::parse(key);
+		match github_key.as_ref().and_then(|k| k.package_version().map(|v| (k, v))) {
+			Some((github_key, version)) => self.get_package(github_key, version, dest).await,
+			None => Err(Error::RepoKeyNotFound(self.key_url(key))),
+		}
+	}
+
+	async fn put_file(&self, _key: &str, _file: &Path) -> Result<()> {
+		Err(self.not_supported("publish"))
+	}
+
+	async fn put_text(&self, _key: &str, _content: &str) -> Result<()> {
+		Err(self.not_supported("publish"))
+	}
+
+	async fn exists(&self, key: &str) -> Result<bool> {
+		if self.get_text(key).await?.is_some() {
+			return Ok(true);
+		}
+		let github_key = GithubKey::parse(key);
+		match github_key.as_ref().and_then(|k| k.package_version().map(|v| (k, v))) {
+			Some((key, version)) => Ok(self.release(version).await?.is_some_and(|r| select_asset(&r, key.target).is_some())),
+			None => Ok(false),
+		}
+	}
+
+	async fn list(&self, _prefix: &str) -> Result<Vec<String>> {
+		Err(self.not_supported("list"))
+	}
+
+	async fn copy(&self, _key: &str, _to_key: &str) -> Result<()> {
+		Err(self.not_supported("copy"))
+	}
+
+	async fn delete(&self, _key: &str) -> Result<()> {
+		Err(self.not_supported("delete"))
+	}
+}
+
+impl GithubBackend {
+	fn not_supported(&self, operation: &str) -> Error {
+		Error::RepoOperationNotSupported(operation.to_string(), self.github_info.url.to_string())
+	}
+}
+
+impl<'a> GithubKey<'a> {
+	fn parse(key: &'a str) -> Option<GithubKey<'a>> {
+		let parts: Vec<&str> = key.split('/').collect();
+		match parts.as_slice() {
+			[bin, target, stream, file] if *file == LATEST_TOML => Some(GithubKey {
+				bin,
+				target,
+				stream,
+				version: None,
+				file,
+			}),
+			[bin, target, stream, version, file] => Some(GithubKey {
+				bin,
+				target,
+				stream,
+				version: Some(Version::parse(version).ok()?),
+				file,
+			}),
+			_ => None,
+		}
+	}
+
+	/// The version of the package `<bin>.tar.gz` key (None for the other keys).
+	fn package_version(&self) -> Option<&Version> {
+		match self.file == f!("{}.tar.gz", self.bin) {
+			true => self.version.as_ref(),
+			false => None,
+		}
+	}
+}
+
+impl std::fmt::Display for GithubKey<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.version {
+			Some(version) => write!(f, "{}/{}/{}/{}/{}", self.bin, self.target, self.stream, version, self.file),
+			None => write!(f, "{}/{}/{}/{}", self.bin, self.target, self.stream, self.file),
+		}
+	}
+}
+
+// region:    --- Release Utils
+fn is_true(value: &Value, name: &str) -> bool {
+	value[name].as_bool().unwrap_or(false)
+}
+
+/// The version of the release tag (e.g., `v1.2.3` or `1.2.3`).
+fn tag_version(release: &Value) -> Option<Version> {
+	let tag = release["tag_name"].as_str()?;
+	Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+fn assets(release: &Value) -> Vec<Value> {
+	release["assets"].as_array().cloned().unwrap_or_default()
+}
+
+/// Returns the release asset which matches the target the best (see `asset_score`).
+fn select_asset(release: &Value, target: &str) -> Option<(Value, AssetKind)> {
+	let mut best: Option<(u32, Value, AssetKind)> = None;
+	for asset in assets(release) {
+		let Some((score, kind)) = asset["name"].as_str().and_then(|name| asset_score(name, target)) else {
+			continue;
+		};
+		if best.as_ref().is_none_or(|(best_score, ..)| score > *best_score) {
+			best = Some((score, asset, kind));
+		}
+	}
+	best.map(|(_, asset, kind)| (asset, kind))
+}
+
+/// Returns the match score and kind of the asset for the target, or None if it does not match (or not supported).
+/// - The asset name has the target (e.g., `cool_cli-1.2.3-x86_64-unknown-linux-musl.tar.gz`),
+/// - or has the target arch and os (e.g., `cool_cli_1.2.3_linux_amd64.tar.gz`), and the matching libc if any
+///   (a `musl` asset is fine for a `gnu` target, as static, but not the other way around).
+///
+/// The `.tar.gz` assets are preferred over the raw binaries.
+fn asset_score(asset_name: &str, target: &str) -> Option<(u32, AssetKind)> {
+	let name = asset_name.to_lowercase();
+	let kind = asset_kind(&name)?;
+	let kind_score = match kind {
+		AssetKind::TarGz => 1,
+		AssetKind::Bin => 0,
+	};
+	if name.contains(target) {
+		return Some((100 + kind_score, kind));
+	}
+
+	let (arch, target_rest) = target.split_once('-')?;
+	let arch_names: Vec<&str> = match arch {
+		"x86_64" => vec!["x86_64", "x86-64", "amd64", "x64"],
+		"aarch64" => vec!["aarch64", "arm64"],
+		arch => vec![arch],
+	};
+	let (os_names, libc) = if target_rest.contains("linux") {
+		(vec!["linux"], target_rest.rsplit('-').next())
+	} else if target_rest.contains("darwin") {
+		(vec!["darwin", "macos", "apple", "osx"], None)
+	} else {
+		return None;
+	};
+
+	let has_arch = arch_names.iter().any(|a| name.contains(a)) || (libc.is_none() && name.contains("universal"));
+	if !has_arch || !os_names.iter().any(|os| name.contains(os)) {
+		return None;
+	}
+
+	let libc_score = match (libc, name.contains("musl"), name.contains("gnu")) {
+		(Some("musl"), _, true) => return None,
+		(Some("musl"), true, _) | (Some("gnu"), _, true) => 20,
+		(Some("gnu"), true, _) => 0,
+		_ => 10,
+	};
+	Some((libc_score + kind_score, kind))
+}
+
+/// The supported asset kinds, the `.tar.gz` (or `.tgz`), or the binary itself (no extension).
+/// Other extensions (e.g., `.zip`, `.sha256`, `.deb`) are not supported.
+fn asset_kind(name: &str) -> Option<AssetKind> {
+	if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+		return Some(AssetKind::TarGz);
+	}
+	match name.rsplit_once('.') {
+		// Note: not the `3-linux-amd64` of a `cool_cli-1.2.3-linux-amd64` binary
+		Some((_, ext))
+			if ext.len() <= 6
+				&& ext.chars().all(|c| c.is_ascii_alphanumeric())
+				&& ext.chars().any(|c| c.is_ascii_alphabetic()) =>
+		{
+			None
+		}
+		_ => Some(AssetKind::Bin),
+	}
+}
+
+/// Returns the shallowest `bin_name` file of the unpacked asset dir (e.g., `cool_cli-1.2.3-x86_64-linux/cool_cli`).
+fn find_bin_file(dir: &Path, bin_name: &str) -> Result<Option<PathBuf>> {
+	let mut dirs = vec![dir.to_path_buf()];
+	while !dirs.is_empty() {
+		let mut sub_dirs = Vec::new();
+		for dir in dirs {
+			for entry in read_dir(dir)? {
+				let entry = entry?;
+				// Note: the file type does not follow the symlinks (no loop)
+				let file_type = entry.file_type()?;
+				if file_type.is_dir() {
+					sub_dirs.push(entry.path());
+				} else if file_type.is_file() && entry.file_name() == bin_name {
+					return Ok(Some(entry.path()));
+				}
+			}
+		}
+		sub_dirs.sort();
+		dirs = sub_dirs;
+	}
+	Ok(None)
+}
+
+/// Pack the bin file as a binst package (`<bin_name>` executable at the root of the tar.gz).
+fn pack_bin_tar_gz(bin_file: &Path, bin_name: &str, gz_path: &Path) -> Result<()> {
+	// Note: Reproducible (no gzip nor tar mtime), so that the sha256 is the same on each download (e.g., for the binst.lock).
+	let options = EncodeOptions::new().header(HeaderBuilder::new().modification_time(0).finish());
+	let mut builder = Builder::new(Encoder::with_options(File::create(gz_path)?, options)?);
+	let mut header = Header::new_gnu();
+	header.set_size(bin_file.metadata()?.len());
+	header.set_mode(0o755);
+	header.set_mtime(0);
+	header.set_cksum();
+	builder.append_data(&mut header, bin_name, File::open(bin_file)?)?;
+	builder.into_inner()?.finish().into_result()?;
+	Ok(())
+}
+// endregion: --- Release Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_pack_bin_tar_gz_reproducible() -> Result<()> {
+		let test_dir = super::super::tests::set_test_binst_root("github-pack")?;
+		let bin_file = test_dir.join("cool_cli");
+		std::fs::write(&bin_file, "#!/bin/sh\necho cool")?;
+
+		let (gz_1, gz_2) = (test_dir.join("1.tar.gz"), test_dir.join("2.tar.gz"));
+		pack_bin_tar_gz(&bin_file, "cool_cli", &gz_1)?;
+		// Note: a gzip mtime would be in seconds
+		std::thread::sleep(std::time::Duration::from_millis(1100));
+		pack_bin_tar_gz(&bin_file, "cool_cli", &gz_2)?;
+		assert_eq!(sha256_file(&gz_1)?, sha256_file(&gz_2)?);
+
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_github_asset_score() {
+		fn select(names: &[&str], target: &str) -> Option<String> {
+			let assets: Vec<Value> = names.iter().map(|n| json!({ "name": n })).collect();
+			let release = json!({ "assets": assets });
+			select_asset(&release, target).map(|(asset, _)| asset["name"].as_str().unwrap().to_string())
+		}
+		let names = [
+			"rg-14.1.0-x86_64-unknown-linux-musl.tar.gz",
+			"rg-14.1.0-x86_64-unknown-linux-musl.tar.gz.sha256",
+			"rg-14.1.0-aarch64-unknown-linux-gnu.tar.gz",
+			"rg-14.1.0-aarch64-apple-darwin.tar.gz",
+			"rg-14.1.0-x86_64-pc-windows-msvc.zip",
+		];
+		assert_eq!(Some(names[0].to_string()), select(&names, "x86_64-unknown-linux-musl"));
+		assert_eq!(Some(names[2].to_string()), select(&names, "aarch64-unknown-linux-gnu"));
+		assert_eq!(Some(names[3].to_string()), select(&names, "aarch64-apple-darwin"));
+		assert_eq!(None, select(&names, "x86_64-apple-darwin"));
+
+		let names = [
+			"tool_1.2.3_linux_amd64",
+			"tool_1.2.3_linux_amd64_musl.tar.gz",
+			"tool_1.2.3_linux_arm64.deb",
+			"tool_1.2.3_macos_universal.tgz",
+		];
+		// the gnu target prefers the unspecified libc, the musl target does not take the gnu assets
+		assert_eq!(Some(names[0].to_string()), select(&names, "x86_64-unknown-linux-gnu"));
+		assert_eq!(Some(names[1].to_string()), select(&names, "x86_64-unknown-linux-musl"));
+		assert_eq!(None, select(&names, "aarch64-unknown-linux-gnu"));
+		assert_eq!(Some(names[3].to_string()), select(&names, "aarch64-apple-darwin"));
+		assert_eq!(None, select(&["tool-linux-x86_64-gnu"], "x86_64-unknown-linux-musl"));
+	}
+
+	/// tar.gz data of the files
+	fn tar_gz(files: &[(&str, &str)]) -> Vec<u8> {
+		let mut builder = Builder::new(Encoder::new(Vec::new()).unwrap());
+		for (path, content) in files {
+			let mut header = Header::new_gnu();
+			header.set_size(content.len() as u64);
+			header.set_mode(0o755);
+			header.set_cksum();
+			builder.append_data(&mut header, path, content.as_bytes()).unwrap();
+		}
+		builder.into_inner().unwrap().finish().into_result().unwrap()
+	}
+
+	/// Local stand-in of the GitHub releases api (`/api/repos/owner/tool/...`) and asset downloads (`/download/...`).
+	fn start_github_api() -> String {
+		use crate::utils::sha256_bytes;
+		use std::collections::HashMap;
+		use tiny_http::{Response, Server};
+
+		let server = Server::http("127.0.0.1:0").unwrap();
+		let port = server.server_addr().to_ip().unwrap().port();
+		let base_url = format!("http://127.0.0.1:{}", port);
+
+		let tool_gz = tar_gz(&[("tool-0.2.0/README.md", "readme"), ("tool-0.2.0/tool", "tool 0.2.0")]);
+		let private_gz = tar_gz(&[("tool", "tool 1.0.0 private")]);
+		let downloads: HashMap<String, Vec<u8>> = HashMap::from([
+			("tool-0.2.0-x86_64-unknown-linux-musl.tar.gz".to_string(), tool_gz.clone()),
+			("tool-0.2.0-darwin-arm64".to_string(), b"tool 0.2.0 darwin".to_vec()),
+			("tool-0.1.0-x86_64-unknown-linux-gnu.tar.gz".to_string(), tool_gz.clone()),
+			("tool-0.3.0-beta.1-linux-amd64".to_string(), b"tool 0.3.0-beta.1".to_vec()),
+		]);
+		let asset = |name: &str, digest: Option<String>| {
+			let mut asset = json!({
+				"name": name,
+				"url": format!("{}/api/repos/owner/tool/releases/assets/{}", base_url, name),
+				"browser_download_url": format!("{}/download/{}", base_url, name)
+			});
+			if let Some(digest) = digest {
+				asset["digest"] = json!(digest);
+			}
+			asset
+		};
+		// Note: as Gitea, only the browser url
+		let gitea_asset = |name: &str| json!({ "name": name, "browser_download_url": format!("{}/download/{}", base_url, name) });
+		// Note: as a private GitHub repo, only the api asset url with the token (the browser url is not found)
+		let private_release = json!({ "tag_name": "v1.0.0", "assets": [{
+			"name": "tool-1.0.0-x86_64-unknown-linux-musl.tar.gz",
+			"url": format!("{}/api/repos/owner/private/releases/assets/1", base_url),
+			"browser_download_url": format!("{}/private/download/tool-1.0.0-x86_64-unknown-linux-musl.tar.gz", base_url)
+		}]});
+		let release = |tag: &str, prerelease: bool, draft: bool, assets: Vec<Value>| {
+			json!({ "tag_name": tag, "prerelease": prerelease, "draft": draft, "assets": assets })
+		};
+		let releases = vec![
+			release("v0.4.0", false, true, vec![asset("tool-0.4.0-x86_64-unknown-linux-musl.tar.gz", None)]),
+			release("v0.3.0-beta.1", true, false, vec![asset("tool-0.3.0-beta.1-linux-amd64", None)]),
+			release(
+				"v0.2.0",
+				false,
+				false,
+				vec![
+					asset("tool-0.2.0-x86_64-unknown-linux-musl.tar.gz", Some(format!("sha256:{}", sha256_bytes(&tool_gz)))),
+					gitea_asset("tool-0.2.0-darwin-arm64"),
+				],
+			),
+			release("0.1.0", false, false, vec![asset("tool-0.1.0-x86_64-unknown-linux-gnu.tar.gz", Some("sha256:bad".to_string()))]),
+		];
+
+		std::thread::spawn(move || {
+			for req in server.incoming_requests() {
+				let url = req.url().to_string();
+				let header = |name: &'static str| {
+					req.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str().to_string()).unwrap_or_default()
+				};
+				let is_octet_stream = header("Accept") == "application/octet-stream";
+				let resp = if url == "/api/repos/owner/tool/releases?per_page=100" {
+					Response::from_string(Value::Array(releases.clone()).to_string())
+				} else if let Some(tag) = url.strip_prefix("/api/repos/owner/tool/releases/tags/") {
+					match releases.iter().find(|r| r["tag_name"] == tag) {
+						Some(release) => Response::from_string(release.to_string()),
+						None => Response::from_string("{}").with_status_code(404),
+					}
+				} else if let Some(name) = url.strip_prefix("/api/repos/owner/tool/releases/assets/") {
+					match (is_octet_stream, downloads.get(name)) {
+						(true, Some(data)) => Response::from_data(data.clone()),
+						(false, Some(_)) => Response::from_string(json!({ "name": name }).to_string()),
+						_ => Response::from_string("").with_status_code(404),
+					}
+				} else if let Some(data) = url.strip_prefix("/download/").and_then(|name| downloads.get(name)) {
+					Response::from_data(data.clone())
+				} else if url == "/api/repos/owner/private/releases/tags/v1.0.0" && header("Authorization") == "Bearer secret" {
+					Response::from_string(private_release.to_string())
+				} else if url == "/api/repos/owner/private/releases/assets/1" && header("Authorization") == "Bearer secret" {
+					match is_octet_stream {
+						true => Response::from_data(private_gz.clone()),
+						false => Response::from_string(private_release["assets"][0].to_string()),
+					}
+				} else {
+					Response::from_string("").with_status_code(404)
+				};
+				let _ = req.respond(resp);
+			}
+		});
+
+		format!("http://127.0.0.1:{}/api", port)
+	}
+
+	#[tokio::test]
+	async fn test_github_backend_install() -> Result<()> {
+		let test_dir = super::super::tests::set_test_binst_root("github")?;
+		let github_info = GithubInfo {
+			url: "github:owner/tool".to_string(),
+			api_url: start_github_api(),
+			owner_repo: "owner/tool".to_string(),
+			auth: None,
+		};
+		let backend = GithubBackend::new(&github_info);
+
+		// -- latest of the streams (the draft is skipped, the darwin target has a binary asset)
+		let latest = |target: &str, stream: &str| format!("tool/{}/{}/latest.toml", target, stream);
+		let latest_0_2_0 = Some("[latest]\nversion = \"0.2.0\"".to_string());
+		assert_eq!(latest_0_2_0, backend.get_text(&latest("x86_64-unknown-linux-gnu", "main")).await?);
+		assert_eq!(latest_0_2_0, backend.get_text(&latest("aarch64-apple-darwin", "main")).await?);
+		assert_eq!(None, backend.get_text(&latest("aarch64-unknown-linux-gnu", "main")).await?);
+		let latest_beta = backend.get_text(&latest("x86_64-unknown-linux-gnu", "beta")).await?;
+		assert_eq!(Some("[latest]\nversion = \"0.3.0-beta.1\"".to_string()), latest_beta);
+		assert_eq!(None, backend.get_text("tool/x86_64-unknown-linux-gnu/main/0.2.0/tool.toml").await?);
+
+		// -- the asset repacked as a binst package
+		let gz_file = test_dir.join("tool.tar.gz");
+		backend.get_file("tool/x86_64-unknown-linux-gnu/main/0.2.0/tool.tar.gz", &gz_file).await?;
+		unpack_tar_gz(&gz_file, &test_dir.join("unpacked"))?;
+		assert_eq!("tool 0.2.0", std::fs::read_to_string(test_dir.join("unpacked/tool"))?);
+		backend.get_file("tool/aarch64-apple-darwin/main/0.2.0/tool.tar.gz", &gz_file).await?;
+		unpack_tar_gz(&gz_file, &test_dir.join("unpacked-darwin"))?;
+		assert_eq!("tool 0.2.0 darwin", std::fs::read_to_string(test_dir.join("unpacked-darwin/tool"))?);
+
+		// -- errors
+		let res = backend.get_file("tool/x86_64-unknown-linux-gnu/main/0.1.0/tool.tar.gz", &gz_file).await;
+		assert!(matches!(res, Err(Error::ChecksumMismatch(..))));
+		let res = backend.get_file("tool/aarch64-unknown-linux-gnu/main/0.2.0/tool.tar.gz", &gz_file).await;
+		assert!(matches!(res, Err(Error::GithubNoAssetForTarget(..))));
+		let res = backend.get_file("tool/x86_64-unknown-linux-gnu/main/0.9.0/tool.tar.gz", &gz_file).await;
+		assert!(matches!(res, Err(Error::RepoKeyNotFound(_))));
+		let res = backend.put_text(&latest("x86_64-unknown-linux-gnu", "main"), "").await;
+		assert!(matches!(res, Err(Error::RepoOperationNotSupported(..))));
+
+		// -- private repo, from the api asset url with the token
+		let private_info = |auth: Option<HttpAuth>| GithubInfo {
+			url: "github:owner/private".to_string(),
+			api_url: github_info.api_url.to_string(),
+			owner_repo: "owner/private".to_string(),
+			auth,
+		};
+		let private_key = "tool/x86_64-unknown-linux-musl/main/1.0.0/tool.tar.gz";
+		let private = GithubBackend::new(&private_info(Some(HttpAuth::Bearer("secret".to_string()))));
+		private.get_file(private_key, &gz_file).await?;
+		unpack_tar_gz(&gz_file, &test_dir.join("unpacked-private"))?;
+		assert_eq!("tool 1.0.0 private", std::fs::read_to_string(test_dir.join("unpacked-private/tool"))?);
+		let res = GithubBackend::new(&private_info(None)).get_file(private_key, &gz_file).await;
+		assert!(matches!(res, Err(Error::RepoKeyNotFound(_))));
+
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
+}
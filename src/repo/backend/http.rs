@@ -0,0 +1,372 @@
+//! Http/https repo backend
+//!
+//! Publish uses `PUT` (and `MKCOL` for the WebDAV collections if `mkcol`), e.g., for a WebDAV or Artifactory server.
+//! Requests have the basic or bearer auth of the repo if any (see `HttpInfo`).
+
+use super::RepoBackend;
+use crate::paths::binst_tmp_dir;
+use crate::prelude::*;
+use crate::repo::download::PartialDownload;
+use crate::repo::retry::RetryPolicy;
+use crate::repo::{Error, HttpAuth, HttpInfo, Result};
+use crate::utils::{is_verbose, sha256_str};
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use std::fs::remove_file;
+use std::io::Write;
+use std::path::Path;
+
+pub struct HttpBackend {
+	http_info: HttpInfo,
+	client: Client,
+	retry: RetryPolicy,
+}
+
+impl HttpBackend {
+	pub fn new(http_info: &HttpInfo) -> Self {
+		HttpBackend {
+			http_info: http_info.clone(),
+			client: Client::new(),
+			retry: RetryPolicy::load(),
+		}
+	}
+
+	/// New request for the url, with the repo auth.
+	fn request(&self, method: Method, url: &str) -> RequestBuilder {
+		let req = self.client.request(method, url);
+		match &self.http_info.auth {
+			Some(HttpAuth::Basic(user, password)) => req.basic_auth(user, password.as_ref()),
+			Some(HttpAuth::Bearer(token)) => req.bearer_auth(token),
+			None => req,
+		}
+	}
+
+	/// Create the WebDAV collections of the key parent dirs (from the repo base, which must exist).
+	async fn make_collections(&self, key: &str) -> Result<()> {
+		let mut dirs: Vec<&str> = key.split('/').filter(|p| !p.is_empty()).collect();
+		dirs.pop(); // the file name
+
+		let mut dir_key = String::new();
+		for dir in dirs {
+			dir_key = match dir_key.is_empty() {
+				true => dir.to_string(),
+				false => f!("{dir_key}/{dir}"),
+			};
+			let url = f!("{}/", self.key_url(&dir_key));
+			let mkcol = Method::from_bytes(b"MKCOL").expect("MKCOL is a valid method");
+			self.retry
+				.run(&f!("MKCOL {url}"), || async {
+					let resp = self.request(mkcol.clone(), &url).send().await?;
+					// Note: 405 Method Not Allowed when the collection already exists
+					match resp.status() {
+						StatusCode::METHOD_NOT_ALLOWED => Ok(()),
+						_ => check_http_status(resp, &url, Error::RepoKeyNotFound).map(|_| ()),
+					}
+				})
+				.await?;
+		}
+		Ok(())
+	}
+
+	/// GET the url content as text (with retries).
+	async fn get_http_text(&self, url: &str) -> Result<String> {
+		self.retry
+			.run(&f!("GET {url}"), || async {
+				let resp = self.request(Method::GET, url).send().await?;
+				let resp = check_http_status(resp, url, Error::RepoKeyNotFound)?;
+				let data = resp.text().await?;
+				Ok(data)
+			})
+			.await
+	}
+
+	/// Download the url to the dest file (with retries).
+	/// Interrupted downloads are resumed with a `Range` request, and the `If-Range` validator
+	/// (ETag or Last-Modified) makes sure the server sends the full content if the object changed.
+	async fn download_http_resumable(&self, url: &str, dest: &Path) -> Result<()> {
+		self.retry
+			.run(&f!("GET {url}"), || async {
+				let partial = PartialDownload::new(url)?;
+				let mut resume_point = partial.resume_point();
+
+				let mut resp = loop {
+					let mut req = self.request(Method::GET, url);
+					if let Some((validator, offset)) = &resume_point {
+						req = req.header(RANGE, f!("bytes={offset}-")).header(IF_RANGE, validator);
+					}
+					let resp = req.send().await?;
+
+					// the partial does not match the remote object anymore, restart from zero
+					if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE && resume_point.is_some() {
+						partial.discard();
+						resume_point = None;
+						continue;
+					}
+					break check_http_status(resp, url, Error::RepoKeyNotFound)?;
+				};
+
+				let mut file = match (resp.status(), &resume_point) {
+					(StatusCode::PARTIAL_CONTENT, Some((_, offset))) if content_range_start(&resp) == Some(*offset) => {
+						if is_verbose() {
+							println!("  resuming download at byte {offset} - {url}");
+						}
+						partial.append()?
+					}
+					(StatusCode::PARTIAL_CONTENT, _) => {
+						partial.discard();
+						return Err(Error::HttpInvalidRange(url.to_string()));
+					}
+					_ => partial.start(response_validator(&resp).as_deref())?,
+				};
+
+				while let Some(chunk) = resp.chunk().await? {
+					file.write_all(&chunk)?;
+				}
+				file.flush()?;
+				drop(file);
+
+				partial.complete(dest)
+			})
+			.await
+	}
+}
+
+#[async_trait]
+impl RepoBackend for HttpBackend {
+	fn key_url(&self, key: &str) -> String {
+		f!("{}/{key}", self.http_info.url)
+	}
+
+	async fn get_text(&self, key: &str) -> Result<Option<String>> {
+		match self.get_http_text(&self.key_url(key)).await {
+			Ok(content) => Ok(Some(content)),
+			Err(Error::RepoKeyNotFound(_)) => Ok(None),
+			Err(ex) => Err(ex),
+		}
+	}
+
+	async fn get_file(&self, key: &str, dest: &Path) -> Result<()> {
+		self.download_http_resumable(&self.key_url(key), dest).await
+	}
+
+	async fn put_file(&self, key: &str, file: &Path) -> Result<()> {
+		if self.http_info.mkcol {
+			self.make_collections(key).await?;
+		}
+		let url = self.key_url(key);
+		self.retry
+			.run(&f!("PUT {url}"), || async {
+				let file = tokio::fs::File::open(file).await?;
+				let length = file.metadata().await?.len();
+				let req = self.request(Method::PUT, &url).header(CONTENT_LENGTH, length).body(file);
+				check_http_status(req.send().await?, &url, Error::RepoKeyNotFound)?;
+				Ok(())
+			})
+			.await
+	}
+
+	async fn put_text(&self, key: &str, content: &str) -> Result<()> {
+		if self.http_info.mkcol {
+			self.make_collections(key).await?;
+		}
+		let url = self.key_url(key);
+		self.retry
+			.run(&f!("PUT {url}"), || async {
+				let req = self.request(Method::PUT, &url).body(content.to_string());
+				check_http_status(req.send().await?, &url, Error::RepoKeyNotFound)?;
+				Ok(())
+			})
+			.await
+	}
+
+	async fn exists(&self, key: &str) -> Result<bool> {
+		let url = self.key_url(key);
+		self.retry
+			.run(&f!("HEAD {url}"), || async {
+				let resp = self.request(Method::HEAD, &url).send().await?;
+				match check_http_status(resp, &url, Error::RepoKeyNotFound) {
+					Ok(_) => Ok(true),
+					Err(Error::RepoKeyNotFound(_)) => Ok(false),
+					Err(ex) => Err(ex),
+				}
+			})
+			.await
+	}
+
+	async fn list(&self, _prefix: &str) -> Result<Vec<String>> {
+		Err(Error::RepoOperationNotSupported("list".to_string(), self.http_info.url.to_string()))
+	}
+
+	/// Note: Downloaded and uploaded back (WebDAV COPY is not supported by all servers).
+	async fn copy(&self, key: &str, to_key: &str) -> Result<()> {
+		let tmp_file = binst_tmp_dir(None)?.join(f!("http-copy-{}-{}", std::process::id(), &sha256_str(key)[..16]));
+		let res = match self.get_file(key, &tmp_file).await {
+			Ok(_) => self.put_file(to_key, &tmp_file).await,
+			Err(ex) => Err(ex),
+		};
+		let _ = remove_file(&tmp_file);
+		res
+	}
+
+	async fn delete(&self, key: &str) -> Result<()> {
+		let url = self.key_url(key);
+		self.retry
+			.run(&f!("DELETE {url}"), || async {
+				let resp = self.request(Method::DELETE, &url).send().await?;
+				match check_http_status(resp, &url, Error::RepoKeyNotFound) {
+					Ok(_) | Err(Error::RepoKeyNotFound(_)) => Ok(()),
+					Err(ex) => Err(ex),
+				}
+			})
+			.await
+	}
+}
+
+/// Returns the strong ETag, or the Last-Modified, usable for a `If-Range` header.
+fn response_validator(resp: &Response) -> Option<String> {
+	let header = |name| resp.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+	header(ETAG).filter(|etag| !etag.starts_with("W/")).or_else(|| header(LAST_MODIFIED))
+}
+
+/// Returns the start byte of a `Content-Range: bytes 100-199/200` header.
+fn content_range_start(resp: &Response) -> Option<u64> {
+	let value = resp.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+	let range = value.strip_prefix("bytes ")?;
+	range.split('-').next()?.trim().parse().ok()
+}
+
+/// Returns the response if its status is a success, otherwise the corresponding repo Error.
+/// The `not_found` builder gives the 404 error (e.g., `Error::OriginLatestNotFound`).
+pub(super) fn check_http_status(resp: Response, url: &str, not_found: fn(String) -> Error) -> Result<Response> {
+	match http_status_error(resp.status(), url, not_found) {
+		Some(err) => Err(err),
+		None => Ok(resp),
+	}
+}
+
+fn http_status_error(status: StatusCode, url: &str, not_found: fn(String) -> Error) -> Option<Error> {
+	if status.is_success() {
+		return None;
+	}
+
+	let url = url.to_string();
+	let status = status.as_u16();
+	let err = match status {
+		404 => not_found(url),
+		401 | 403 => Error::HttpAuth(url, status),
+		500..=599 => Error::HttpServerError(url, status),
+		_ => Error::HttpStatus(url, status),
+	};
+	Some(err)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_http_status_error() {
+		fn run(status: u16) -> Option<Error> {
+			http_status_error(StatusCode::from_u16(status).unwrap(), "http://repo/x", Error::RepoKeyNotFound)
+		}
+
+		assert!(run(200).is_none());
+		assert!(run(206).is_none());
+		assert!(matches!(run(404), Some(Error::RepoKeyNotFound(url)) if url == "http://repo/x"));
+		assert!(matches!(run(401), Some(Error::HttpAuth(_, 401))));
+		assert!(matches!(run(403), Some(Error::HttpAuth(_, 403))));
+		assert!(matches!(run(503), Some(ref err @ Error::HttpServerError(_, 503)) if err.is_retryable()));
+		assert!(matches!(run(400), Some(ref err @ Error::HttpStatus(_, 400)) if !err.is_retryable()));
+	}
+
+	/// Local stand-in WebDAV server (PUT/GET/HEAD/DELETE/MKCOL), with bearer auth.
+	/// PUT fails with 409 Conflict when the parent collection does not exist (as WebDAV servers).
+	fn start_dav_server(token: &'static str) -> String {
+		use std::collections::{HashMap, HashSet};
+		use tiny_http::{Response, Server};
+
+		let server = Server::http("127.0.0.1:0").unwrap();
+		let port = server.server_addr().to_ip().unwrap().port();
+		std::thread::spawn(move || {
+			let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+			let mut collections: HashSet<String> = HashSet::from(["/base".to_string()]);
+			for mut req in server.incoming_requests() {
+				let authorized = req
+					.headers()
+					.iter()
+					.any(|h| h.field.equiv("Authorization") && h.value.as_str() == format!("Bearer {}", token));
+				let path = req.url().trim_end_matches('/').to_string();
+				let parent = path.rsplit_once('/').map(|(p, _)| p.to_string()).unwrap_or_default();
+				let status = match (authorized, req.method().as_str()) {
+					(false, _) => 401,
+					(_, "MKCOL") if collections.contains(&path) => 405,
+					(_, "MKCOL") => {
+						collections.insert(path);
+						201
+					}
+					(_, "PUT") if !collections.contains(&parent) => 409,
+					(_, "PUT") => {
+						let mut body = Vec::new();
+						req.as_reader().read_to_end(&mut body).unwrap();
+						files.insert(path, body);
+						201
+					}
+					(_, "GET") if files.contains_key(&path) => {
+						let _ = req.respond(Response::from_data(files[&path].clone()));
+						continue;
+					}
+					(_, "HEAD") if files.contains_key(&path) => 200,
+					(_, "DELETE") if files.remove(&path).is_some() => 204,
+					_ => 404,
+				};
+				let _ = req.respond(Response::empty(status));
+			}
+		});
+
+		format!("http://127.0.0.1:{}/base", port)
+	}
+
+	#[tokio::test]
+	async fn test_http_backend_publish() -> Result<()> {
+		let root = super::super::tests::set_test_binst_root("http")?;
+		let url = start_dav_server("secret");
+		let info = |auth: Option<HttpAuth>, mkcol: bool| HttpInfo {
+			url: url.clone(),
+			auth,
+			mkcol,
+		};
+		let backend = HttpBackend::new(&info(Some(HttpAuth::Bearer("secret".to_string())), true));
+
+		// -- put with the collections, get, copy, exists, delete
+		let gz_file = root.join("cool_cli.tar.gz");
+		std::fs::write(&gz_file, "gz content")?;
+		backend.put_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &gz_file).await?;
+		backend.put_text("cool_cli/x86/main/latest.toml", "[latest]").await?;
+		assert_eq!(Some("[latest]".to_string()), backend.get_text("cool_cli/x86/main/latest.toml").await?);
+		backend.copy("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", "cool_cli/x86/main/0.1.1/cool_cli.tar.gz").await?;
+		let dest = root.join("downloaded.tar.gz");
+		backend.get_file("cool_cli/x86/main/0.1.1/cool_cli.tar.gz", &dest).await?;
+		assert_eq!("gz content", std::fs::read_to_string(&dest)?);
+		assert!(backend.exists("cool_cli/x86/main/0.1.0/cool_cli.tar.gz").await?);
+		backend.delete("cool_cli/x86/main/0.1.0/cool_cli.tar.gz").await?;
+		assert!(!backend.exists("cool_cli/x86/main/0.1.0/cool_cli.tar.gz").await?);
+		assert_eq!(None, backend.get_text("cool_cli/x86/main/nope.toml").await?);
+
+		// -- without mkcol, the server rejects the put in a missing collection
+		let backend = HttpBackend::new(&info(Some(HttpAuth::Bearer("secret".to_string())), false));
+		let res = backend.put_text("other_cli/x86/main/latest.toml", "[latest]").await;
+		assert!(matches!(res, Err(Error::HttpStatus(_, 409))));
+
+		// -- wrong or missing auth
+		let backend = HttpBackend::new(&info(Some(HttpAuth::Basic("user".to_string(), None)), true));
+		let res = backend.get_text("cool_cli/x86/main/latest.toml").await;
+		assert!(matches!(res, Err(Error::HttpAuth(_, 401))));
+		let backend = HttpBackend::new(&info(None, true));
+		let res = backend.put_text("cool_cli/x86/main/latest.toml", "[latest]").await;
+		assert!(matches!(res, Err(Error::HttpAuth(_, 401))));
+
+		std::fs::remove_dir_all(&root)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,209 @@
+//! Local dir repo backend
+
+use super::RepoBackend;
+use crate::prelude::*;
+use crate::repo::{Error, Result};
+use crate::utils::sha256_file;
+use async_trait::async_trait;
+use std::fs::{copy, create_dir_all, read_dir, read_to_string, remove_dir_all, remove_file, rename, write};
+use std::path::{Path, PathBuf};
+
+pub struct LocalBackend {
+	dir: PathBuf,
+}
+
+impl LocalBackend {
+	pub fn new(dir: &str) -> Self {
+		LocalBackend { dir: PathBuf::from(dir) }
+	}
+
+	fn key_path(&self, key: &str) -> PathBuf {
+		self.dir.join(key)
+	}
+
+	/// Write the file under a temporary name, and rename it in place (never half written).
+	fn write_in_place(&self, key: &str, write_tmp: impl FnOnce(&Path) -> std::io::Result<()>) -> Result<()> {
+		let path = self.key_path(key);
+		if let Some(parent) = path.parent() {
+			create_dir_all(parent)?;
+		}
+		let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+		let tmp_path = path.with_file_name(f!(".{file_name}.tmp-{}", std::process::id()));
+		if let Err(ex) = write_tmp(&tmp_path).and_then(|_| rename(&tmp_path, &path)) {
+			let _ = remove_file(&tmp_path);
+			return Err(ex.into());
+		}
+		Ok(())
+	}
+}
+
+#[async_trait]
+impl RepoBackend for LocalBackend {
+	fn key_url(&self, key: &str) -> String {
+		self.key_path(key).to_string_lossy().to_string()
+	}
+
+	async fn get_text(&self, key: &str) -> Result<Option<String>> {
+		let path = self.key_path(key);
+		match path.is_file() {
+			true => Ok(Some(read_to_string(&path)?)),
+			false => Ok(None),
+		}
+	}
+
+	async fn get_file(&self, key: &str, dest: &Path) -> Result<()> {
+		let path = self.key_path(key);
+		if !path.is_file() {
+			return Err(Error::RepoKeyNotFound(self.key_url(key)));
+		}
+		copy(&path, dest)?;
+		Ok(())
+	}
+
+	async fn put_file(&self, key: &str, file: &Path) -> Result<()> {
+		self.write_in_place(key, |tmp_path| copy(file, tmp_path).map(|_| ()))
+	}
+
+	async fn put_text(&self, key: &str, content: &str) -> Result<()> {
+		self.write_in_place(key, |tmp_path| write(tmp_path, content))
+	}
+
+	/// Note: The dir is staged next to its final location, and then moved in place (never half populated, nor mixed).
+	async fn put_dir(&self, dir_key: &str, files: &[(&str, &Path)]) -> Result<()> {
+		let dir = self.key_path(dir_key);
+		let dir_name = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+		let staged_dir = dir.with_file_name(f!(".{dir_name}.tmp-{}", std::process::id()));
+
+		let res = (|| {
+			create_dir_all(&staged_dir)?;
+			for (file_name, file) in files {
+				copy(file, staged_dir.join(file_name))?;
+			}
+			if dir.exists() {
+				// re-publish of the same version (or at_path), move the previous one out before removing it
+				let replaced_dir = dir.with_file_name(f!(".{dir_name}.old-{}", std::process::id()));
+				rename(&dir, &replaced_dir)?;
+				rename(&staged_dir, &dir)?;
+				remove_dir_all(&replaced_dir)?;
+			} else {
+				rename(&staged_dir, &dir)?;
+			}
+			Ok::<_, std::io::Error>(())
+		})();
+
+		if res.is_err() && staged_dir.exists() {
+			let _ = remove_dir_all(&staged_dir);
+		}
+		Ok(res?)
+	}
+
+	async fn exists(&self, key: &str) -> Result<bool> {
+		Ok(self.key_path(key).is_file())
+	}
+
+	async fn sha256(&self, key: &str) -> Result<Option<String>> {
+		let path = self.key_path(key);
+		match path.is_file() {
+			true => Ok(Some(sha256_file(&path)?)),
+			false => Ok(None),
+		}
+	}
+
+	async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+		let mut keys = Vec::new();
+		collect_keys(&self.dir, "", &mut keys)?;
+		keys.retain(|key| key.starts_with(prefix));
+		keys.sort();
+		Ok(keys)
+	}
+
+	async fn copy(&self, key: &str, to_key: &str) -> Result<()> {
+		let path = self.key_path(key);
+		if !path.is_file() {
+			return Err(Error::RepoKeyNotFound(self.key_url(key)));
+		}
+		self.write_in_place(to_key, |tmp_path| copy(&path, tmp_path).map(|_| ()))
+	}
+
+	async fn delete(&self, key: &str) -> Result<()> {
+		let path = self.key_path(key);
+		if path.is_file() {
+			remove_file(path)?;
+		}
+		Ok(())
+	}
+}
+
+/// Collect the file keys of the dir, recursively.
+/// Note: The dot files (e.g., the `.latest.toml.tmp-123` being written) are not keys.
+fn collect_keys(dir: &Path, key_prefix: &str, keys: &mut Vec<String>) -> Result<()> {
+	if !dir.is_dir() {
+		return Ok(());
+	}
+	for entry in read_dir(dir)? {
+		let entry = entry?;
+		let name = entry.file_name().to_string_lossy().to_string();
+		if name.starts_with('.') {
+			continue;
+		}
+		let key = f!("{key_prefix}{name}");
+		let path = entry.path();
+		if path.is_dir() {
+			collect_keys(&path, &f!("{key}/"), keys)?;
+		} else {
+			keys.push(key);
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_local_backend() -> Result<()> {
+		let dir = std::env::temp_dir().join(f!("binst-test-local-backend-{}", std::process::id()));
+		let backend = LocalBackend::new(&dir.to_string_lossy());
+
+		backend.put_text("cool_cli/x86/main/latest.toml", "[latest]").await?;
+		backend.copy("cool_cli/x86/main/latest.toml", "cool_cli/x86/main/0.1.0/cool_cli.toml").await?;
+		assert_eq!(Some("[latest]".to_string()), backend.get_text("cool_cli/x86/main/0.1.0/cool_cli.toml").await?);
+		assert_eq!(None, backend.get_text("cool_cli/x86/main/nope.toml").await?);
+		assert!(matches!(
+			backend.get_file("cool_cli/nope.tar.gz", &dir.join("nope")).await,
+			Err(Error::RepoKeyNotFound(_))
+		));
+		assert_eq!(
+			vec!["cool_cli/x86/main/0.1.0/cool_cli.toml", "cool_cli/x86/main/latest.toml"],
+			backend.list("cool_cli/").await?
+		);
+
+		backend.delete("cool_cli/x86/main/latest.toml").await?;
+		assert!(!backend.exists("cool_cli/x86/main/latest.toml").await?);
+		assert_eq!(1, backend.list("").await?.len());
+
+		std::fs::remove_dir_all(&dir)?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_local_backend_put_dir() -> Result<()> {
+		let dir = std::env::temp_dir().join(f!("binst-test-local-backend-put-dir-{}", std::process::id()));
+		let backend = LocalBackend::new(&dir.to_string_lossy());
+		let file = dir.join("file");
+		create_dir_all(&dir)?;
+		write(&file, "v1")?;
+
+		// -- a new dir, then replaced (the previous files gone, and no staged nor old dirs left)
+		backend.put_dir("cool_cli/x86/main/0.1.0", &[("a.toml", &file), ("b.tar.gz", &file)]).await?;
+		write(&file, "v2")?;
+		backend.put_dir("cool_cli/x86/main/0.1.0", &[("a.toml", &file)]).await?;
+		assert_eq!(Some("v2".to_string()), backend.get_text("cool_cli/x86/main/0.1.0/a.toml").await?);
+		assert_eq!(vec!["cool_cli/x86/main/0.1.0/a.toml"], backend.list("cool_cli/").await?);
+		assert_eq!(1, read_dir(dir.join("cool_cli/x86/main"))?.count());
+
+		std::fs::remove_dir_all(&dir)?;
+		Ok(())
+	}
+}
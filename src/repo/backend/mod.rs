@@ -0,0 +1,118 @@
+//! Repo backends, the key based storage of a repo (local dir, S3, http, sftp, oci, git, github releases)
+//!
+//! Keys are relative to the repo base, with `/` separators (e.g., `cool_cli/x86_64-unknown-linux-gnu/main/latest.toml`).
+//! The `BinRepo` install/publish logic is written once on top of the `RepoBackend`.
+
+// -- Re-exports
+pub use self::git::GitBackend;
+pub use self::github::GithubBackend;
+pub use self::http::HttpBackend;
+pub use self::local::LocalBackend;
+pub use self::oci::OciBackend;
+pub use self::s3::S3Backend;
+pub use self::sftp::SftpBackend;
+
+// -- Imports
+use crate::prelude::*;
+use crate::repo::{make_bin_temp_dir, Error, RepoInfo, Result};
+use crate::utils::sha256_file;
+use async_trait::async_trait;
+use std::path::Path;
+
+// -- Sub-Modules
+mod git;
+mod github;
+mod http;
+mod local;
+mod oci;
+mod s3;
+mod sftp;
+
+#[async_trait]
+pub trait RepoBackend: Send + Sync {
+	/// The url (or path) of the key, for reporting (e.g., `s3://bucket/base/key`).
+	fn key_url(&self, key: &str) -> String;
+
+	/// Returns the key content as string, or None if the key does not exist.
+	async fn get_text(&self, key: &str) -> Result<Option<String>>;
+
+	/// Download the key content to the dest file (streamed).
+	/// Fails with `Error::RepoKeyNotFound` if the key does not exist.
+	async fn get_file(&self, key: &str, dest: &Path) -> Result<()>;
+
+	/// Upload the file as the key content (streamed), replacing the eventual previous one.
+	async fn put_file(&self, key: &str, file: &Path) -> Result<()>;
+
+	async fn put_text(&self, key: &str, content: &str) -> Result<()>;
+
+	/// Upload the files as the `(file_name, file)` keys of the dir (e.g., a package version dir), in order,
+	/// replacing the eventual previous dir (its other files are removed, so not for an `--at-path` dir).
+	/// By default one key at a time, so a re-publish has a window with the new and previous files mixed (e.g., S3, http).
+	/// The backends which can rename a dir (local, sftp) stage the dir, and then swap it in place.
+	async fn put_dir(&self, dir_key: &str, files: &[(&str, &Path)]) -> Result<()> {
+		for (file_name, file) in files {
+			self.put_file(&f!("{dir_key}/{file_name}"), file).await?;
+		}
+		Ok(())
+	}
+
+	async fn exists(&self, key: &str) -> Result<bool>;
+
+	/// Returns the sha256 of the key content, or None if the key does not exist.
+	/// By default downloaded to a tmp file (the local backend hashes the file in place).
+	async fn sha256(&self, key: &str) -> Result<Option<String>> {
+		let tmp_dir = make_bin_temp_dir("sha256")?;
+		let tmp_file = tmp_dir.join("content");
+		match self.get_file(key, &tmp_file).await {
+			Ok(()) => Ok(Some(sha256_file(&tmp_file)?)),
+			Err(Error::RepoKeyNotFound(_)) => Ok(None),
+			Err(ex) => Err(ex),
+		}
+	}
+
+	/// Returns all the keys starting with the prefix (e.g., `cool_cli/`).
+	async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+
+	// Note: `copy` and `delete` are not used by install/publish/mirror yet, but complete the key operations
+	//       (implemented and tested by each backend).
+	#[allow(dead_code)]
+	async fn copy(&self, key: &str, to_key: &str) -> Result<()>;
+
+	/// Delete the key (no error if it does not exist).
+	#[allow(dead_code)]
+	async fn delete(&self, key: &str) -> Result<()>;
+
+	/// Make the puts and deletes visible in the repo, for the backends which batch them (e.g., git commit and push).
+	async fn flush(&self) -> Result<()> {
+		Ok(())
+	}
+}
+
+/// Build the backend of the repo.
+pub async fn new_repo_backend(repo_info: &RepoInfo) -> Result<Box<dyn RepoBackend>> {
+	let backend: Box<dyn RepoBackend> = match repo_info {
+		RepoInfo::Local(dir) => Box::new(LocalBackend::new(dir)),
+		RepoInfo::S3(s3_info) => Box::new(S3Backend::new(s3_info).await?),
+		RepoInfo::Http(http_info) => Box::new(HttpBackend::new(http_info)),
+		RepoInfo::Sftp(sftp_info) => Box::new(SftpBackend::new(sftp_info)),
+		RepoInfo::Oci(oci_info) => Box::new(OciBackend::new(oci_info)),
+		RepoInfo::Git(git_info) => Box::new(GitBackend::new(git_info)),
+		RepoInfo::Github(github_info) => Box::new(GithubBackend::new(github_info)),
+	};
+	Ok(backend)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+	use std::path::PathBuf;
+
+	/// Set the binst root of the backend tests (shared, as set once per process),
+	/// and returns the test dir for the test files (removed by the test).
+	pub fn set_test_binst_root(test_name: &str) -> std::io::Result<PathBuf> {
+		let root = std::env::temp_dir().join(format!("binst-test-backend-{}", std::process::id()));
+		crate::paths::set_binst_root(&root)?;
+		let test_dir = root.join(test_name);
+		std::fs::create_dir_all(&test_dir)?;
+		Ok(test_dir)
+	}
+}
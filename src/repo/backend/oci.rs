@@ -0,0 +1,760 @@
+//! OCI registry repo backend, with the registry http api (OCI distribution spec)
+//!
+//! The keys are layers of OCI artifacts, in the `<namespace>/<bin>` repository:
+//! - `<bin>/<dir>/<file>` is the layer titled `<file>` of the `<dir>` tag (with `-` for `/`),
+//!   e.g., `cool_cli/x86_64-unknown-linux-gnu/main/0.1.0/cool_cli.tar.gz` is in `ns/cool_cli:x86_64-unknown-linux-gnu-main-0.1.0`
+//! - `<bin>/<target>/<stream>/latest.toml` is the `<target>-<stream>-latest` tag, the artifact of the latest version
+//!   with the `latest.toml` layer added (so pulling the latest tag gives the latest package).
+//!
+//! The `/` of the dirs are `-` in the tags, so two dirs can have the same tag (e.g., an at-path `x86-main/0.1.0`).
+//! The artifact dir annotation is checked, the second dir is refused on publish (and not found on install).
+//!
+//! Listing all the bins (e.g., `binst repo mirror` without `--bin`) needs the registry catalog (`/v2/_catalog`),
+//! which GHCR, Docker Hub or ECR do not serve.
+//!
+//! Auth is the repo basic auth (or bearer token), with the registry token flow (`WWW-Authenticate: Bearer realm=...`).
+
+use super::http::check_http_status;
+use super::RepoBackend;
+use crate::paths::binst_tmp_dir;
+use crate::prelude::*;
+use crate::repo::retry::RetryPolicy;
+use crate::repo::{Error, HttpAuth, OciInfo, Result, LATEST_TOML};
+use crate::utils::{get_toml_value_as_string, sha256_bytes, sha256_file, sha256_str};
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, LOCATION, WWW_AUTHENTICATE};
+use reqwest::{Body, Client, RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value};
+use std::fs::{remove_file, write, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const ARTIFACT_TYPE: &str = "application/vnd.binst.package.v1";
+const EMPTY_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
+const EMPTY_CONFIG: &[u8] = b"{}";
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+// the key dir of the artifact (e.g., `x86_64-unknown-linux-gnu/main/0.1.0`), to list the keys back
+const DIR_ANNOTATION: &str = "io.binst.dir";
+const DIGEST_HEADER: &str = "Docker-Content-Digest";
+
+pub struct OciBackend {
+	oci_info: OciInfo,
+	client: Client,
+	retry: RetryPolicy,
+	/// The last registry token (token flow), reused until the registry asks for another one
+	token: Mutex<Option<String>>,
+}
+
+/// The OCI artifact and layer of a key
+struct OciRef {
+	repository: String,
+	tag: String,
+	/// The key dir, without the bin (e.g., `x86_64-unknown-linux-gnu/main/0.1.0`)
+	dir: String,
+	/// The layer title (the key file name)
+	file: String,
+}
+
+impl OciRef {
+	fn is_latest(&self) -> bool {
+		self.file == LATEST_TOML
+	}
+
+	/// The artifact manifest is the one of this dir (or of no dir), not of another dir with the same tag.
+	fn is_dir_of(&self, manifest: &Value) -> bool {
+		manifest_dir(manifest).is_none_or(|dir| dir == self.dir)
+	}
+}
+
+impl OciBackend {
+	pub fn new(oci_info: &OciInfo) -> Self {
+		OciBackend {
+			oci_info: oci_info.clone(),
+			client: Client::new(),
+			retry: RetryPolicy::load(),
+			token: Mutex::new(None),
+		}
+	}
+
+	fn oci_ref(&self, key: &str) -> Result<OciRef> {
+		let parts: Vec<&str> = key.split('/').filter(|p| !p.is_empty()).collect();
+		let [bin, dir @ .., file] = parts.as_slice() else {
+			return Err(Error::RepoKeyNotFound(self.key_url(key)));
+		};
+		if dir.is_empty() {
+			return Err(Error::RepoKeyNotFound(self.key_url(key)));
+		}
+
+		let dir = dir.join("/");
+		let tag = match *file == LATEST_TOML {
+			true => f!("{}-latest", dir.replace('/', "-")),
+			false => dir.replace('/', "-"),
+		};
+		Ok(OciRef {
+			repository: self.repository(bin),
+			tag: sanitize_tag(&tag),
+			dir,
+			file: file.to_string(),
+		})
+	}
+
+	fn repository(&self, bin_name: &str) -> String {
+		f!("{}/{}", self.oci_info.namespace, bin_name.to_lowercase())
+	}
+
+	fn api_url(&self, path: &str) -> String {
+		f!("{}/v2/{path}", self.oci_info.registry_url)
+	}
+}
+
+// region:    --- Registry Requests
+impl OciBackend {
+	/// Send the request (with retries), with the registry auth.
+	/// The `build` function is called again when the registry asks for auth (401).
+	async fn send(&self, label: &str, build: impl Fn() -> Result<RequestBuilder>) -> Result<Response> {
+		self.retry
+			.run(label, || async {
+				let resp = self.with_auth(build()?).send().await?;
+				if resp.status() != StatusCode::UNAUTHORIZED {
+					return Ok(resp);
+				}
+
+				let challenge = resp.headers().get(WWW_AUTHENTICATE).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+				match challenge {
+					Some(challenge) if challenge.starts_with("Bearer ") => {
+						let token = self.fetch_token(&challenge).await?;
+						*self.token.lock().unwrap() = Some(token.clone());
+						Ok(build()?.bearer_auth(token).send().await?)
+					}
+					Some(challenge) if challenge.starts_with("Basic ") => match &self.oci_info.auth {
+						Some(HttpAuth::Basic(user, password)) => Ok(build()?.basic_auth(user, password.as_ref()).send().await?),
+						_ => Ok(resp),
+					},
+					_ => Ok(resp),
+				}
+			})
+			.await
+	}
+
+	/// The last token if any, otherwise the configured bearer token if any.
+	fn with_auth(&self, req: RequestBuilder) -> RequestBuilder {
+		let token = self.token.lock().unwrap().clone();
+		match (token, &self.oci_info.auth) {
+			(Some(token), _) => req.bearer_auth(token),
+			(None, Some(HttpAuth::Bearer(token))) => req.bearer_auth(token),
+			_ => req,
+		}
+	}
+
+	/// Get a registry token for the challenge (e.g., `Bearer realm="https://auth.io/token",service="registry",scope="..."`),
+	/// with the basic auth if any (anonymous otherwise).
+	async fn fetch_token(&self, challenge: &str) -> Result<String> {
+		let rx = Regex::new(r#"(\w+)="([^"]*)""#).unwrap(); // can't fail if it worked once
+		let params: Vec<(String, String)> =
+			rx.captures_iter(challenge).map(|c| (c[1].to_string(), c[2].to_string())).collect();
+		let realm = params
+			.iter()
+			.find(|(name, _)| name == "realm")
+			.map(|(_, v)| v.to_string())
+			.ok_or_else(|| Error::HttpAuth(self.oci_info.registry_url.to_string(), 401))?;
+		let query: Vec<&(String, String)> = params.iter().filter(|(name, _)| name == "service" || name == "scope").collect();
+
+		let mut req = self.client.get(&realm).query(&query);
+		if let Some(HttpAuth::Basic(user, password)) = &self.oci_info.auth {
+			req = req.basic_auth(user, password.as_ref());
+		}
+		let resp = check_http_status(req.send().await?, &realm, Error::RepoKeyNotFound)?;
+		let content: Value = resp.json().await?;
+
+		// Note: `token`, or the OAuth2 compatible `access_token`
+		let token = content.get("token").or_else(|| content.get("access_token")).and_then(|v| v.as_str());
+		token.map(|t| t.to_string()).ok_or_else(|| Error::HttpAuth(realm.to_string(), 401))
+	}
+
+	/// Returns the manifest and its digest, or None if not found.
+	async fn get_manifest(&self, repository: &str, reference: &str) -> Result<Option<(Value, String)>> {
+		let url = self.api_url(&f!("{repository}/manifests/{reference}"));
+		let resp = self
+			.send(&f!("GET {url}"), || Ok(self.client.get(&url).header(ACCEPT, MANIFEST_MEDIA_TYPE)))
+			.await?;
+		let resp = match check_http_status(resp, &url, Error::RepoKeyNotFound) {
+			Ok(resp) => resp,
+			Err(Error::RepoKeyNotFound(_)) => return Ok(None),
+			Err(ex) => return Err(ex),
+		};
+
+		let digest_header = resp.headers().get(DIGEST_HEADER).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+		let content = resp.bytes().await?;
+		let digest = digest_header.unwrap_or_else(|| f!("sha256:{}", sha256_bytes(&content)));
+		let manifest: Value =
+			serde_json::from_slice(&content).map_err(|ex| Error::OciInvalidManifest(url.to_string(), ex.to_string()))?;
+		Ok(Some((manifest, digest)))
+	}
+
+	async fn put_manifest(&self, repository: &str, tag: &str, manifest: &Value) -> Result<()> {
+		let url = self.api_url(&f!("{repository}/manifests/{tag}"));
+		let content = manifest.to_string();
+		let resp = self
+			.send(&f!("PUT {url}"), || {
+				Ok(self.client.put(&url).header(CONTENT_TYPE, MANIFEST_MEDIA_TYPE).body(content.clone()))
+			})
+			.await?;
+		check_http_status(resp, &url, Error::RepoKeyNotFound)?;
+		Ok(())
+	}
+
+	async fn delete_manifest(&self, repository: &str, digest: &str) -> Result<()> {
+		let url = self.api_url(&f!("{repository}/manifests/{digest}"));
+		let resp = self.send(&f!("DELETE {url}"), || Ok(self.client.delete(&url))).await?;
+		match check_http_status(resp, &url, Error::RepoKeyNotFound) {
+			Ok(_) | Err(Error::RepoKeyNotFound(_)) => Ok(()),
+			Err(ex) => Err(ex),
+		}
+	}
+
+	/// Upload the blob (monolithic upload), if not already in the repository.
+	/// The `body` function gives the blob content (called again on retry).
+	async fn upload_blob(&self, repository: &str, digest: &str, size: u64, body: impl Fn() -> Result<Body>) -> Result<()> {
+		let blob_url = self.api_url(&f!("{repository}/blobs/{digest}"));
+		let resp = self.send(&f!("HEAD {blob_url}"), || Ok(self.client.head(&blob_url))).await?;
+		if resp.status().is_success() {
+			return Ok(());
+		}
+
+		// -- start the upload session
+		let uploads_url = self.api_url(&f!("{repository}/blobs/uploads/"));
+		let resp = self.send(&f!("POST {uploads_url}"), || Ok(self.client.post(&uploads_url))).await?;
+		let resp = check_http_status(resp, &uploads_url, Error::RepoKeyNotFound)?;
+		let location = resp
+			.headers()
+			.get(LOCATION)
+			.and_then(|v| v.to_str().ok())
+			.ok_or_else(|| Error::HttpStatus(uploads_url.to_string(), resp.status().as_u16()))?;
+
+		// -- upload the content (the location can be relative to the registry)
+		let location = match location.starts_with('/') {
+			true => f!("{}{location}", self.oci_info.registry_url),
+			false => location.to_string(),
+		};
+		let separator = if location.contains('?') { '&' } else { '?' };
+		let upload_url = f!("{location}{separator}digest={digest}");
+		let resp = self
+			.send(&f!("PUT {upload_url}"), || {
+				let req = self.client.put(&upload_url).header(CONTENT_TYPE, "application/octet-stream");
+				Ok(req.header(CONTENT_LENGTH, size).body(body()?))
+			})
+			.await?;
+		check_http_status(resp, &upload_url, Error::RepoKeyNotFound)?;
+		Ok(())
+	}
+
+	/// Download the blob to the dest file (streamed), and verify its digest.
+	async fn download_blob(&self, repository: &str, digest: &str, dest: &Path) -> Result<()> {
+		let url = self.api_url(&f!("{repository}/blobs/{digest}"));
+		let resp = self.send(&f!("GET {url}"), || Ok(self.client.get(&url))).await?;
+		let mut resp = check_http_status(resp, &url, Error::RepoKeyNotFound)?;
+		let mut file = File::create(dest)?;
+		while let Some(chunk) = resp.chunk().await? {
+			file.write_all(&chunk)?;
+		}
+		file.flush()?;
+
+		let actual = f!("sha256:{}", sha256_file(dest)?);
+		if actual != digest {
+			let _ = remove_file(dest);
+			return Err(Error::ChecksumMismatch(url, digest.to_string(), actual));
+		}
+		Ok(())
+	}
+
+	/// Returns the tags of the repository (empty if the repository does not exist).
+	async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+		let url = self.api_url(&f!("{repository}/tags/list"));
+		let resp = self.send(&f!("GET {url}"), || Ok(self.client.get(&url))).await?;
+		let content: Value = match check_http_status(resp, &url, Error::RepoKeyNotFound) {
+			Ok(resp) => resp.json().await?,
+			Err(Error::RepoKeyNotFound(_)) => return Ok(Vec::new()),
+			Err(ex) => return Err(ex),
+		};
+		Ok(as_strings(content.get("tags")))
+	}
+
+	/// Returns the bin names of the namespace (from the registry catalog).
+	/// Note: Most public registries (GHCR, Docker Hub, ECR) do not serve the catalog (or not to the namespace users).
+	async fn list_bins(&self) -> Result<Vec<String>> {
+		let url = self.api_url("_catalog");
+		let resp = self.send(&f!("GET {url}"), || Ok(self.client.get(&url))).await?;
+		if matches!(
+			resp.status(),
+			StatusCode::NOT_FOUND | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::METHOD_NOT_ALLOWED
+		) {
+			return Err(Error::OciNoCatalog(self.oci_info.registry_url.to_string(), resp.status().as_u16()));
+		}
+		let content: Value = check_http_status(resp, &url, Error::RepoKeyNotFound)?.json().await?;
+		let namespace_prefix = f!("{}/", self.oci_info.namespace);
+		let bins = as_strings(content.get("repositories"))
+			.into_iter()
+			.filter_map(|r| r.strip_prefix(&namespace_prefix).map(|b| b.to_string()))
+			.filter(|b| !b.contains('/'))
+			.collect();
+		Ok(bins)
+	}
+}
+// endregion: --- Registry Requests
+
+// region:    --- Layer Operations
+impl OciBackend {
+	/// Add (or replace) the file as the layer of the key artifact.
+	async fn put_layer(&self, key: &str, file: &Path) -> Result<()> {
+		let oci_ref = self.oci_ref(key)?;
+		let size = file.metadata()?.len();
+		let digest = f!("sha256:{}", sha256_file(file)?);
+
+		// -- the artifact to add the layer to (the version one for the latest tag)
+		let base_manifest = match oci_ref.is_latest() {
+			true => {
+				let toml: toml::Value = toml::from_str(&std::fs::read_to_string(file)?)?;
+				let version = get_toml_value_as_string(&toml, &["latest", "version"])?;
+				let version_tag = sanitize_tag(&f!("{}-{}", oci_ref.dir.replace('/', "-"), version));
+				let version_manifest = self.get_manifest(&oci_ref.repository, &version_tag).await?;
+				// Note: the version layers, without the previous latest layer
+				version_manifest.map(|(mut manifest, _)| {
+					set_layer(&mut manifest, LATEST_TOML, None);
+					manifest
+				})
+			}
+			false => self.get_manifest(&oci_ref.repository, &oci_ref.tag).await?.map(|(m, _)| m),
+		};
+		// -- refuse to replace the artifact of another dir with the same tag
+		let tag_manifest = match oci_ref.is_latest() {
+			true => self.get_manifest(&oci_ref.repository, &oci_ref.tag).await?.map(|(m, _)| m),
+			false => base_manifest.clone(),
+		};
+		if let Some(other_dir) = tag_manifest.as_ref().filter(|m| !oci_ref.is_dir_of(m)).and_then(manifest_dir) {
+			return Err(Error::OciTagCollision(self.key_url(key), oci_ref.tag, other_dir.to_string()));
+		}
+
+		let mut manifest = base_manifest.unwrap_or_else(new_manifest);
+		manifest["annotations"][DIR_ANNOTATION] = json!(oci_ref.dir);
+
+		// -- upload the blobs, and then the manifest
+		let empty_digest = f!("sha256:{}", sha256_bytes(EMPTY_CONFIG));
+		self.upload_blob(&oci_ref.repository, &empty_digest, EMPTY_CONFIG.len() as u64, || Ok(Body::from(EMPTY_CONFIG)))
+			.await?;
+		self.upload_blob(&oci_ref.repository, &digest, size, || {
+			let file = tokio::fs::File::from_std(File::open(file)?);
+			Ok(Body::from(file))
+		})
+		.await?;
+
+		let layer = json!({
+			"mediaType": layer_media_type(&oci_ref.file),
+			"digest": digest,
+			"size": size,
+			"annotations": { TITLE_ANNOTATION: oci_ref.file },
+		});
+		set_layer(&mut manifest, &oci_ref.file, Some(layer));
+		self.put_manifest(&oci_ref.repository, &oci_ref.tag, &manifest).await
+	}
+
+	/// Returns the (layer digest, artifact manifest, artifact digest) of the key, or None if not found.
+	async fn find_layer(&self, oci_ref: &OciRef) -> Result<Option<(String, Value, String)>> {
+		let Some((manifest, manifest_digest)) = self.get_manifest(&oci_ref.repository, &oci_ref.tag).await? else {
+			return Ok(None);
+		};
+		if !oci_ref.is_dir_of(&manifest) {
+			return Ok(None);
+		}
+		let digest = layers(&manifest)
+			.iter()
+			.find(|l| layer_title(l) == Some(oci_ref.file.as_str()))
+			.and_then(|l| l.get("digest").and_then(|d| d.as_str()))
+			.map(|d| d.to_string());
+		Ok(digest.map(|digest| (digest, manifest, manifest_digest)))
+	}
+
+	fn tmp_file(&self, key: &str) -> Result<std::path::PathBuf> {
+		let name = f!("oci-{}-{}", std::process::id(), &sha256_str(key)[..16]);
+		Ok(binst_tmp_dir(None)?.join(name))
+	}
+}
+// endregion: --- Layer Operations
+
+#[async_trait]
+impl RepoBackend for OciBackend {
+	fn key_url(&self, key: &str) -> String {
+		f!("{}/{key}", self.oci_info.url)
+	}
+
+	async fn get_text(&self, key: &str) -> Result<Option<String>> {
+		let tmp_file = self.tmp_file(key)?;
+		let res = match self.get_file(key, &tmp_file).await {
+			Ok(_) => Ok(Some(std::fs::read_to_string(&tmp_file)?)),
+			Err(Error::RepoKeyNotFound(_)) => Ok(None),
+			Err(ex) => Err(ex),
+		};
+		let _ = remove_file(&tmp_file);
+		res
+	}
+
+	async fn get_file(&self, key: &str, dest: &Path) -> Result<()> {
+		let oci_ref = self.oci_ref(key)?;
+		match self.find_layer(&oci_ref).await? {
+			Some((digest, _, _)) => self.download_blob(&oci_ref.repository, &digest, dest).await,
+			None => Err(Error::RepoKeyNotFound(self.key_url(key))),
+		}
+	}
+
+	async fn put_file(&self, key: &str, file: &Path) -> Result<()> {
+		self.put_layer(key, file).await
+	}
+
+	async fn put_text(&self, key: &str, content: &str) -> Result<()> {
+		let tmp_file = self.tmp_file(key)?;
+		write(&tmp_file, content)?;
+		let res = self.put_layer(key, &tmp_file).await;
+		let _ = remove_file(&tmp_file);
+		res
+	}
+
+	async fn exists(&self, key: &str) -> Result<bool> {
+		let oci_ref = self.oci_ref(key)?;
+		Ok(self.find_layer(&oci_ref).await?.is_some())
+	}
+
+	async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+		let bins = match prefix.split_once('/') {
+			Some((bin, _)) => vec![bin.to_string()],
+			None => self.list_bins().await?.into_iter().filter(|b| b.starts_with(prefix)).collect(),
+		};
+
+		let mut keys = Vec::new();
+		for bin in bins {
+			let repository = self.repository(&bin);
+			for tag in self.list_tags(&repository).await? {
+				let Some((manifest, _)) = self.get_manifest(&repository, &tag).await? else {
+					continue;
+				};
+				let Some(dir) = manifest_dir(&manifest) else {
+					continue;
+				};
+				// Note: the latest tag only has the latest.toml key (the other layers are the version ones)
+				let is_latest = tag.ends_with("-latest");
+				for title in layers(&manifest).iter().filter_map(layer_title) {
+					if !is_latest || title == LATEST_TOML {
+						keys.push(f!("{bin}/{dir}/{title}"));
+					}
+				}
+			}
+		}
+
+		keys.retain(|key| key.starts_with(prefix));
+		keys.sort();
+		keys.dedup();
+		Ok(keys)
+	}
+
+	/// Note: Downloaded and uploaded back (the blob upload is skipped if already in the repository).
+	async fn copy(&self, key: &str, to_key: &str) -> Result<()> {
+		let tmp_file = self.tmp_file(key)?;
+		let res = match self.get_file(key, &tmp_file).await {
+			Ok(_) => self.put_layer(to_key, &tmp_file).await,
+			Err(ex) => Err(ex),
+		};
+		let _ = remove_file(&tmp_file);
+		res
+	}
+
+	/// Remove the layer from the artifact, and delete the artifact when it has no more layers.
+	async fn delete(&self, key: &str) -> Result<()> {
+		let oci_ref = self.oci_ref(key)?;
+		let Some((_, mut manifest, manifest_digest)) = self.find_layer(&oci_ref).await? else {
+			return Ok(());
+		};
+		set_layer(&mut manifest, &oci_ref.file, None);
+		match layers(&manifest).is_empty() {
+			true => self.delete_manifest(&oci_ref.repository, &manifest_digest).await,
+			false => self.put_manifest(&oci_ref.repository, &oci_ref.tag, &manifest).await,
+		}
+	}
+}
+
+// region:    --- Oci Utils
+fn new_manifest() -> Value {
+	json!({
+		"schemaVersion": 2,
+		"mediaType": MANIFEST_MEDIA_TYPE,
+		"artifactType": ARTIFACT_TYPE,
+		"config": {
+			"mediaType": EMPTY_CONFIG_MEDIA_TYPE,
+			"digest": f!("sha256:{}", sha256_bytes(EMPTY_CONFIG)),
+			"size": EMPTY_CONFIG.len(),
+		},
+		"layers": [],
+		"annotations": {},
+	})
+}
+
+fn layers(manifest: &Value) -> Vec<Value> {
+	manifest.get("layers").and_then(|l| l.as_array()).cloned().unwrap_or_default()
+}
+
+fn layer_title(layer: &Value) -> Option<&str> {
+	layer.get("annotations")?.get(TITLE_ANNOTATION)?.as_str()
+}
+
+/// Set (or remove if None) the layer titled `title`.
+fn set_layer(manifest: &mut Value, title: &str, layer: Option<Value>) {
+	let mut layers: Vec<Value> = layers(manifest).into_iter().filter(|l| layer_title(l) != Some(title)).collect();
+	if let Some(layer) = layer {
+		layers.push(layer);
+	}
+	manifest["layers"] = Value::Array(layers);
+}
+
+fn layer_media_type(file_name: &str) -> &'static str {
+	if file_name.ends_with(".tar.gz") {
+		"application/vnd.binst.package.v1.tar+gzip"
+	} else if file_name.ends_with(".toml") {
+		"application/toml"
+	} else {
+		"application/octet-stream"
+	}
+}
+
+/// Make the tag valid (`[a-zA-Z0-9_][a-zA-Z0-9._-]{0,127}`), e.g., the `+` of the semver build metadata.
+fn sanitize_tag(tag: &str) -> String {
+	let mut tag: String = tag
+		.chars()
+		.map(|c| match c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' {
+			true => c,
+			false => '_',
+		})
+		.collect();
+	if tag.starts_with('.') || tag.starts_with('-') {
+		tag.insert(0, '_');
+	}
+	tag.truncate(128);
+	tag
+}
+
+/// The key dir of the artifact (its dir annotation).
+fn manifest_dir(manifest: &Value) -> Option<&str> {
+	manifest.get("annotations")?.get(DIR_ANNOTATION)?.as_str()
+}
+
+fn as_strings(value: Option<&Value>) -> Vec<String> {
+	let values = value.and_then(|v| v.as_array()).cloned().unwrap_or_default();
+	values.iter().filter_map(|v| v.as_str()).map(|v| v.to_string()).collect()
+}
+
+// endregion: --- Oci Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_oci_sanitize_tag() {
+		assert_eq!("x86_64-unknown-linux-gnu-main-0.1.0", sanitize_tag("x86_64-unknown-linux-gnu-main-0.1.0"));
+		assert_eq!("main-0.1.0_build.1", sanitize_tag("main-0.1.0+build.1"));
+		assert_eq!("_-main", sanitize_tag("-main"));
+		assert_eq!(128, sanitize_tag(&"a".repeat(200)).len());
+	}
+
+	/// Local stand-in registry (registry:2 like), with the token flow:
+	/// `/v2/` requests need the `Bearer tk` token, given by `/token` for the `user:pass` basic auth.
+	/// Note: Without the `catalog`, the `/v2/_catalog` is not found (as on GHCR, Docker Hub or ECR).
+	fn start_registry(catalog: bool) -> String {
+		use std::collections::HashMap;
+		use tiny_http::{Header, Response, Server};
+
+		let server = Server::http("127.0.0.1:0").unwrap();
+		let port = server.server_addr().to_ip().unwrap().port();
+		std::thread::spawn(move || {
+			let mut blobs: HashMap<String, Vec<u8>> = HashMap::new();
+			// (repository, reference) -> manifest, with the reference as tag or digest
+			let mut manifests: HashMap<(String, String), Vec<u8>> = HashMap::new();
+			let mut upload_count = 0;
+			let header = |name: &str, value: &str| Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap();
+
+			for mut req in server.incoming_requests() {
+				let authorization = req.headers().iter().find(|h| h.field.equiv("Authorization")).map(|h| h.value.to_string());
+				let url = req.url().to_string();
+				let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+				let mut body = Vec::new();
+				req.as_reader().read_to_end(&mut body).unwrap();
+
+				// -- token endpoint (basic auth `user:pass`)
+				if path == "/token" {
+					let resp = match authorization.as_deref() {
+						Some("Basic dXNlcjpwYXNz") => Response::from_string(r#"{"token":"tk"}"#).boxed(),
+						_ => Response::empty(401).boxed(),
+					};
+					let _ = req.respond(resp);
+					continue;
+				}
+				if authorization.as_deref() != Some("Bearer tk") {
+					let challenge = format!(r#"Bearer realm="http://127.0.0.1:{}/token",service="mock",scope="any""#, port);
+					let _ = req.respond(Response::empty(401).with_header(header("WWW-Authenticate", &challenge)));
+					continue;
+				}
+
+				let path = path.strip_prefix("/v2/").unwrap_or_default().to_string();
+				let method = req.method().as_str().to_string();
+				let resp = if path == "_catalog" && !catalog {
+					Response::empty(404).boxed()
+				} else if path == "_catalog" {
+					let mut repositories: Vec<String> = manifests.keys().map(|(r, _)| r.to_string()).collect();
+					repositories.sort();
+					repositories.dedup();
+					Response::from_string(serde_json::json!({ "repositories": repositories }).to_string()).boxed()
+				} else if let Some(repository) = path.strip_suffix("/tags/list") {
+					let tags: Vec<&String> =
+						manifests.keys().filter(|(r, t)| r == repository && !t.starts_with("sha256:")).map(|(_, t)| t).collect();
+					match tags.is_empty() {
+						true => Response::empty(404).boxed(),
+						false => Response::from_string(serde_json::json!({ "name": repository, "tags": tags }).to_string()).boxed(),
+					}
+				} else if let Some((repository, _)) = path.split_once("/blobs/uploads/") {
+					match method.as_str() {
+						"POST" => {
+							upload_count += 1;
+							let location = format!("/v2/{}/blobs/uploads/{}", repository, upload_count);
+							Response::empty(202).with_header(header("Location", &location)).boxed()
+						}
+						_ => {
+							let digest = query.strip_prefix("digest=").unwrap_or_default().to_string();
+							assert_eq!(digest, format!("sha256:{}", sha256_bytes(&body)));
+							blobs.insert(digest, body);
+							Response::empty(201).boxed()
+						}
+					}
+				} else if let Some((_, digest)) = path.split_once("/blobs/") {
+					match (method.as_str(), blobs.get(digest)) {
+						("HEAD", Some(_)) => Response::empty(200).boxed(),
+						("GET", Some(blob)) => Response::from_data(blob.clone()).boxed(),
+						_ => Response::empty(404).boxed(),
+					}
+				} else if let Some((repository, reference)) = path.split_once("/manifests/") {
+					let key = (repository.to_string(), reference.to_string());
+					match method.as_str() {
+						"PUT" => {
+							let digest = format!("sha256:{}", sha256_bytes(&body));
+							manifests.insert((repository.to_string(), digest), body.clone());
+							manifests.insert(key, body);
+							Response::empty(201).boxed()
+						}
+						"DELETE" => {
+							let manifest = manifests.get(&key).cloned();
+							manifests.retain(|(r, _), m| r != repository || Some(&*m) != manifest.as_ref());
+							Response::empty(if manifest.is_some() { 202 } else { 404 }).boxed()
+						}
+						_ => match manifests.get(&key) {
+							Some(manifest) => {
+								let digest = format!("sha256:{}", sha256_bytes(manifest));
+								Response::from_data(manifest.clone()).with_header(header(DIGEST_HEADER, &digest)).boxed()
+							}
+							None => Response::empty(404).boxed(),
+						},
+					}
+				} else {
+					Response::empty(404).boxed()
+				};
+				let _ = req.respond(resp);
+			}
+		});
+
+		format!("oci://127.0.0.1:{}/team/binst", port)
+	}
+
+	#[tokio::test]
+	async fn test_oci_backend_publish() -> Result<()> {
+		let test_dir = super::super::tests::set_test_binst_root("oci")?;
+		let url = start_registry(true);
+		let info = |auth: Option<HttpAuth>| OciInfo {
+			auth,
+			..OciInfo::from_oci_url(&url, None).unwrap()
+		};
+		let backend = OciBackend::new(&info(Some(HttpAuth::Basic("user".to_string(), Some("pass".to_string())))));
+
+		// -- publish the version layers, and then the latest tag
+		let gz_file = test_dir.join("cool_cli.tar.gz");
+		std::fs::write(&gz_file, "gz content")?;
+		backend.put_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &gz_file).await?;
+		backend.put_text("cool_cli/x86/main/0.1.0/cool_cli.toml", "[package]").await?;
+		let latest_toml = "[latest]\nversion = \"0.1.0\"\n";
+		backend.put_text("cool_cli/x86/main/latest.toml", latest_toml).await?;
+
+		// -- the latest tag is the version artifact with the latest.toml layer
+		let (manifest, _) = backend.get_manifest("team/binst/cool_cli", "x86-main-latest").await?.unwrap();
+		let titles: Vec<&str> = manifest["layers"].as_array().unwrap().iter().filter_map(layer_title).collect();
+		assert_eq!(vec!["cool_cli.tar.gz", "cool_cli.toml", "latest.toml"], titles);
+		assert_eq!(ARTIFACT_TYPE, manifest["artifactType"]);
+
+		// -- get, exists, list
+		assert_eq!(Some(latest_toml.to_string()), backend.get_text("cool_cli/x86/main/latest.toml").await?);
+		let dest = test_dir.join("downloaded.tar.gz");
+		backend.get_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &dest).await?;
+		assert_eq!("gz content", std::fs::read_to_string(&dest)?);
+		assert!(backend.exists("cool_cli/x86/main/0.1.0/cool_cli.toml").await?);
+		assert!(!backend.exists("cool_cli/x86/main/0.1.1/cool_cli.toml").await?);
+		assert_eq!(None, backend.get_text("other_cli/x86/main/latest.toml").await?);
+		let expected_keys = vec![
+			"cool_cli/x86/main/0.1.0/cool_cli.tar.gz",
+			"cool_cli/x86/main/0.1.0/cool_cli.toml",
+			"cool_cli/x86/main/latest.toml",
+		];
+		assert_eq!(expected_keys, backend.list("cool_cli/").await?);
+		assert_eq!(expected_keys, backend.list("").await?);
+
+		// -- copy, and delete the layers (the artifact is deleted with its last layer)
+		backend.copy("cool_cli/x86/main/0.1.0/cool_cli.toml", "cool_cli/x86/main/0.1.1/cool_cli.toml").await?;
+		assert!(backend.exists("cool_cli/x86/main/0.1.1/cool_cli.toml").await?);
+		backend.delete("cool_cli/x86/main/0.1.0/cool_cli.toml").await?;
+		backend.delete("cool_cli/x86/main/0.1.0/cool_cli.tar.gz").await?;
+		backend.delete("cool_cli/x86/main/0.1.0/cool_cli.tar.gz").await?;
+		assert!(backend.get_manifest("team/binst/cool_cli", "x86-main-0.1.0").await?.is_none());
+		assert_eq!(
+			vec!["cool_cli/x86/main/0.1.1/cool_cli.toml", "cool_cli/x86/main/latest.toml"],
+			backend.list("cool_cli/x86/").await?
+		);
+
+		// -- an at-path dir with the tag of a version dir (`x86-main-0.1.1`), refused and not found
+		let res = backend.put_text("cool_cli/x86-main/0.1.1/cool_cli.toml", "[package]").await;
+		assert!(matches!(res, Err(Error::OciTagCollision(_, tag, dir)) if tag == "x86-main-0.1.1" && dir == "x86/main/0.1.1"));
+		assert_eq!(None, backend.get_text("cool_cli/x86-main/0.1.1/cool_cli.toml").await?);
+
+		// -- wrong or missing credentials for the token
+		let backend = OciBackend::new(&info(Some(HttpAuth::Basic("user".to_string(), None))));
+		let res = backend.get_text("cool_cli/x86/main/latest.toml").await;
+		assert!(matches!(res, Err(Error::HttpAuth(_, 401))));
+		let backend = OciBackend::new(&info(None));
+		let res = backend.put_text("cool_cli/x86/main/latest.toml", latest_toml).await;
+		assert!(matches!(res, Err(Error::HttpAuth(_, 401))));
+
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_oci_backend_no_catalog() -> Result<()> {
+		let test_dir = super::super::tests::set_test_binst_root("oci-no-catalog")?;
+		let url = start_registry(false);
+		let backend = OciBackend::new(&OciInfo {
+			auth: Some(HttpAuth::Basic("user".to_string(), Some("pass".to_string()))),
+			..OciInfo::from_oci_url(&url, None).unwrap()
+		});
+		backend.put_text("cool_cli/x86/main/0.1.0/cool_cli.toml", "[package]").await?;
+
+		// -- the bin keys without the catalog, but not all the bins
+		assert_eq!(vec!["cool_cli/x86/main/0.1.0/cool_cli.toml"], backend.list("cool_cli/").await?);
+		assert!(matches!(backend.list("").await, Err(Error::OciNoCatalog(_, 404))));
+
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
+}
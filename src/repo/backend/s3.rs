@@ -0,0 +1,70 @@
+//! S3 repo backend (see `s3w` for the S3 client wrapper)
+
+use super::RepoBackend;
+use crate::repo::s3w::{get_full_key_and_s3_url, new_repo_bucket, Bucket};
+use crate::repo::{Error, Result, S3Info};
+use async_trait::async_trait;
+use std::path::Path;
+
+pub struct S3Backend {
+	s3_info: S3Info,
+	bucket: Bucket,
+}
+
+impl S3Backend {
+	pub async fn new(s3_info: &S3Info) -> Result<Self> {
+		let bucket = new_repo_bucket(s3_info.profile.clone()).await?;
+		Ok(S3Backend {
+			s3_info: s3_info.clone(),
+			bucket,
+		})
+	}
+}
+
+#[async_trait]
+impl RepoBackend for S3Backend {
+	fn key_url(&self, key: &str) -> String {
+		get_full_key_and_s3_url(&self.s3_info, key).1
+	}
+
+	async fn get_text(&self, key: &str) -> Result<Option<String>> {
+		self.bucket.download_to_string_opt(&self.s3_info, key).await
+	}
+
+	async fn get_file(&self, key: &str, dest: &Path) -> Result<()> {
+		match self.bucket.download_to_file(&self.s3_info, key, dest).await {
+			Ok(_) => Ok(()),
+			Err(Error::AwsServiceError(code)) if code == "NotFound" || code == "NoSuchKey" => {
+				Err(Error::RepoKeyNotFound(self.key_url(key)))
+			}
+			Err(ex) => Err(ex),
+		}
+	}
+
+	async fn put_file(&self, key: &str, file: &Path) -> Result<()> {
+		self.bucket.upload_file(&self.s3_info, key, file).await?;
+		Ok(())
+	}
+
+	async fn put_text(&self, key: &str, content: &str) -> Result<()> {
+		self.bucket.upload_text(&self.s3_info, key, content.to_string(), None).await?;
+		Ok(())
+	}
+
+	async fn exists(&self, key: &str) -> Result<bool> {
+		self.bucket.exists(&self.s3_info, key).await
+	}
+
+	async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+		self.bucket.list(&self.s3_info, prefix).await
+	}
+
+	async fn copy(&self, key: &str, to_key: &str) -> Result<()> {
+		self.bucket.copy(&self.s3_info, key, to_key).await?;
+		Ok(())
+	}
+
+	async fn delete(&self, key: &str) -> Result<()> {
+		self.bucket.delete(&self.s3_info, key).await
+	}
+}
@@ -0,0 +1,311 @@
+//! Sftp repo backend, with the `sftp` command in batch mode (`sftp -b -`)
+//!
+//! Note: `BatchMode=yes` (no password prompt, the ssh agent or key file must be set),
+//!       and `StrictHostKeyChecking=yes` (the host key must be in the known hosts).
+
+use super::RepoBackend;
+use crate::paths::binst_tmp_dir;
+use crate::prelude::*;
+use crate::repo::{Error, Result, SftpInfo};
+use crate::utils::{is_verbose, sha256_str};
+use async_trait::async_trait;
+use std::fs::{read_to_string, remove_file, write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+const SFTP_PROMPT: &str = "sftp> ";
+
+pub struct SftpBackend {
+	sftp_info: SftpInfo,
+}
+
+impl SftpBackend {
+	pub fn new(sftp_info: &SftpInfo) -> Self {
+		SftpBackend {
+			sftp_info: sftp_info.clone(),
+		}
+	}
+
+	fn remote_path(&self, key: &str) -> String {
+		match self.sftp_info.base.as_str() {
+			"" => key.to_string(),
+			"/" => f!("/{key}"),
+			base => f!("{base}/{key}"),
+		}
+	}
+
+	/// Run the sftp batch commands, and returns the stdout (commands are echoed as `sftp> ...` lines).
+	/// Note: The sftp command stops and fails on the first failing command, unless prefixed with `-`.
+	async fn run_batch(&self, commands: &[String]) -> Result<String> {
+		let info = &self.sftp_info;
+		let mut cmd = Command::new("sftp");
+		cmd.args(["-b", "-", "-o", "BatchMode=yes", "-o", "StrictHostKeyChecking=yes"]);
+		if let Some(port) = info.port {
+			cmd.arg("-P").arg(port.to_string());
+		}
+		if let Some(key_file) = &info.key_file {
+			cmd.arg("-i").arg(key_file);
+		}
+		if let Some(known_hosts) = &info.known_hosts {
+			cmd.arg("-o").arg(f!("UserKnownHostsFile={known_hosts}"));
+		}
+		cmd.arg(&info.destination);
+		cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+		if is_verbose() {
+			println!("  sftp {} - {}", info.destination, commands.join(" ; "));
+		}
+
+		let mut child = cmd.spawn().map_err(|ex| Error::SftpFailed(info.url.to_string(), ex.to_string()))?;
+		if let Some(mut stdin) = child.stdin.take() {
+			stdin.write_all(f!("{}\n", commands.join("\n")).as_bytes()).await?;
+		}
+		let output = child.wait_with_output().await?;
+
+		if !output.status.success() {
+			let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+			return Err(Error::SftpFailed(info.url.to_string(), stderr));
+		}
+		Ok(String::from_utf8_lossy(&output.stdout).to_string())
+	}
+
+	/// A local tmp file for the transfers (e.g., put_text, copy).
+	fn tmp_file(&self, key: &str) -> Result<PathBuf> {
+		let name = f!("sftp-{}-{}", std::process::id(), &sha256_str(key)[..16]);
+		Ok(binst_tmp_dir(None)?.join(name))
+	}
+}
+
+#[async_trait]
+impl RepoBackend for SftpBackend {
+	fn key_url(&self, key: &str) -> String {
+		f!("{}/{key}", self.sftp_info.url)
+	}
+
+	async fn get_text(&self, key: &str) -> Result<Option<String>> {
+		let tmp_file = self.tmp_file(key)?;
+		let res = match self.get_file(key, &tmp_file).await {
+			Ok(_) => Ok(Some(read_to_string(&tmp_file)?)),
+			Err(Error::RepoKeyNotFound(_)) => Ok(None),
+			Err(ex) => Err(ex),
+		};
+		let _ = remove_file(&tmp_file);
+		res
+	}
+
+	async fn get_file(&self, key: &str, dest: &Path) -> Result<()> {
+		let remote = self.remote_path(key);
+		let cmd = f!("get {} {}", quote(&remote), quote(&dest.to_string_lossy()));
+		match self.run_batch(&[cmd]).await {
+			Err(Error::SftpFailed(_, cause)) if is_not_found(&cause) => Err(Error::RepoKeyNotFound(self.key_url(key))),
+			res => res.map(|_| ()),
+		}
+	}
+
+	/// Note: Uploaded to a tmp file, and then renamed in place (posix-rename, never half written).
+	async fn put_file(&self, key: &str, file: &Path) -> Result<()> {
+		let remote = self.remote_path(key);
+		let remote_tmp = f!("{remote}.tmp-{}", std::process::id());
+
+		// create the parent dirs (`-` to ignore the already existing ones)
+		let mut commands: Vec<String> = parent_dirs(&remote).iter().map(|dir| f!("-mkdir {}", quote(dir))).collect();
+		commands.push(f!("put {} {}", quote(&file.to_string_lossy()), quote(&remote_tmp)));
+		commands.push(f!("rename {} {}", quote(&remote_tmp), quote(&remote)));
+
+		self.run_batch(&commands).await?;
+		Ok(())
+	}
+
+	/// Note: Uploaded to a tmp dir, and then renamed in place (never half populated, nor mixed).
+	async fn put_dir(&self, dir_key: &str, files: &[(&str, &Path)]) -> Result<()> {
+		let remote_dir = self.remote_path(dir_key);
+		let (parent, dir_name) = remote_dir.rsplit_once('/').unwrap_or(("", &remote_dir));
+		let sibling = |name: String| match parent {
+			"" => name,
+			parent => f!("{parent}/{name}"),
+		};
+		let staged_dir = sibling(f!(".{dir_name}.tmp-{}", std::process::id()));
+		let replaced_dir = sibling(f!(".{dir_name}.old-{}", std::process::id()));
+
+		// create the parent dirs and the staged dir (`-` to ignore the already existing ones)
+		let mut commands: Vec<String> = parent_dirs(&remote_dir).iter().map(|dir| f!("-mkdir {}", quote(dir))).collect();
+		commands.push(f!("-mkdir {}", quote(&staged_dir)));
+		for (file_name, file) in files {
+			commands.push(f!("put {} {}", quote(&file.to_string_lossy()), quote(&f!("{staged_dir}/{file_name}"))));
+		}
+		// move the eventual previous dir out (`-` for a new dir), the staged one in, and remove the previous one
+		commands.push(f!("-rename {} {}", quote(&remote_dir), quote(&replaced_dir)));
+		commands.push(f!("rename {} {}", quote(&staged_dir), quote(&remote_dir)));
+		commands.push(f!("-rm {}", quote(&f!("{replaced_dir}/*"))));
+		commands.push(f!("-rmdir {}", quote(&replaced_dir)));
+
+		self.run_batch(&commands).await?;
+		Ok(())
+	}
+
+	async fn put_text(&self, key: &str, content: &str) -> Result<()> {
+		let tmp_file = self.tmp_file(key)?;
+		write(&tmp_file, content)?;
+		let res = self.put_file(key, &tmp_file).await;
+		let _ = remove_file(&tmp_file);
+		res
+	}
+
+	async fn exists(&self, key: &str) -> Result<bool> {
+		let cmd = f!("ls {}", quote(&self.remote_path(key)));
+		match self.run_batch(&[cmd]).await {
+			Ok(_) => Ok(true),
+			Err(Error::SftpFailed(_, cause)) if is_not_found(&cause) => Ok(false),
+			Err(ex) => Err(ex),
+		}
+	}
+
+	/// Note: One sftp run per dir level (all the dirs of a level are listed in the same batch).
+	async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+		// start from the prefix dir (e.g., `cool_cli/x86` for `cool_cli/x86/ma`)
+		let start_key = prefix.rsplit_once('/').map(|(dir, _)| dir.to_string()).unwrap_or_default();
+		let mut keys = Vec::new();
+		let mut dir_keys = vec![start_key];
+
+		while !dir_keys.is_empty() {
+			let commands: Vec<String> = dir_keys
+				.iter()
+				.map(|dir_key| {
+					let dir = self.remote_path(dir_key);
+					let dir = match dir.trim_end_matches('/') {
+						"" if dir.starts_with('/') => "/",
+						"" => ".",
+						dir => dir,
+					};
+					f!("-ls -la {}", quote(dir))
+				})
+				.collect();
+			let output = self.run_batch(&commands).await?;
+
+			let mut next_dir_keys = Vec::new();
+			for (dir_key, entries) in dir_keys.iter().zip(parse_ls_sections(&output)) {
+				for (name, is_dir) in entries {
+					let key = match dir_key.is_empty() {
+						true => name,
+						false => f!("{dir_key}/{name}"),
+					};
+					if !key.starts_with(prefix) && !prefix.starts_with(&f!("{key}/")) {
+						continue;
+					}
+					match is_dir {
+						true => next_dir_keys.push(key),
+						false => keys.push(key),
+					}
+				}
+			}
+			dir_keys = next_dir_keys;
+		}
+
+		keys.sort();
+		Ok(keys)
+	}
+
+	/// Note: Downloaded and uploaded back (no server side copy in sftp v3).
+	async fn copy(&self, key: &str, to_key: &str) -> Result<()> {
+		let tmp_file = self.tmp_file(key)?;
+		let res = match self.get_file(key, &tmp_file).await {
+			Ok(_) => self.put_file(to_key, &tmp_file).await,
+			Err(ex) => Err(ex),
+		};
+		let _ = remove_file(&tmp_file);
+		res
+	}
+
+	async fn delete(&self, key: &str) -> Result<()> {
+		self.run_batch(&[f!("-rm {}", quote(&self.remote_path(key)))]).await?;
+		Ok(())
+	}
+}
+
+// region:    --- Sftp Utils
+/// Quote the path for the sftp batch commands.
+fn quote(path: &str) -> String {
+	f!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn is_not_found(cause: &str) -> bool {
+	cause.contains("not found") || cause.contains("No such file")
+}
+
+/// The parent dirs of the remote path, from the top (e.g., `/srv`, `/srv/repo` for `/srv/repo/latest.toml`).
+fn parent_dirs(remote_path: &str) -> Vec<String> {
+	let mut dirs = Vec::new();
+	let mut dir = String::new();
+	let parts: Vec<&str> = remote_path.split('/').collect();
+	for (idx, part) in parts[..parts.len().saturating_sub(1)].iter().enumerate() {
+		dir = match idx {
+			0 => part.to_string(),
+			_ => f!("{dir}/{part}"),
+		};
+		if !part.is_empty() {
+			dirs.push(dir.clone());
+		}
+	}
+	dirs
+}
+
+/// Parse the `sftp> ls -la ...` sections of the batch output, as `(name, is_dir)` entries per section.
+/// Note: Failed `-ls` (e.g., missing dir) have an empty section.
+fn parse_ls_sections(output: &str) -> Vec<Vec<(String, bool)>> {
+	let mut sections: Vec<Vec<(String, bool)>> = Vec::new();
+	for line in output.lines() {
+		if line.starts_with(SFTP_PROMPT) {
+			sections.push(Vec::new());
+			continue;
+		}
+		let Some(section) = sections.last_mut() else {
+			continue;
+		};
+		// e.g., `drwxr-xr-x    2 deploy   deploy       4096 Jan  1 10:00 0.1.0`
+		let fields: Vec<&str> = line.split_whitespace().collect();
+		if fields.len() < 9 {
+			continue;
+		}
+		let name = fields[8..].join(" ");
+		if name == "." || name == ".." || name.starts_with('.') {
+			continue;
+		}
+		let name = name.rsplit('/').next().unwrap_or_default().to_string();
+		match line.chars().next() {
+			Some('d') => section.push((name, true)),
+			Some('-') => section.push((name, false)),
+			_ => (),
+		}
+	}
+	sections
+}
+// endregion: --- Sftp Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_sftp_utils() {
+		assert_eq!(vec!["/srv", "/srv/repo"], parent_dirs("/srv/repo/latest.toml"));
+		assert_eq!(vec!["repo", "repo/cool_cli"], parent_dirs("repo/cool_cli/latest.toml"));
+		assert_eq!(r#""/srv/my \"repo\"""#, quote(r#"/srv/my "repo""#));
+
+		let output = "sftp> -ls -la \"/srv/repo/cool_cli\"
+drwxr-xr-x    3 deploy   deploy       4096 Jan  1 10:00 .
+drwxr-xr-x    3 deploy   deploy       4096 Jan  1 10:00 ..
+drwxr-xr-x    3 deploy   deploy       4096 Jan  1 10:00 x86_64-unknown-linux-gnu
+sftp> -ls -la \"/srv/repo/other\"
+sftp> -ls -la \"/srv/repo/cool_cli/x86_64-unknown-linux-gnu/main\"
+-rw-r--r--    1 deploy   deploy         25 Jan  1 10:00 latest.toml
+-rw-r--r--    1 deploy   deploy         25 Jan  1 10:00 .latest.toml.tmp-123
+";
+		let sections = parse_ls_sections(output);
+		assert_eq!(3, sections.len());
+		assert_eq!(vec![("x86_64-unknown-linux-gnu".to_string(), true)], sections[0]);
+		assert!(sections[1].is_empty());
+		assert_eq!(vec![("latest.toml".to_string(), false)], sections[2]);
+	}
+}
@@ -0,0 +1,247 @@
+//! Local package cache (`~/.binst/cache/` by default, or `BINST_CACHE_DIR`)
+//!
+//! Layout: `cache/<repo_key>/<bin_name>/<target>/<version>/<sha256>/<bin_name>.tar.gz`
+//! (`<sha256>` is `unverified` for packages published without checksum)
+
+use crate::paths::binst_cache_dir;
+use crate::prelude::*;
+use crate::repo::{extract_stream, get_version_part, Result};
+use crate::utils::{is_verbose, sha256_file, sha256_str};
+use semver::Version;
+use std::env;
+use std::fs::{copy, create_dir_all, read_dir, remove_dir_all, remove_file, rename};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const ENV_CACHE_MAX_MB: &str = "BINST_CACHE_MAX_MB";
+const DEFAULT_CACHE_MAX_MB: u64 = 2048;
+const UNVERIFIED: &str = "unverified";
+/// Infix of the in-flight `put` files (`<bin_name>.tar.gz.tmp-<pid>`)
+const TMP_INFIX: &str = ".tmp-";
+
+/// A cache entry for a given repo/bin/target/version/checksum.
+pub struct CacheEntry {
+	dir: PathBuf,
+	gz_name: String,
+	sha256: Option<String>,
+}
+
+/// Builders
+impl CacheEntry {
+	pub fn new(repo_url: &str, bin_name: &str, target: &str, version: &Version, sha256: Option<&str>) -> Result<Self> {
+		let dir = bin_target_cache_dir(repo_url, bin_name, target)?
+			.join(get_version_part(version))
+			.join(sha256.unwrap_or(UNVERIFIED));
+
+		Ok(CacheEntry {
+			dir,
+			gz_name: f!("{bin_name}.tar.gz"),
+			sha256: sha256.map(|s| s.to_string()),
+		})
+	}
+
+	/// Returns the entry of the latest cached version for this stream, or of the given version (for offline install).
+	pub fn find_latest(
+		repo_url: &str,
+		bin_name: &str,
+		target: &str,
+		stream: &str,
+		version: Option<&Version>,
+	) -> Option<(Version, Self)> {
+		let target_dir = bin_target_cache_dir(repo_url, bin_name, target).ok()?;
+		let gz_name = f!("{bin_name}.tar.gz");
+
+		let mut candidates: Vec<(Version, PathBuf)> = read_dir(target_dir)
+			.ok()?
+			.filter_map(|entry| entry.ok())
+			.filter_map(|entry| {
+				let entry_version = Version::parse(&entry.file_name().to_string_lossy()).ok()?;
+				let is_match = match version {
+					Some(version) => &entry_version == version,
+					None => extract_stream(&entry_version) == stream,
+				};
+				is_match.then_some((entry_version, entry.path()))
+			})
+			.collect();
+		candidates.sort_by(|a, b| b.0.cmp(&a.0));
+
+		// first version dir which has a cached gz file
+		candidates.into_iter().find_map(|(version, version_dir)| {
+			let dir = read_dir(version_dir)
+				.ok()?
+				.filter_map(|entry| entry.ok())
+				.map(|entry| entry.path())
+				.find(|dir| dir.join(&gz_name).is_file())?;
+			let sha256 = dir
+				.file_name()
+				.map(|name| name.to_string_lossy().to_string())
+				.filter(|name| name != UNVERIFIED);
+			let entry = CacheEntry {
+				dir,
+				gz_name: gz_name.clone(),
+				sha256,
+			};
+			Some((version, entry))
+		})
+	}
+}
+
+impl CacheEntry {
+	pub fn gz_path(&self) -> PathBuf {
+		self.dir.join(&self.gz_name)
+	}
+
+	/// Returns the cached gz file path if present (and still matching its checksum).
+	pub fn get(&self) -> Result<Option<PathBuf>> {
+		let gz_path = self.gz_path();
+		if !gz_path.is_file() {
+			return Ok(None);
+		}
+
+		if let Some(sha256) = &self.sha256 {
+			if &sha256_file(&gz_path)? != sha256 {
+				println!("WARNING - corrupted cache entry removed {}", gz_path.to_string_lossy());
+				remove_file(&gz_path)?;
+				return Ok(None);
+			}
+		}
+
+		Ok(Some(gz_path))
+	}
+
+	/// Copy the (verified) gz file into the cache, and prune the cache if over its max size.
+	pub fn put(&self, gz_file: &Path) -> Result<()> {
+		create_dir_all(&self.dir)?;
+		// copy then rename, so that a concurrent reader never sees a partial file
+		let tmp_path = self.dir.join(f!("{}{TMP_INFIX}{}", self.gz_name, std::process::id()));
+		copy(gz_file, &tmp_path)?;
+		rename(&tmp_path, self.gz_path())?;
+
+		prune_cache(&self.gz_path())?;
+		Ok(())
+	}
+}
+
+/// Remove all the cache entries. Returns the number of bytes freed.
+pub fn clean_cache() -> Result<u64> {
+	let cache_dir = binst_cache_dir()?;
+	if !cache_dir.is_dir() {
+		return Ok(0);
+	}
+
+	let size = list_cached_files(&cache_dir)?.iter().map(|(_, size, _)| size).sum();
+	for entry in read_dir(&cache_dir)? {
+		let path = entry?.path();
+		if path.is_dir() {
+			remove_dir_all(&path)?;
+		} else {
+			remove_file(&path)?;
+		}
+	}
+
+	Ok(size)
+}
+
+// region:    --- Cache Private Utils
+fn bin_target_cache_dir(repo_url: &str, bin_name: &str, target: &str) -> Result<PathBuf> {
+	Ok(binst_cache_dir()?.join(repo_key(repo_url)).join(bin_name).join(target))
+}
+
+/// Filesystem friendly key for a repo url, a readable prefix and the short url hash
+/// (e.g., `s3://bucket/base` to `s3_bucket_base_<hash>`), as the prefix alone is not unique
+/// (e.g., `s3://a/b_c` and `s3://a_b/c`).
+fn repo_key(repo_url: &str) -> String {
+	let prefix: String = repo_url
+		.chars()
+		.map(|c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+			true => c,
+			false => '_',
+		})
+		.collect();
+	let prefix = prefix.split('_').filter(|p| !p.is_empty()).collect::<Vec<_>>().join("_");
+	f!("{prefix}_{}", &sha256_str(repo_url)[..16])
+}
+
+fn cache_max_bytes() -> u64 {
+	let max_mb = env::var(ENV_CACHE_MAX_MB)
+		.ok()
+		.and_then(|v| v.trim().parse::<u64>().ok())
+		.unwrap_or(DEFAULT_CACHE_MAX_MB);
+	max_mb * 1024 * 1024
+}
+
+/// Remove the oldest cached files until the cache is under its max size (keeping the `keep` file).
+/// Note: The tmp files are skipped, as they can be the in-flight `put` of another process.
+fn prune_cache(keep: &Path) -> Result<()> {
+	let max_bytes = cache_max_bytes();
+	let mut files = list_cached_files(&binst_cache_dir()?)?;
+	let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+
+	// oldest first
+	files.sort_by_key(|(_, _, modified)| *modified);
+	for (path, size, _) in files {
+		if total <= max_bytes {
+			break;
+		}
+		if path == keep || is_tmp_file(&path) {
+			continue;
+		}
+		if is_verbose() {
+			println!("  cache max size reached, removing {}", path.to_string_lossy());
+		}
+		remove_file(&path)?;
+		total -= size;
+	}
+
+	Ok(())
+}
+
+fn is_tmp_file(path: &Path) -> bool {
+	path.file_name().is_some_and(|name| name.to_string_lossy().contains(TMP_INFIX))
+}
+
+/// Returns all the cached files with their size and modified time.
+fn list_cached_files(dir: &Path) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+	let mut files = Vec::new();
+	if !dir.is_dir() {
+		return Ok(files);
+	}
+
+	for entry in read_dir(dir)? {
+		let path = entry?.path();
+		if path.is_dir() {
+			files.extend(list_cached_files(&path)?);
+		} else {
+			let meta = path.metadata()?;
+			files.push((path, meta.len(), meta.modified()?));
+		}
+	}
+
+	Ok(files)
+}
+// endregion: --- Cache Private Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_cache_repo_key() {
+		assert!(repo_key("s3://my-bucket/repo").starts_with("s3_my-bucket_repo_"));
+		assert!(repo_key("https://repo.binst.io/").starts_with("https_repo.binst.io_"));
+		assert!(repo_key("/tmp/my repo").starts_with("tmp_my_repo_"));
+
+		// -- same prefix, distinct keys
+		let keys = [repo_key("s3://a/b_c"), repo_key("s3://a/b/c"), repo_key("s3://a_b/c")];
+		assert!(keys.iter().all(|key| key.starts_with("s3_a_b_c_")));
+		assert_ne!(keys[0], keys[1]);
+		assert_ne!(keys[0], keys[2]);
+		assert_ne!(keys[1], keys[2]);
+	}
+
+	#[test]
+	fn test_cache_is_tmp_file() {
+		assert!(is_tmp_file(Path::new("/cache/s3_a/cool_cli/x86/0.1.0/abc/cool_cli.tar.gz.tmp-1234")));
+		assert!(!is_tmp_file(Path::new("/cache/s3_a/cool_cli/x86/0.1.0/abc/cool_cli.tar.gz")));
+	}
+}
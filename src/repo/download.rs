@@ -0,0 +1,75 @@
+//! Partial (resumable) download files, kept under `~/.binst/tmp/downloads/`
+
+use crate::paths::binst_tmp_dir;
+use crate::prelude::*;
+use crate::repo::Result;
+use crate::utils::{get_toml_value_as_string, sha256_str};
+use std::fs::{read_to_string, remove_file, rename, write, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+const DOWNLOADS_DIR: &str = "downloads";
+
+/// A `.part` file with its `.part.toml` meta file (source url and validator).
+/// The validator (ETag or Last-Modified) makes sure that the bytes appended on resume
+/// come from the same remote object.
+pub struct PartialDownload {
+	url: String,
+	part_path: PathBuf,
+	meta_path: PathBuf,
+}
+
+impl PartialDownload {
+	pub fn new(url: &str) -> Result<Self> {
+		let dir = binst_tmp_dir(Some(DOWNLOADS_DIR))?;
+		let name = &sha256_str(url)[..16];
+		Ok(PartialDownload {
+			url: url.to_string(),
+			part_path: dir.join(f!("{name}.part")),
+			meta_path: dir.join(f!("{name}.part.toml")),
+		})
+	}
+}
+
+impl PartialDownload {
+	/// Returns the validator and byte offset to resume from, if any.
+	pub fn resume_point(&self) -> Option<(String, u64)> {
+		let content = read_to_string(&self.meta_path).ok()?;
+		let meta: Value = toml::from_str(&content).ok()?;
+		let url = get_toml_value_as_string(&meta, &["url"]).ok()?;
+		let validator = get_toml_value_as_string(&meta, &["validator"]).ok()?;
+		let offset = self.part_path.metadata().ok()?.len();
+
+		(url == self.url && offset > 0).then_some((validator, offset))
+	}
+
+	/// Start a new download from zero. The partial is only resumable later if a validator is given.
+	pub fn start(&self, validator: Option<&str>) -> Result<File> {
+		let _ = remove_file(&self.meta_path);
+		let file = File::create(&self.part_path)?;
+		if let Some(validator) = validator {
+			let meta = f!("url = {:?}\nvalidator = {:?}\n", self.url, validator);
+			write(&self.meta_path, meta)?;
+		}
+		Ok(file)
+	}
+
+	/// Open the partial file to append the remaining bytes.
+	pub fn append(&self) -> Result<File> {
+		let file = OpenOptions::new().append(true).open(&self.part_path)?;
+		Ok(file)
+	}
+
+	/// Move the completed partial file to its destination.
+	pub fn complete(self, dest: &Path) -> Result<()> {
+		rename(&self.part_path, dest)?;
+		let _ = remove_file(&self.meta_path);
+		Ok(())
+	}
+
+	/// Remove the partial file and its meta (e.g., on checksum mismatch).
+	pub fn discard(&self) {
+		let _ = remove_file(&self.part_path);
+		let _ = remove_file(&self.meta_path);
+	}
+}
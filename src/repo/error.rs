@@ -5,6 +5,7 @@ use aws_smithy_http::result::CreateUnhandledError;
 pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
 	// region:    --- S3
 	#[error(
@@ -22,9 +23,6 @@ pub enum Error {
 	#[error("No environment variable '{0}'")]
 	NoCredentialEnv(String),
 
-	#[error("http protocols not supported for publish")]
-	HttpProtocolNotSupportedForPublish,
-
 	#[error("Credential profile config key {0} not found")]
 	NoCredentialConfig(String),
 
@@ -33,9 +31,55 @@ pub enum Error {
 	#[error("Invalid S3 repo url {0}")]
 	RepoInvalidS3(String),
 
+	#[error("Invalid sftp repo url {0}. Must be sftp://[user@]host[:port]/base_dir")]
+	RepoInvalidSftp(String),
+
+	#[error("sftp command failed for {0}. Cause: {1}")]
+	SftpFailed(String, String),
+
+	#[error("Invalid oci repo url {0}. Must be oci://registry[:port]/namespace")]
+	RepoInvalidOci(String),
+
+	#[error("Invalid oci manifest for {0}. Cause: {1}")]
+	OciInvalidManifest(String, String),
+
+	#[error("The OCI registry {0} does not serve the catalog (http {1}), so its bins cannot be listed. Use --bin")]
+	OciNoCatalog(String, u16),
+
+	#[error("Cannot publish {0}, its OCI tag '{1}' is the one of the '{2}' dir (the '/' are '-' in the tags). Publish at another path")]
+	OciTagCollision(String, String, String),
+
+	#[error("Invalid file repo url {0}. Must be file:///path or file://localhost/path (absolute)")]
+	RepoInvalidFile(String),
+
+	#[error("Invalid git repo url {0}. Must be git+ssh://[user@]host[:port]/path or git+file:///path (with an optional #branch)")]
+	RepoInvalidGit(String),
+
+	#[error("git command failed for {0}. Cause: {1}")]
+	GitFailed(String, String),
+
+	#[error("Invalid github repo {0}. Must be github:owner/repo")]
+	RepoInvalidGithub(String),
+
+	#[error("No release asset of {0} matches the target {1}. Assets: {2}")]
+	GithubNoAssetForTarget(String, String, String),
+
+	#[error("Binary '{0}' not found in the release asset {1}")]
+	GithubBinNotInAsset(String, String),
+
+	#[error("Unsupported repo scheme '{0}://' in {1}. Must be s3://, http://, https://, sftp://, oci://, git+ssh://, git+file://, file://, github:, or a local dir path")]
+	RepoUnsupportedScheme(String, String),
+
+	#[error("No home dir to expand the repo path {0}")]
+	RepoNoHomeDir(String),
+
 	/// Simplified AWS Error message with code.
 	#[error("AWS Error. Code: {0}")]
 	AwsServiceError(String), // Code
+
+	/// Transient AWS failure (timeout, dispatch failure, throttling, ...), can be retried.
+	#[error("AWS transient error: {0}")]
+	AwsTransientError(String),
 	// endregion: --- S3
 
 	// region:    --- Others
@@ -48,6 +92,42 @@ pub enum Error {
 	#[error("The package .tar.gz file was not found at {0}")]
 	OriginTarGzNotFound(String),
 
+	#[error("Access denied (http status {1}) for {0}. Check the repo credentials and permissions.")]
+	HttpAuth(String, u16),
+
+	#[error("Server error (http status {1}) for {0}. Might be temporary, retry later.")]
+	HttpServerError(String, u16),
+
+	#[error("Unexpected http status {1} for {0}")]
+	HttpStatus(String, u16),
+
+	#[error("Invalid http partial content range for {0}")]
+	HttpInvalidRange(String),
+
+	#[error("Repo key not found {0}")]
+	RepoKeyNotFound(String),
+
+	#[error("Repo operation '{0}' not supported by repo {1}")]
+	RepoOperationNotSupported(String, String),
+
+	#[error("Checksum mismatch for {0}\n  expected sha256: {1}\n    actual sha256: {2}")]
+	ChecksumMismatch(String, String, String),
+
+	#[error("Package from the mirror {0} refused, no sha256 to check it (from the install repo package toml, or the binst.lock)")]
+	MirrorNoChecksum(String),
+
+	#[error("Package not found in the local cache (offline) {0}")]
+	NotInCache(String),
+
+	#[error("Unsafe package archive entry '{0}': {1}")]
+	UnsafeArchiveEntry(String, String),
+
+	#[error("{0} exists, and was not installed by binst (not a link to a binst package, nor a binst shim). Remove it first to install over it")]
+	BinNotFromBinst(String),
+
+	#[error("No stable binst path for the shims (binst {0} is a package version, without an active one). Run 'binst self' first")]
+	ShimNoStableBinst(String),
+
 	#[error("The unpacked binary file not found at {0}")]
 	UnpackedBinFileNotFound(String),
 
@@ -63,6 +143,9 @@ pub enum Error {
 	#[error(transparent)]
 	UtilsError(#[from] crate::utils::Error),
 
+	#[error(transparent)]
+	ConfigError(#[from] crate::config::Error),
+
 	#[error(transparent)]
 	ReqwestError(#[from] reqwest::Error),
 
@@ -71,14 +154,46 @@ pub enum Error {
 	// endregion: --- Others
 }
 
+impl Error {
+	/// Returns true if the error is transient, and the operation could be retried.
+	pub fn is_retryable(&self) -> bool {
+		match self {
+			Error::HttpServerError(..) | Error::HttpInvalidRange(_) | Error::AwsTransientError(_) | Error::ByteStream(_) => {
+				true
+			}
+			// 408 Request Timeout and 429 Too Many Requests
+			Error::HttpStatus(_, status) => *status == 408 || *status == 429,
+			Error::ReqwestError(ex) => ex.is_timeout() || ex.is_connect() || ex.is_request() || ex.is_body(),
+			_ => false,
+		}
+	}
+}
+
+/// AWS error codes which are worth a retry.
+const AWS_TRANSIENT_CODES: &[&str] = &[
+	"InternalError",
+	"RequestTimeout",
+	"ServiceUnavailable",
+	"SlowDown",
+	"ThrottlingException",
+];
+
 /// Generic for AWS Error simple reporting
 impl<E> From<SdkError<E>> for Error
 where
 	E: std::error::Error + Send + Sync + CreateUnhandledError + ProvideErrorKind + 'static,
 {
 	fn from(val: SdkError<E>) -> Self {
+		// Note: Those failures happen before getting a service response, so they are worth a retry.
+		if let SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) = val {
+			return Error::AwsTransientError(val.to_string());
+		}
+
 		let se = val.into_service_error();
 		let code = se.code().unwrap_or_default().to_string();
+		if se.retryable_error_kind().is_some() || AWS_TRANSIENT_CODES.contains(&code.as_str()) {
+			return Error::AwsTransientError(code);
+		}
 		Error::AwsServiceError(code)
 		// Note: Unforuntately, it seems there is no trait for the .message(), so,
 		//       cannot get it when using the generic way. Can be implemented for each E type.
@@ -1,114 +1,133 @@
-use super::s3w::new_repo_bucket;
-use super::{BinRepo, RepoInfo, S3Info};
-use crate::paths::binst_package_bin_dir;
-use crate::repo::s3w::get_full_key_and_s3_url;
-use crate::repo::{create_bin_symlink, create_install_toml, get_version_part, make_bin_temp_dir};
+use super::backend::new_repo_backend;
+use super::cache::CacheEntry;
+use super::unpack::unpack_tar_gz;
+use super::{BinRepo, RepoInfo, LATEST_TOML};
+use crate::paths::binst_package_dir;
+use crate::repo::{activate_package, create_install_toml, get_version_part, make_bin_temp_dir};
 use crate::repo::{Error, Result};
-use crate::utils::{get_toml_value_as_string, safer_remove_dir};
-use libflate::gzip::Decoder;
+use crate::utils::{get_toml_value_as_string, is_verbose, safer_remove_dir, sha256_file};
 use semver::Version;
-use std::fs::{copy, create_dir_all, read_to_string, remove_file, File};
-use std::io::{BufReader, Cursor, Read, Write};
+use std::fs::{copy, create_dir_all, remove_file, rename};
+use std::future::Future;
 use std::path::{Path, PathBuf};
-use tar::Archive;
 use toml::Value;
 
-const LATEST_TOML: &str = "latest.toml";
+/// The installed package info (e.g., for the `binst.lock`)
+#[derive(Debug)]
+pub struct Installed {
+	pub version: Version,
+	pub sha256: String,
+	pub target: String,
+}
 
 // repo install method(s)
 impl BinRepo {
-	pub async fn install(&self, stream: String) -> Result<()> {
-		// create the tempdir
+	/// Install the latest version of the stream.
+	pub async fn install(&self, stream: String) -> Result<Installed> {
+		self.install_version(stream, None, None).await
+	}
+
+	/// Install the given version (latest of the stream if None).
+	/// When `expected_sha256` is defined (e.g., from `binst.lock`), the package must match it.
+	pub async fn install_version(
+		&self,
+		stream: String,
+		version: Option<&Version>,
+		expected_sha256: Option<&str>,
+	) -> Result<Installed> {
+		// create the tempdir (removed on drop, even on error)
 		let tmp_dir = make_bin_temp_dir(&self.bin_name)?;
 
-		//// download the package tar files to the folder
-		let (download_url, version, tmp_gz) = match &self.install_repo {
-			RepoInfo::Local(local_repo_origin) => {
-				self.download_from_local(local_repo_origin, &tmp_dir, &stream).await?
-			}
-			RepoInfo::S3(s3_info) => self.download_from_s3(s3_info, &tmp_dir, &stream).await?,
-			RepoInfo::Http(base_url) => self.download_from_http(base_url, &tmp_dir, &stream).await?,
-		};
+		//// get the package tar file to the folder (from cache or repo)
+		let (download_url, version, tmp_gz) = self.fetch_package(&stream, version, expected_sha256, &tmp_dir).await?;
+		let sha256 = sha256_file(&tmp_gz)?;
 
-		//// copy the gz file
-		let package_dir = binst_package_bin_dir(&self.bin_name, &version)?;
-		let gz_path = package_dir.join(format!("{}.tar.gz", self.bin_name));
-		copy(tmp_gz, &gz_path)?;
+		//// stage the full package dir in the tmp dir
+		let gz_name = format!("{}.tar.gz", self.bin_name);
+		let stage_dir = tmp_dir.join("stage");
+		create_dir_all(&stage_dir)?;
+		rename(&tmp_gz, stage_dir.join(&gz_name))?;
+		unpack_tar_gz(&stage_dir.join(&gz_name), &stage_dir.join("unpacked"))?;
+		let staged_bin = stage_dir.join("unpacked").join(&self.bin_name);
+		if !staged_bin.is_file() {
+			return Err(Error::UnpackedBinFileNotFound(staged_bin.to_string_lossy().to_string()));
+		}
+		create_install_toml(&stage_dir, self.install_repo.url(), &stream, &version, &self.bin_target())?;
 
-		//// unpack
+		//// move the verified package dir in place, and create the symlink
+		let package_dir = binst_package_dir(&self.bin_name)?.join(get_version_part(&version));
 		let unpacked_dir = package_dir.join("unpacked");
-		if !unpacked_dir.is_dir() {
-			create_dir_all(&unpacked_dir)?;
-		}
-		// decode gz file
-		let gz_file = File::open(&gz_path)?;
-		let gz_reader = BufReader::new(&gz_file);
-		let mut dec = Decoder::new(gz_reader)?;
-		let mut buf = Vec::new(); // TODO might want ot use a streamed file buffer
-		dec.read_to_end(&mut buf)?;
-		// save and expand the tar file
-		let tar_path = package_dir.join(format!("{}.tar", self.bin_name));
-		let mut tar_file = File::create(&tar_path)?;
-		tar_file.write_all(&buf)?;
-		let tar_file = File::open(&tar_path)?;
-		let mut tar_archive = Archive::new(tar_file);
-		tar_archive.unpack(&unpacked_dir)?;
-		// remove the tar file
-		remove_file(&tar_path)?;
-
-		// create the install.toml
-		create_install_toml(&package_dir, self.install_repo.url(), &stream, &version)?;
-
-		//// create the symlink
-		let unpacked_bin = unpacked_dir.join(&self.bin_name);
-		let bin_symlink_path = create_bin_symlink(&self.bin_name, &unpacked_bin)?;
+		let bin_symlink_path = commit_package_dir(&stage_dir, &package_dir, &tmp_dir, || {
+			activate_package(&self.bin_name, &version)
+		})?;
 
 		// print info
 		println!(
 			"Install Complete - package: {} - version: {}
+  Target:           {}
   Downloaded from:  {}
   Downloaded   to:  {}
   Unpacked     at:  {}
   Symlinked    at:  {}",
 			self.bin_name,
 			version,
+			self.bin_target(),
 			download_url,
-			gz_path.to_string_lossy(),
+			package_dir.join(&gz_name).to_string_lossy(),
 			unpacked_dir.to_string_lossy(),
 			bin_symlink_path.to_string_lossy()
 		);
 
-		safer_remove_dir(&tmp_dir)?;
-
-		Ok(())
+		Ok(Installed {
+			version,
+			sha256,
+			target: self.bin_target(),
+		})
 	}
 
+	/// Returns the origin `latest.toml` content of the first target (of the ordered targets) found in the repo,
+	/// and resolve the bin target to it.
 	pub async fn get_origin_latest_toml_content(&self, stream: &str) -> Result<String> {
-		let base_target_uri = self.origin_bin_target_uri(stream);
-
-		// TODO - make sure it is the publish repo we want here.
-		let content = match &self.install_repo {
-			RepoInfo::Local(local_repo_origin) => {
-				let origin_target_dir = Path::new(local_repo_origin).join(base_target_uri);
-				let origin_latest_path = origin_target_dir.join(LATEST_TOML);
-				if !origin_latest_path.is_file() {
-					return Err(Error::OriginLatestNotFound(
-						origin_latest_path.to_string_lossy().to_string(),
-					));
+		let targets = match self.resolved_target.get() {
+			Some(target) => vec![target.to_string()],
+			None => self.targets.clone(),
+		};
+
+		let mut not_found = Vec::new();
+		for target in targets {
+			match self.get_origin_target_latest_toml_content(&target, stream).await {
+				Ok(content) => {
+					self.resolve_target(&target);
+					return Ok(content);
 				}
-				read_to_string(&origin_latest_path)?
-			}
-			RepoInfo::S3(s3_info) => {
-				let bucket = new_repo_bucket(s3_info.profile.clone()).await?;
-				let key = format!("{base_target_uri}/{LATEST_TOML}");
-				bucket.download_to_string(s3_info, &key).await?
-			}
-			RepoInfo::Http(base_url) => {
-				get_origin_latest_toml_content_from_base_url(base_url, &base_target_uri).await?
+				Err(Error::OriginLatestNotFound(url)) => {
+					if is_verbose() {
+						println!("  no {} for target {} ({})", LATEST_TOML, target, url);
+					}
+					not_found.push(url);
+				}
+				Err(ex) => return Err(ex),
 			}
-		};
+		}
+
+		Err(Error::OriginLatestNotFound(not_found.join(", ")))
+	}
+
+	async fn get_origin_target_latest_toml_content(&self, target: &str, stream: &str) -> Result<String> {
+		let key = format!("{}/{}", self.origin_target_uri(target, stream), LATEST_TOML);
 
-		Ok(content)
+		let (content, key_url) = self.get_install_text(&key).await?;
+		content.ok_or(Error::OriginLatestNotFound(key_url))
+	}
+
+	/// Returns the origin `<bin_name>.toml` package content, or None if not found.
+	/// Note: From the install repo only (never its mirrors), as it has the sha256 the mirror packages are checked against
+	///       (see `get_mirror_package_sha256` for the `trust_mirrors` exception).
+	pub async fn get_origin_package_toml_content(&self, stream: &str, version: &Version) -> Result<Option<String>> {
+		let package_key = self.origin_package_key(stream, version, &format!("{}.toml", self.bin_name));
+
+		let backend = new_repo_backend(&self.install_repo).await?;
+		backend.get_text(&package_key).await
 	}
 
 	pub async fn get_origin_latest_version(&self, stream: &str) -> Result<Version> {
@@ -123,120 +142,384 @@ impl BinRepo {
 	}
 }
 
-async fn get_origin_latest_toml_content_from_base_url(base_url: &str, base_uri: &str) -> Result<String> {
-	let latest_url = &format!("{}/{}/{}", base_url, base_uri, LATEST_TOML);
-	let resp = reqwest::get(latest_url).await?;
-
-	let data = resp.text().await?;
-	Ok(data)
-}
-
-// download from http
-impl BinRepo {
-	async fn download_from_http(
-		&self,
-		http_base: &str,
-		tmp_dir: &Path,
-		stream: &str,
-	) -> Result<(String, Version, PathBuf)> {
-		let version = self.get_origin_latest_version(stream).await?;
-
-		let gz_url = self.get_origin_http_url(http_base, stream, &version)?;
-		let gz_name = gz_url.rsplit_once('/').unwrap().1; // We know it must have one.
-
-		let resp = reqwest::get(&gz_url).await?;
-		let gz_tmp_path = tmp_dir.join(gz_name);
-		let mut gz_file = File::create(&gz_tmp_path)?;
-		let mut content = Cursor::new(resp.bytes().await?);
-		std::io::copy(&mut content, &mut gz_file)?;
+/// Move the staged package dir to the package_dir (replacing the eventual previous one), and then run the `activate` function.
+/// If anything fails, the previous package dir is restored, so that the previous version stays active.
+fn commit_package_dir<T>(
+	stage_dir: &Path,
+	package_dir: &Path,
+	tmp_dir: &Path,
+	activate: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+	if let Some(parent) = package_dir.parent() {
+		create_dir_all(parent)?;
+	}
 
-		Ok((gz_url, version, gz_tmp_path))
+	// move the previous package dir (same version re-install) out of the way
+	let replaced_dir = tmp_dir.join("replaced");
+	let has_previous = package_dir.exists();
+	if has_previous {
+		rename(package_dir, &replaced_dir)?;
 	}
 
-	pub fn get_origin_http_url(&self, http_base: &str, stream: &str, version: &Version) -> Result<String> {
-		let http_base = format!("{}/{}", http_base, self.origin_bin_target_uri(stream));
-		let gz_name = format!("{}.tar.gz", self.bin_name);
-		let gz_url = format!("{}/{}/{}", http_base, get_version_part(version), gz_name);
+	let res = rename(stage_dir, package_dir).map_err(Error::from).and_then(|_| activate());
 
-		Ok(gz_url)
+	if res.is_err() {
+		if package_dir.exists() {
+			let _ = safer_remove_dir(package_dir);
+		}
+		if has_previous {
+			let _ = rename(&replaced_dir, package_dir);
+		}
 	}
+
+	res
 }
 
-// download from s3
+// fetch package (cache or download) and checksum
 impl BinRepo {
-	async fn download_from_s3(
+	/// Get the package gz file into the tmp_dir, from the local cache if present, otherwise from the repo.
+	/// Returns the (source url or path, version, tmp gz path)
+	pub(super) async fn fetch_package(
 		&self,
-		s3_info: &S3Info,
-		tmp_dir: &Path,
 		stream: &str,
+		version: Option<&Version>,
+		expected_sha256: Option<&str>,
+		tmp_dir: &Path,
 	) -> Result<(String, Version, PathBuf)> {
-		// -- download orignal latest version
-		let version = self.get_origin_latest_version(stream).await?;
+		let repo_url = self.install_repo.url();
+		let tmp_gz = tmp_dir.join(format!("{}.tar.gz", self.bin_name));
 
-		let (gz_name, gz_key) = self.get_name_and_key(stream, &version);
+		// -- offline, only from the cache (first target cached)
+		if self.offline {
+			let (target, version, entry) = self
+				.targets
+				.iter()
+				.find_map(|target| {
+					CacheEntry::find_latest(repo_url, &self.bin_name, target, stream, version)
+						.map(|(version, entry)| (target, version, entry))
+				})
+				.ok_or_else(|| Error::NotInCache(format!("{} ({}, stream {})", self.bin_name, repo_url, stream)))?;
+			self.resolve_target(target);
+			let cached_gz = entry.get()?.ok_or_else(|| Error::NotInCache(self.bin_name.to_string()))?;
+			copy(&cached_gz, &tmp_gz)?;
+			verify_checksum(&tmp_gz, expected_sha256.map(|s| s.to_string()))?;
+			return Ok((cached_gz.to_string_lossy().to_string(), version, tmp_gz));
+		}
 
-		// -- download the gz file
+		// Note: resolves the bin target (first target found in the repo)
+		let latest_version = self.get_origin_latest_version(stream).await?;
+		let version = version.cloned().unwrap_or(latest_version);
+		let target = self.bin_target();
+		// Note: packages published before the checksum support only have the expected (lock) one,
+		//       which is also the one to check the mirror packages against when the install repo is down
+		let sha256 = match self.get_origin_package_sha256(stream, &version).await {
+			Ok(sha256) => sha256.or_else(|| expected_sha256.map(|s| s.to_string())),
+			Err(ex) if expected_sha256.is_some() && !self.install_mirrors.is_empty() => {
+				println!("  {} failed, the package checked against the binst.lock sha256 - cause: {}", repo_url, ex);
+				expected_sha256.map(|s| s.to_string())
+			}
+			Err(ex) if self.trust_mirrors && !self.install_mirrors.is_empty() => {
+				println!("  {} failed, the package checked against the mirror package toml sha256 (trust_mirrors) - cause: {}", repo_url, ex);
+				self.get_mirror_package_sha256(stream, &version).await?
+			}
+			Err(ex) => return Err(ex),
+		};
 
-		let gz_tmp_path = tmp_dir.join(gz_name);
+		// -- from the cache
+		let entry = CacheEntry::new(repo_url, &self.bin_name, &target, &version, sha256.as_deref())?;
+		if let Some(cached_gz) = entry.get()? {
+			copy(&cached_gz, &tmp_gz)?;
+			verify_checksum(&tmp_gz, expected_sha256.map(|s| s.to_string()))?;
+			return Ok((cached_gz.to_string_lossy().to_string(), version, tmp_gz));
+		}
 
-		let bucket = new_repo_bucket(s3_info.profile.clone()).await?;
-		let download_url = bucket.download_to_file(s3_info, &gz_key, &gz_tmp_path).await?;
+		// -- from the repo (or its mirrors), checked against the origin package sha256
+		let gz_key = self.origin_package_key(stream, &version, &format!("{}.tar.gz", self.bin_name));
+		let (gz_key, tmp_gz_ref, sha256) = (&gz_key, &tmp_gz, &sha256);
+		let download_url = self
+			.with_install_repos(|repo| async move {
+				let backend = new_repo_backend(repo).await?;
+				// Note: a mirror is trusted only for the bytes checked against a sha256 it did not serve
+				if sha256.is_none() && repo.url() != repo_url {
+					return Err(Error::MirrorNoChecksum(backend.key_url(gz_key)));
+				}
+				backend.get_file(gz_key, tmp_gz_ref).await.map_err(|ex| match ex {
+					Error::RepoKeyNotFound(url) => Error::OriginTarGzNotFound(url),
+					ex => ex,
+				})?;
+				verify_checksum(tmp_gz_ref, sha256.clone())?;
+				Ok(backend.key_url(gz_key))
+			})
+			.await?;
+
+		verify_checksum(&tmp_gz, expected_sha256.map(|s| s.to_string()))?;
+		entry.put(&tmp_gz)?;
+
+		Ok((download_url, version, tmp_gz))
+	}
 
-		Ok((download_url, version, gz_tmp_path))
+	/// Returns the `sha256` of the origin package toml.
+	/// Note: Packages published before the checksum support do not have one.
+	async fn get_origin_package_sha256(&self, stream: &str, version: &Version) -> Result<Option<String>> {
+		let content = self.get_origin_package_toml_content(stream, version).await?;
+		package_toml_sha256(content)
 	}
 
-	pub fn get_origin_s3_url(&self, s3_info: &S3Info, stream: &str, version: &Version) -> Result<String> {
-		let (_, key) = self.get_name_and_key(stream, version);
+	/// Returns the `sha256` of the package toml of the first mirror which answers, when the install repo is down
+	/// and the config repo has `trust_mirrors = true`.
+	/// Note: The mirror then serves both the package and its checksum, so it is only as trusted as the install repo.
+	async fn get_mirror_package_sha256(&self, stream: &str, version: &Version) -> Result<Option<String>> {
+		{
+			let install_url = self.install_repo.url().to_string();
+			let mut failed = self.failed_install_repos.lock().unwrap();
+			if !failed.contains(&install_url) {
+				failed.push(install_url);
+			}
+		}
+		let package_key = self.origin_package_key(stream, version, &format!("{}.toml", self.bin_name));
+		let (content, _) = self.get_install_text(&package_key).await?;
+		package_toml_sha256(content)
+	}
+}
 
-		let (_key, s3_url) = get_full_key_and_s3_url(s3_info, &key);
+/// Returns the `[package] sha256` of the package toml content if any.
+fn package_toml_sha256(content: Option<String>) -> Result<Option<String>> {
+	let sha256 = match content {
+		Some(content) => {
+			let toml: Value = toml::from_str(&content)?;
+			get_toml_value_as_string(&toml, &["package", "sha256"]).ok()
+		}
+		None => None,
+	};
+	Ok(sha256)
+}
 
-		Ok(s3_url)
+// install repo mirrors fallback
+impl BinRepo {
+	/// The repos to install from, in order, the install repo and then its mirrors, without the ones which already failed
+	/// (all of them if they all failed). Never empty.
+	fn install_repos(&self) -> Vec<&RepoInfo> {
+		let repos: Vec<&RepoInfo> = std::iter::once(&self.install_repo).chain(self.install_mirrors.iter()).collect();
+		let failed = self.failed_install_repos.lock().unwrap();
+		let available: Vec<&RepoInfo> = repos
+			.iter()
+			.copied()
+			.filter(|repo| !failed.iter().any(|url| url == repo.url()))
+			.collect();
+		match available.is_empty() {
+			true => repos,
+			false => available,
+		}
 	}
 
-	fn get_name_and_key(&self, stream: &str, version: &Version) -> (String, String) {
-		// e.g., ...repo/bin_name/target/v0.1.2
-		let origin_version_key = format!("{}/{}", self.origin_bin_target_uri(stream), get_version_part(version));
-
-		let gz_name = format!("{}.tar.gz", self.bin_name);
-		let gz_key = format!("{}/{}", origin_version_key, gz_name);
+	/// Run the operation on the install repo, and on its mirrors in turn while it fails.
+	/// Returns the first success, or the error of the last repo.
+	async fn with_install_repos<'a, T, F, Fut>(&'a self, op: F) -> Result<T>
+	where
+		F: Fn(&'a RepoInfo) -> Fut,
+		Fut: Future<Output = Result<T>>,
+	{
+		let repos = self.install_repos();
+		let mut idx = 0;
+		loop {
+			let repo = repos[idx];
+			match (op(repo).await, repos.get(idx + 1)) {
+				(Ok(val), _) => return Ok(val),
+				(Err(ex), Some(next_repo)) => {
+					println!("  {} failed, trying mirror {} - cause: {}", repo.url(), next_repo.url(), ex);
+					self.failed_install_repos.lock().unwrap().push(repo.url().to_string());
+					idx += 1;
+				}
+				(Err(ex), None) => return Err(ex),
+			}
+		}
+	}
 
-		(gz_name, gz_key)
+	/// Returns the key content (None if not found) with the key url, from the install repo or its mirrors.
+	/// Note: A not found key is an answer, the mirrors are only tried when the repo fails (e.g., down).
+	async fn get_install_text(&self, key: &str) -> Result<(Option<String>, String)> {
+		self.with_install_repos(|repo| async move {
+			let backend = new_repo_backend(repo).await?;
+			Ok((backend.get_text(key).await?, backend.key_url(key)))
+		})
+		.await
 	}
 }
 
-// download from local
-impl BinRepo {
-	async fn download_from_local(
-		&self,
-		local_repo_origin: &str,
-		tmp_dir: &Path,
-		stream: &str,
-	) -> Result<(String, Version, PathBuf)> {
-		// read the version file
-		let version = self.get_origin_latest_version(stream).await?;
+/// Verify the downloaded gz file against the origin package `sha256` (skipped if None).
+fn verify_checksum(gz_path: &Path, expected: Option<String>) -> Result<()> {
+	let Some(expected) = expected else {
+		if is_verbose() {
+			println!("  No sha256 in origin package toml, checksum check skipped");
+		}
+		return Ok(());
+	};
+
+	let actual = sha256_file(gz_path)?;
+	if actual != expected {
+		remove_file(gz_path)?;
+		return Err(Error::ChecksumMismatch(
+			gz_path.to_string_lossy().to_string(),
+			expected,
+			actual,
+		));
+	}
 
-		let origin_gz = self.get_origin_local_path(local_repo_origin, stream, &version)?;
+	Ok(())
+}
 
-		let tmp_gz = tmp_dir.join(format!("{}.tar.gz", self.bin_name));
-		copy(&origin_gz, &tmp_gz)?;
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::repo::backend::{LocalBackend, RepoBackend};
+	use crate::repo::HttpInfo;
+	use std::sync::{Mutex, OnceLock};
+
+	#[tokio::test]
+	async fn test_install_mirrors_fallback() -> Result<()> {
+		let test_dir = super::super::backend::tests::set_test_binst_root("install-mirrors")?;
+
+		// -- the install repo, failing (access denied, not retried)
+		let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+		let port = server.server_addr().to_ip().unwrap().port();
+		std::thread::spawn(move || {
+			for req in server.incoming_requests() {
+				let _ = req.respond(tiny_http::Response::empty(403));
+			}
+		});
+		let install_repo = || RepoInfo::Http(HttpInfo::from_public_url(&format!("http://127.0.0.1:{}/repo", port)));
+
+		// -- the mirrors, the first one with a corrupted package
+		let gz_file = test_dir.join("cool_cli.tar.gz");
+		std::fs::write(&gz_file, "fake gz")?;
+		let sha256 = sha256_file(&gz_file)?;
+		let mut mirror_dirs = Vec::new();
+		for name in ["mirror-1", "mirror-2"] {
+			let dir = test_dir.join(name).to_string_lossy().to_string();
+			let backend = LocalBackend::new(&dir);
+			backend.put_text("cool_cli/x86/main/latest.toml", "[latest]\nversion = \"0.1.0\"").await?;
+			backend
+				.put_text("cool_cli/x86/main/0.1.0/cool_cli.toml", &format!("[package]\nsha256 = \"{}\"\n", sha256))
+				.await?;
+			if name == "mirror-1" {
+				std::fs::write(test_dir.join("corrupted.tar.gz"), "corrupted gz")?;
+				backend.put_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &test_dir.join("corrupted.tar.gz")).await?;
+			} else {
+				backend.put_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &gz_file).await?;
+			}
+			mirror_dirs.push(dir);
+		}
+		let mirrors = || mirror_dirs.iter().map(|dir| RepoInfo::Local(dir.clone())).collect::<Vec<_>>();
+		let mirror_urls: Vec<String> = mirrors().iter().map(|m| m.url().to_string()).collect();
+
+		let bin_repo = |install_mirrors: Vec<RepoInfo>, trust_mirrors: bool| BinRepo {
+			bin_name: "cool_cli".to_string(),
+			install_repo: install_repo(),
+			install_mirrors,
+			trust_mirrors,
+			failed_install_repos: Mutex::new(Vec::new()),
+			publish_repo: install_repo(),
+			target: None,
+			targets: vec!["x86".to_string()],
+			resolved_target: OnceLock::new(),
+			offline: false,
+		};
 
-		let download_path = origin_gz.to_string_lossy().to_string();
-		Ok((download_path, version, tmp_gz))
-	}
+		// -- the install repo down, no package toml sha256, so not installed from the mirrors without a lock one
+		let tmp_dir = make_bin_temp_dir("cool_cli")?;
+		assert!(matches!(
+			bin_repo(mirrors(), false).fetch_package("main", None, None, &tmp_dir).await,
+			Err(Error::HttpAuth(_, 403))
+		));
+
+		// -- the package from the second mirror, checked against the binst.lock sha256
+		let with_mirrors = bin_repo(mirrors(), false);
+		let (download_url, version, tmp_gz) = with_mirrors.fetch_package("main", None, Some(&sha256), &tmp_dir).await?;
+		assert_eq!(format!("{}/cool_cli/x86/main/0.1.0/cool_cli.tar.gz", mirror_urls[1]), download_url);
+		assert_eq!("0.1.0", version.to_string());
+		assert_eq!(sha256, sha256_file(&tmp_gz)?);
+		assert_eq!(
+			vec![install_repo().url().to_string(), mirror_urls[0].to_string()],
+			*with_mirrors.failed_install_repos.lock().unwrap()
+		);
+
+		// -- with trust_mirrors, the package from the second mirror (not the cached one), checked against the mirror package toml sha256
+		remove_file(CacheEntry::new(install_repo().url(), "cool_cli", "x86", &version, Some(&sha256))?.gz_path())?;
+		let trusting = bin_repo(mirrors(), true);
+		let (download_url, _, tmp_gz) = trusting.fetch_package("main", None, None, &tmp_dir).await?;
+		assert_eq!(format!("{}/cool_cli/x86/main/0.1.0/cool_cli.tar.gz", mirror_urls[1]), download_url);
+		assert_eq!(sha256, sha256_file(&tmp_gz)?);
+		assert_eq!(
+			vec![install_repo().url().to_string(), mirror_urls[0].to_string()],
+			*trusting.failed_install_repos.lock().unwrap()
+		);
 
-	pub fn get_origin_local_path(&self, local_repo_origin: &str, stream: &str, version: &Version) -> Result<PathBuf> {
-		let base_uri = self.origin_bin_target_uri(stream);
-		let origin_target_dir = Path::new(local_repo_origin).join(base_uri);
+		// -- without mirrors, the install repo error
+		let without_mirrors = bin_repo(Vec::new(), false);
+		assert!(matches!(
+			without_mirrors.get_origin_latest_version("main").await,
+			Err(Error::HttpAuth(_, 403))
+		));
 
-		// e.g., ...repo/bin_name/target/main/v0.1.2/
-		let origin_dir = origin_target_dir.join(get_version_part(version));
+		drop(tmp_dir);
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
 
-		// check origin tar file
-		let origin_gz = origin_dir.join(format!("{}.tar.gz", self.bin_name));
-		if !origin_gz.is_file() {
-			return Err(Error::OriginTarGzNotFound(origin_gz.to_string_lossy().to_string()));
+	#[tokio::test]
+	async fn test_install_mirrors_tampered() -> Result<()> {
+		let test_dir = super::super::backend::tests::set_test_binst_root("install-mirrors-tampered")?;
+		let gz_file = test_dir.join("cool_cli.tar.gz");
+		std::fs::write(&gz_file, "fake gz")?;
+		let sha256 = sha256_file(&gz_file)?;
+		let tampered_gz_file = test_dir.join("tampered.tar.gz");
+		std::fs::write(&tampered_gz_file, "tampered gz")?;
+		let tampered_sha256 = sha256_file(&tampered_gz_file)?;
+
+		// -- the install repo without the package gz (e.g., lost), and the mirror with a tampered gz and its matching toml
+		let repo_dir = test_dir.join("repo").to_string_lossy().to_string();
+		let mirror_dir = test_dir.join("mirror").to_string_lossy().to_string();
+		let repo = LocalBackend::new(&repo_dir);
+		let mirror = LocalBackend::new(&mirror_dir);
+		for backend in [&repo, &mirror] {
+			backend.put_text("cool_cli/x86/main/latest.toml", "[latest]\nversion = \"0.1.0\"").await?;
 		}
+		repo.put_text("cool_cli/x86/main/0.1.0/cool_cli.toml", &format!("[package]\nsha256 = \"{}\"\n", sha256))
+			.await?;
+		mirror
+			.put_text("cool_cli/x86/main/0.1.0/cool_cli.toml", &format!("[package]\nsha256 = \"{}\"\n", tampered_sha256))
+			.await?;
+		mirror.put_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &tampered_gz_file).await?;
+
+		let bin_repo = || BinRepo {
+			bin_name: "cool_cli".to_string(),
+			install_repo: RepoInfo::Local(repo_dir.clone()),
+			install_mirrors: vec![RepoInfo::Local(mirror_dir.clone())],
+			trust_mirrors: false,
+			failed_install_repos: Mutex::new(Vec::new()),
+			publish_repo: RepoInfo::Local(repo_dir.clone()),
+			target: None,
+			targets: vec!["x86".to_string()],
+			resolved_target: OnceLock::new(),
+			offline: false,
+		};
 
-		Ok(origin_gz)
+		// -- checked against the install repo package toml sha256 (not the mirror one)
+		let tmp_dir = make_bin_temp_dir("cool_cli")?;
+		assert!(matches!(
+			bin_repo().fetch_package("main", None, None, &tmp_dir).await,
+			Err(Error::ChecksumMismatch(_, expected, _)) if expected == sha256
+		));
+
+		// -- without the install repo package toml sha256, the mirror package is refused
+		repo.put_text("cool_cli/x86/main/0.1.0/cool_cli.toml", "[package]\n").await?;
+		assert!(matches!(
+			bin_repo().fetch_package("main", None, None, &tmp_dir).await,
+			Err(Error::MirrorNoChecksum(_))
+		));
+
+		drop(tmp_dir);
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
 	}
 }
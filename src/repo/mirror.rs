@@ -0,0 +1,249 @@
+//! Repo mirroring, copy the packages of a repo into another one (e.g., the binst S3 repo into a local dir
+//! for an air-gapped network), whatever their backends.
+//!
+//! Incremental: the package `.tar.gz` is skipped when the package toml `sha256` is the same in both repos,
+//! the toml files are skipped when their content is the same, and the other files when their content `sha256` is the same.
+//! The `latest.toml` files are copied last, so that they never point to a version not yet in the mirror.
+
+use super::backend::{new_repo_backend, RepoBackend};
+use crate::prelude::*;
+use crate::repo::{make_bin_temp_dir, Error, RepoInfo, Result, LATEST_TOML};
+use crate::utils::{get_toml_value_as_string, sha256_file, sha256_str};
+use std::path::Path;
+use toml::Value;
+
+/// The packages to mirror (all if None), from the repo layout `<bin>/<target>/<stream>/...`.
+#[derive(Debug, Default)]
+pub struct MirrorFilter {
+	pub bin_name: Option<String>,
+	pub target: Option<String>,
+	pub stream: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MirrorReport {
+	pub copied: usize,
+	pub unchanged: usize,
+}
+
+impl MirrorFilter {
+	/// The key prefix to list, as precise as the filter allows (e.g., `cool_cli/x86_64-unknown-linux-gnu/`).
+	fn list_prefix(&self) -> String {
+		let mut prefix = String::new();
+		for part in [&self.bin_name, &self.target, &self.stream] {
+			match part {
+				Some(part) => prefix.push_str(&f!("{part}/")),
+				None => break,
+			}
+		}
+		prefix
+	}
+
+	fn matches(&self, key: &str) -> bool {
+		let mut parts = key.split('/');
+		[&self.bin_name, &self.target, &self.stream]
+			.iter()
+			.all(|filter| match (*filter, parts.next()) {
+				(None, _) => true,
+				(Some(filter), Some(part)) => filter == part,
+				(Some(_), None) => false,
+			})
+	}
+}
+
+/// Copy the keys of the `from` repo matching the filter into the `to` repo (see module doc).
+pub async fn mirror_repo(from: &RepoInfo, to: &RepoInfo, filter: &MirrorFilter) -> Result<MirrorReport> {
+	let from_backend = new_repo_backend(from).await?;
+	let to_backend = new_repo_backend(to).await?;
+	let tmp_dir = make_bin_temp_dir("mirror")?;
+
+	let mut keys = from_backend.list(&filter.list_prefix()).await?;
+	keys.retain(|key| filter.matches(key));
+	// Note: the package files first, then the package tomls, and the latest.toml files last
+	keys.sort_by_key(|key| (key_rank(key), key.to_string()));
+
+	let mut report = MirrorReport::default();
+	for key in keys.iter() {
+		let copied = if key.ends_with(".toml") {
+			mirror_toml(from_backend.as_ref(), to_backend.as_ref(), key).await?
+		} else {
+			mirror_file(from_backend.as_ref(), to_backend.as_ref(), key, &tmp_dir).await?
+		};
+		match copied {
+			true => {
+				println!("    copied: {}", to_backend.key_url(key));
+				report.copied += 1;
+			}
+			false => report.unchanged += 1,
+		}
+	}
+
+	// Note: e.g., the git commit and push of all the copied files
+	to_backend.flush().await?;
+
+	Ok(report)
+}
+
+// region:    --- Mirror Helpers
+fn key_rank(key: &str) -> u8 {
+	match key.rsplit('/').next() {
+		Some(LATEST_TOML) => 2,
+		Some(name) if name.ends_with(".toml") => 1,
+		_ => 0,
+	}
+}
+
+/// Copy the toml if its content changed. Returns true if copied.
+async fn mirror_toml(from: &dyn RepoBackend, to: &dyn RepoBackend, key: &str) -> Result<bool> {
+	// Note: None when deleted since the list
+	let Some(content) = from.get_text(key).await? else {
+		return Ok(false);
+	};
+	if to.get_text(key).await?.as_deref() == Some(content.as_str()) {
+		return Ok(false);
+	}
+	to.put_text(key, &content).await?;
+	Ok(true)
+}
+
+/// Copy the file, unless it is the package `.tar.gz` with the same package toml `sha256` in both repos,
+/// or another file with the same content `sha256` in both repos.
+/// The downloaded package `.tar.gz` is checked against the `sha256` of the `from` package toml.
+/// Returns true if copied.
+async fn mirror_file(from: &dyn RepoBackend, to: &dyn RepoBackend, key: &str, tmp_dir: &Path) -> Result<bool> {
+	let toml_key = package_toml_key(key);
+	let sha256 = match &toml_key {
+		Some(toml_key) => package_sha256(from, toml_key).await?,
+		None => None,
+	};
+
+	if let (Some(sha256), Some(toml_key)) = (&sha256, &toml_key) {
+		let to_sha256 = package_sha256(to, toml_key).await?;
+		if to_sha256.as_ref() == Some(sha256) && to.exists(key).await? {
+			return Ok(false);
+		}
+	}
+
+	let tmp_file = tmp_dir.join(&sha256_str(key)[..16]);
+	from.get_file(key, &tmp_file).await?;
+	let actual_sha256 = sha256_file(&tmp_file)?;
+	let copied = match (sha256, toml_key) {
+		(Some(sha256), _) if actual_sha256 != sha256 => {
+			return Err(Error::ChecksumMismatch(from.key_url(key), sha256, actual_sha256));
+		}
+		// Note: not a package `.tar.gz` (no package toml), compared by content
+		(_, None) if to.sha256(key).await?.as_ref() == Some(&actual_sha256) => false,
+		_ => {
+			to.put_file(key, &tmp_file).await?;
+			true
+		}
+	};
+	std::fs::remove_file(&tmp_file)?;
+
+	Ok(copied)
+}
+
+/// The package toml key of a package `.tar.gz` key (e.g., `cool_cli/x86/main/0.1.0/cool_cli.toml`),
+/// None if the key is not a package `.tar.gz`.
+fn package_toml_key(key: &str) -> Option<String> {
+	let bin_name = key.split('/').next()?;
+	let (dir, file_name) = key.rsplit_once('/')?;
+	(file_name == f!("{bin_name}.tar.gz")).then(|| f!("{dir}/{bin_name}.toml"))
+}
+
+/// The `package.sha256` of the package toml, None if the toml does not exist (or has no sha256).
+async fn package_sha256(backend: &dyn RepoBackend, toml_key: &str) -> Result<Option<String>> {
+	let Some(content) = backend.get_text(toml_key).await? else {
+		return Ok(None);
+	};
+	let toml: Value = toml::from_str(&content)?;
+	Ok(get_toml_value_as_string(&toml, &["package", "sha256"]).ok())
+}
+// endregion: --- Mirror Helpers
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::repo::backend::LocalBackend;
+
+	#[tokio::test]
+	async fn test_mirror_repo() -> Result<()> {
+		let test_dir = super::super::backend::tests::set_test_binst_root("mirror")?;
+		let from_dir = test_dir.join("from").to_string_lossy().to_string();
+		let to_dir = test_dir.join("to").to_string_lossy().to_string();
+		let from_repo = RepoInfo::Local(from_dir.clone());
+		let to_repo = RepoInfo::Local(to_dir.clone());
+		let from = LocalBackend::new(&from_dir);
+		let to = LocalBackend::new(&to_dir);
+
+		// -- the from repo, two streams of cool_cli, and another bin
+		let gz_file = test_dir.join("cool_cli.tar.gz");
+		std::fs::write(&gz_file, "fake gz")?;
+		let sha256 = sha256_file(&gz_file)?;
+		for dir in ["cool_cli/x86/main/0.1.0", "cool_cli/x86/rc/0.2.0-rc.1", "other_cli/x86/main/1.0.0"] {
+			let bin_name = dir.split('/').next().unwrap_or_default();
+			from.put_file(&f!("{dir}/{bin_name}.tar.gz"), &gz_file).await?;
+			from.put_text(&f!("{dir}/{bin_name}.toml"), &f!("[package]\nsha256 = \"{sha256}\"\n")).await?;
+		}
+		from.put_text("cool_cli/x86/main/latest.toml", "[latest]\nversion = \"0.1.0\"").await?;
+		from.put_text("cool_cli/x86/main/0.1.0/cool_cli.sig", "sig 1").await?;
+
+		// -- mirror cool_cli main, then all, then again (nothing to copy)
+		let filter = MirrorFilter {
+			bin_name: Some("cool_cli".to_string()),
+			stream: Some("main".to_string()),
+			..Default::default()
+		};
+		assert_eq!(MirrorReport { copied: 4, unchanged: 0 }, mirror_repo(&from_repo, &to_repo, &filter).await?);
+		assert_eq!(
+			vec![
+				"cool_cli/x86/main/0.1.0/cool_cli.sig",
+				"cool_cli/x86/main/0.1.0/cool_cli.tar.gz",
+				"cool_cli/x86/main/0.1.0/cool_cli.toml",
+				"cool_cli/x86/main/latest.toml"
+			],
+			to.list("").await?
+		);
+		let all = MirrorFilter::default();
+		assert_eq!(MirrorReport { copied: 4, unchanged: 4 }, mirror_repo(&from_repo, &to_repo, &all).await?);
+		assert_eq!(from.list("").await?, to.list("").await?);
+		assert_eq!(MirrorReport { copied: 0, unchanged: 8 }, mirror_repo(&from_repo, &to_repo, &all).await?);
+
+		// -- a new latest.toml and a changed file are copied, and a corrupted package is not
+		from.put_text("cool_cli/x86/main/latest.toml", "[latest]\nversion = \"0.1.1\"").await?;
+		from.put_text("cool_cli/x86/main/0.1.0/cool_cli.sig", "sig 2").await?;
+		assert_eq!(MirrorReport { copied: 2, unchanged: 6 }, mirror_repo(&from_repo, &to_repo, &all).await?);
+		assert_eq!(Some("sig 2".to_string()), to.get_text("cool_cli/x86/main/0.1.0/cool_cli.sig").await?);
+		std::fs::write(&gz_file, "corrupted gz")?;
+		from.put_file("other_cli/x86/main/1.0.0/other_cli.tar.gz", &gz_file).await?;
+		to.delete("other_cli/x86/main/1.0.0/other_cli.tar.gz").await?;
+		assert!(matches!(
+			mirror_repo(&from_repo, &to_repo, &all).await,
+			Err(Error::ChecksumMismatch(..))
+		));
+
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_mirror_filter() {
+		let filter = MirrorFilter {
+			bin_name: Some("cool_cli".to_string()),
+			target: Some("x86".to_string()),
+			..Default::default()
+		};
+		assert_eq!("cool_cli/x86/", filter.list_prefix());
+		assert!(filter.matches("cool_cli/x86/main/latest.toml"));
+		assert!(!filter.matches("cool_cli/arm/main/latest.toml"));
+		assert!(!filter.matches("cool_cli"));
+
+		let filter = MirrorFilter {
+			stream: Some("rc".to_string()),
+			..Default::default()
+		};
+		assert_eq!("", filter.list_prefix());
+		assert!(filter.matches("cool_cli/x86/rc/latest.toml"));
+		assert!(!filter.matches("cool_cli/x86/main/latest.toml"));
+	}
+}
@@ -1,33 +1,63 @@
 // -- Re-export
 pub use self::error::{Error, Result};
+pub use cache::clean_cache;
+pub use mirror::{mirror_repo, MirrorFilter};
 pub use repo_info::*;
 
 // -- Imports
-use crate::paths::{binst_bin_dir, binst_tmp_dir, os_target};
-use crate::utils::sym_link;
+use self::backend::new_repo_backend;
+use crate::config::{Config, RepoConfig};
+use crate::paths::{binst_bin_dir, binst_dir, binst_package_dir, binst_tmp_dir, os_target, target_fallbacks};
+use crate::utils::{safer_remove_dir, sym_link};
 use clap::ArgMatches;
 use regex::Regex;
 use semver::Version;
-use std::fs::{create_dir_all, remove_file, File};
+use std::env;
+use std::fmt;
+use std::fs::{create_dir_all, read_link, read_to_string, remove_file, rename, set_permissions, write, File, Permissions};
 use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::ops::Deref;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 // -- Sub-Modules
+mod backend;
+mod cache;
+mod download;
 mod error;
 mod install;
+mod mirror;
 mod publish;
 mod repo_info;
+mod retry;
+mod run;
 mod s3w;
+mod unpack;
 
 // --- Consts
 pub const BINST_REPO_URL: &str = "https://repo.binst.io/";
+/// The first comment line of the generated `bin/<bin>` shims (to tell them from the other files)
+const SHIM_MARKER: &str = "# binst shim (generated)";
 pub const BINST_REPO_BUCKET: &str = "binst-repo";
 pub const BINST_REPO_AWS_PROFILE: &str = "binst-repo-user";
 // main stream
 pub const MAIN_STREAM: &str = "main";
-
-#[derive(Debug)]
+// the `packages/<bin>/active` link to the active version dir
+pub const ACTIVE_LINK: &str = "active";
+// the `<bin>/<target>/<stream>/latest.toml` of the repo
+const LATEST_TOML: &str = "latest.toml";
+// http repo auth, when not in the config repo
+const HTTP_TOKEN_ENV: &str = "BINST_REPO_HTTP_TOKEN";
+const HTTP_USER_ENV: &str = "BINST_REPO_HTTP_USER";
+const HTTP_PASSWORD_ENV: &str = "BINST_REPO_HTTP_PASSWORD";
+// git repo branch, when no url `#branch` or config repo `branch`
+const GIT_DEFAULT_BRANCH: &str = "main";
+// github repo api, when no config repo `api_url`
+const GITHUB_API_URL: &str = "https://api.github.com";
+
+#[derive(Debug, Clone)]
 pub struct S3Info {
 	url: String,
 	bucket: String,
@@ -73,72 +103,414 @@ impl S3Info {
 	}
 }
 
+#[derive(Debug, Clone)]
+pub struct HttpInfo {
+	url: String,
+	auth: Option<HttpAuth>,
+	/// WebDAV server, the collections (dirs) must be created (MKCOL) before the PUT
+	mkcol: bool,
+}
+
+#[derive(Clone)]
+pub enum HttpAuth {
+	/// (user, password)
+	Basic(String, Option<String>),
+	Bearer(String),
+}
+
+// Note: Does not print the secrets.
+impl fmt::Debug for HttpAuth {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			HttpAuth::Basic(user, _) => write!(f, "Basic({user}, ***)"),
+			HttpAuth::Bearer(_) => write!(f, "Bearer(***)"),
+		}
+	}
+}
+
+impl HttpInfo {
+	/// Build the HttpInfo with the auth of the config repo, otherwise of the `BINST_REPO_HTTP_...` env.
+	/// Note: No auth for the urls of no config repo (e.g., a `-r` url, or a github: asset), so that the env
+	///       credentials are never sent to another host.
+	pub fn from_http_url(http_url: &str, repo_config: Option<&RepoConfig>) -> HttpInfo {
+		HttpInfo {
+			url: http_url.to_string(),
+			auth: repo_http_auth(repo_config, |name| env::var(name).ok()),
+			mkcol: repo_config.map(|c| c.mkcol).unwrap_or(false),
+		}
+	}
+
+	/// The public binst repo (no auth).
+	pub fn from_public_url(http_url: &str) -> HttpInfo {
+		HttpInfo {
+			url: http_url.to_string(),
+			auth: None,
+			mkcol: false,
+		}
+	}
+}
+
+/// The auth of the config repo, otherwise of the `BINST_REPO_HTTP_...` env (from `env_var`), None without config repo.
+fn repo_http_auth(repo_config: Option<&RepoConfig>, env_var: impl Fn(&str) -> Option<String>) -> Option<HttpAuth> {
+	let config = repo_config?;
+	http_auth(config.token.clone(), config.user.clone(), config.password.clone())
+		.or_else(|| http_auth(env_var(HTTP_TOKEN_ENV), env_var(HTTP_USER_ENV), env_var(HTTP_PASSWORD_ENV)))
+}
+
+fn http_auth(token: Option<String>, user: Option<String>, password: Option<String>) -> Option<HttpAuth> {
+	match (token, user) {
+		(Some(token), _) => Some(HttpAuth::Bearer(token)),
+		(None, Some(user)) => Some(HttpAuth::Basic(user, password)),
+		(None, None) => None,
+	}
+}
+
+/// `sftp://[user@]host[:port]/base` repo, run with the `sftp` command in batch mode.
+/// Auth is the ssh agent (or default keys), or the config repo `key_file`,
+/// and the host key must be in the `known_hosts` (or the config repo `known_hosts` file).
+#[derive(Debug, Clone)]
+pub struct SftpInfo {
+	url: String,
+	/// `[user@]host`
+	destination: String,
+	port: Option<u16>,
+	/// The remote base dir, absolute (e.g., `/srv/repo`), or relative to the user home for `/~/repo`
+	base: String,
+	key_file: Option<String>,
+	known_hosts: Option<String>,
+}
+
+impl SftpInfo {
+	pub fn from_sftp_url(sftp_url: &str, repo_config: Option<&RepoConfig>) -> Result<SftpInfo> {
+		let invalid = || Error::RepoInvalidSftp(sftp_url.to_string());
+		let rest = sftp_url.strip_prefix("sftp://").ok_or_else(invalid)?;
+		let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+		// [user@]host[:port]
+		let (user, host_port) = match authority.rsplit_once('@') {
+			Some((user, host_port)) => (Some(user), host_port),
+			None => (None, authority),
+		};
+		let (host, port) = match host_port.split_once(':') {
+			Some((host, port)) => (host, Some(port.parse::<u16>().map_err(|_| invalid())?)),
+			None => (host_port, None),
+		};
+		if host.is_empty() || user.is_some_and(|u| u.is_empty()) {
+			return Err(invalid());
+		}
+		let destination = match user {
+			Some(user) => format!("{user}@{host}"),
+			None => host.to_string(),
+		};
+
+		let path = path.trim_end_matches('/');
+		let base = match path.strip_prefix('~') {
+			Some(home_path) => home_path.trim_start_matches('/').to_string(),
+			None => format!("/{path}"),
+		};
+
+		Ok(SftpInfo {
+			url: sftp_url.trim_end_matches('/').to_string(),
+			destination,
+			port,
+			base,
+			key_file: repo_config.and_then(|c| c.key_file.clone()),
+			known_hosts: repo_config.and_then(|c| c.known_hosts.clone()),
+		})
+	}
+}
+
+/// `oci://registry[:port]/namespace` repo, the packages are OCI artifacts of the `<namespace>/<bin>` repositories
+/// (see `backend/oci.rs`).
+#[derive(Debug, Clone)]
+pub struct OciInfo {
+	url: String,
+	/// e.g., `https://ghcr.io` (`http://` for localhost)
+	registry_url: String,
+	/// e.g., `my-org/binst`
+	namespace: String,
+	auth: Option<HttpAuth>,
+}
+
+impl OciInfo {
+	pub fn from_oci_url(oci_url: &str, repo_config: Option<&RepoConfig>) -> Result<OciInfo> {
+		let invalid = || Error::RepoInvalidOci(oci_url.to_string());
+		let rest = oci_url.strip_prefix("oci://").ok_or_else(invalid)?;
+		let (registry, namespace) = rest.split_once('/').unwrap_or((rest, ""));
+		let namespace = namespace.trim_matches('/').to_lowercase();
+		if registry.is_empty() || namespace.is_empty() {
+			return Err(invalid());
+		}
+
+		// Note: as docker, plain http only for the local registries
+		let host = registry.split(':').next().unwrap_or_default();
+		let scheme = match host {
+			"localhost" | "127.0.0.1" => "http",
+			_ => "https",
+		};
+
+		// Note: same auth resolution as the http repos (config repo, otherwise env)
+		let auth = HttpInfo::from_http_url(oci_url, repo_config).auth;
+
+		Ok(OciInfo {
+			url: format!("oci://{registry}/{namespace}"),
+			registry_url: format!("{scheme}://{registry}"),
+			namespace,
+			auth,
+		})
+	}
+}
+
+/// `git+ssh://[user@]host[:port]/path` or `git+file:///path` repo, on the `#branch` of the url, or the config repo `branch`
+/// (`main` by default), with the `git` command (see `backend/git.rs`).
+#[derive(Debug, Clone)]
+pub struct GitInfo {
+	url: String,
+	/// The git remote url (e.g., `ssh://git@host/team/repo.git` or `file:///srv/repo.git`)
+	remote: String,
+	branch: String,
+}
+
+impl GitInfo {
+	pub fn from_git_url(git_url: &str, repo_config: Option<&RepoConfig>) -> Result<GitInfo> {
+		let invalid = || Error::RepoInvalidGit(git_url.to_string());
+		let rest = git_url.strip_prefix("git+").ok_or_else(invalid)?;
+		let (rest, url_branch) = match rest.split_once('#') {
+			Some((_, "")) => return Err(invalid()),
+			Some((rest, branch)) => (rest, Some(branch)),
+			None => (rest, None),
+		};
+
+		let remote = if let Some(path) = rest.strip_prefix("file://") {
+			let path = repo_info::file_url_path(path).ok_or_else(invalid)?;
+			// Note: normalized, as for the local repos
+			format!("file://{}", repo_info::local_repo_path(path)?)
+		} else if let Some(ssh_path) = rest.strip_prefix("ssh://") {
+			match ssh_path.split_once('/') {
+				Some((host, path)) if !host.is_empty() && !path.is_empty() => rest.trim_end_matches('/').to_string(),
+				_ => return Err(invalid()),
+			}
+		} else {
+			return Err(invalid());
+		};
+
+		let branch = url_branch
+			.or_else(|| repo_config.and_then(|c| c.branch.as_deref()))
+			.unwrap_or(GIT_DEFAULT_BRANCH);
+		let url = match url_branch {
+			Some(branch) => format!("git+{remote}#{branch}"),
+			None => format!("git+{remote}"),
+		};
+
+		Ok(GitInfo {
+			url,
+			remote,
+			branch: branch.to_string(),
+		})
+	}
+}
+
+/// `github:owner/repo` repo, install only, from the release assets (see `backend/github.rs`).
+/// The api can be GitHub Enterprise or Gitea with the config repo `api_url`.
+#[derive(Debug, Clone)]
+pub struct GithubInfo {
+	url: String,
+	/// e.g., `https://api.github.com` (or `https://gitea.example.com/api/v1`)
+	api_url: String,
+	/// e.g., `BurntSushi/ripgrep`
+	owner_repo: String,
+	auth: Option<HttpAuth>,
+}
+
+impl GithubInfo {
+	pub fn from_github_url(github_url: &str, repo_config: Option<&RepoConfig>) -> Result<GithubInfo> {
+		let invalid = || Error::RepoInvalidGithub(github_url.to_string());
+		let owner_repo = github_url.strip_prefix("github:").ok_or_else(invalid)?.trim_matches('/');
+		match owner_repo.split_once('/') {
+			Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() && !repo.contains('/') => (),
+			_ => return Err(invalid()),
+		}
+
+		let api_url = repo_config.and_then(|c| c.api_url.as_deref()).unwrap_or(GITHUB_API_URL);
+
+		// Note: same auth resolution as the http repos (config repo, otherwise env)
+		let auth = HttpInfo::from_http_url(github_url, repo_config).auth;
+
+		Ok(GithubInfo {
+			url: format!("github:{owner_repo}"),
+			api_url: api_url.trim_end_matches('/').to_string(),
+			owner_repo: owner_repo.to_string(),
+			auth,
+		})
+	}
+}
+
 #[derive(Debug)]
 pub struct BinRepo {
 	pub bin_name: String,
 	pub install_repo: RepoInfo,
+	/// The install repo mirrors (config repo `mirrors`), tried in order when the install repo fails
+	pub install_mirrors: Vec<RepoInfo>,
+	/// Check the mirror packages against the mirror package toml sha256 when the install repo is down
+	/// (config repo `trust_mirrors`), otherwise only against the install repo or `binst.lock` one
+	pub trust_mirrors: bool,
+	/// The urls of the install repo or mirrors which failed, not tried again (see `install.rs`)
+	failed_install_repos: Mutex<Vec<String>>,
 	pub publish_repo: RepoInfo,
 	pub target: Option<String>,
+	/// Ordered install targets to try (only the `--target` if defined, otherwise the os target fallbacks)
+	pub targets: Vec<String>,
+	/// The first of the `targets` found in the install repo
+	resolved_target: OnceLock<String>,
+	/// Install only from the local cache
+	pub offline: bool,
 }
 
 /// Constructor
 impl BinRepo {
-	pub fn new(bin_name: &str, argc: &ArgMatches, publish: bool) -> Result<Self> {
+	pub fn new(bin_name: &str, argc: &ArgMatches) -> Result<Self> {
+		let target = argc.try_get_one::<String>("target").ok().flatten().map(|target| target.to_string());
+		let profile = argc.get_one::<String>("profile").map(|s| s.as_str());
+		let repo = argc.get_one::<String>("repo").map(|s| s.as_str());
+		let offline = matches!(argc.try_get_one::<bool>("offline"), Ok(Some(true)));
+
+		BinRepo::from_repo(bin_name, repo, profile, target, offline)
+	}
+
+	/// Build the BinRepo from the repo (name or url) if defined, otherwise from the config defaults,
+	/// otherwise the binst repo.
+	pub fn from_repo(
+		bin_name: &str,
+		repo: Option<&str>,
+		profile: Option<&str>,
+		target: Option<String>,
+		offline: bool,
+	) -> Result<Self> {
 		let bin_name = bin_name.to_string();
 
-		let target = if publish {
-			argc.get_one::<String>("target").map(|target| target.to_string())
-		} else {
-			None
+		// build the RepoInfo
+		let config = Config::load()?;
+		let install_repo_name = repo.map(|s| s.to_string()).or_else(|| config.default_install_repo());
+		let publish_repo_name = repo.map(|s| s.to_string()).or_else(|| config.default_publish_repo());
+		let install_repo = match &install_repo_name {
+			Some(repo) => RepoInfo::from_repo_string(repo, profile)?,
+			None => RepoInfo::binst_install_repo(),
+		};
+		let install_mirrors = match &install_repo_name {
+			Some(repo) => RepoInfo::mirrors_from_repo_string(repo)?,
+			None => Vec::new(),
+		};
+		let trust_mirrors = install_repo_name
+			.as_deref()
+			.and_then(|repo| config.repo(repo).or_else(|| config.repo_with_url(repo)))
+			.map(|repo_config| repo_config.trust_mirrors)
+			.unwrap_or(false);
+		let publish_repo = match &publish_repo_name {
+			Some(repo) => RepoInfo::from_repo_string(repo, profile)?,
+			None => RepoInfo::binst_publish_repo(),
 		};
 
-		// build the RepoInfo
-		let argc_profile = argc.get_one::<String>("profile").map(|s| s.as_str());
-		let argc_repo = argc.get_one::<String>("repo");
-		let (install_repo, publish_repo) = if let Some(repo) = argc_repo {
-			let install_repo = RepoInfo::from_repo_string(repo, argc_profile)?;
-			let publish_repo = RepoInfo::from_repo_string(repo, argc_profile)?;
-			(install_repo, publish_repo)
-		} else {
-			(RepoInfo::binst_install_repo(), RepoInfo::binst_publish_repo())
+		let targets = match &target {
+			Some(target) => vec![target.to_string()],
+			None => target_fallbacks(&os_target(), config.targets(install_repo_name.as_deref())),
 		};
 
 		Ok(BinRepo {
 			bin_name,
 			install_repo,
+			install_mirrors,
+			trust_mirrors,
+			failed_install_repos: Mutex::new(Vec::new()),
 			publish_repo,
 			target,
+			targets,
+			resolved_target: OnceLock::new(),
+			offline,
 		})
 	}
+
+	/// Set the target (e.g., the one of the installed package on update), as with `--target`.
+	pub fn with_target(mut self, target: String) -> Self {
+		self.targets = vec![target.clone()];
+		self.target = Some(target);
+		self
+	}
 }
 
 /// Public functions
 impl BinRepo {
+	/// The target of the bin, the resolved one if already resolved (see `resolve_target`),
+	/// otherwise the first of the targets.
+	pub fn bin_target(&self) -> String {
+		match self.resolved_target.get() {
+			Some(target) => target.to_string(),
+			None => self.targets.first().cloned().unwrap_or_else(os_target),
+		}
+	}
+
+	/// Set the resolved target, and report it if it is a fallback target.
+	fn resolve_target(&self, target: &str) {
+		let first_target = self.targets.first().map(|t| t.as_str()).unwrap_or_default();
+		if self.resolved_target.set(target.to_string()).is_ok() && first_target != target {
+			println!(
+				"  Target {} not available, using fallback target {}",
+				first_target, target
+			);
+		}
+	}
+
 	pub fn origin_bin_target_uri(&self, stream_or_path: &str) -> String {
-		let target = self.target.as_ref().map(|s| s.to_string()).unwrap_or_else(os_target);
+		self.origin_target_uri(&self.bin_target(), stream_or_path)
+	}
+
+	fn origin_target_uri(&self, target: &str, stream_or_path: &str) -> String {
 		format!("{}/{}/{}", self.bin_name, target, stream_or_path)
 	}
 
-	pub fn get_origin_url(&self, stream_or_path: &str, version: &Version) -> Result<String> {
-		let url = match &self.install_repo {
-			RepoInfo::Local(local_repo_origin) => self
-				.get_origin_local_path(local_repo_origin, stream_or_path, version)?
-				.to_string_lossy()
-				.to_string(),
-			RepoInfo::S3(s3_info) => self.get_origin_s3_url(s3_info, stream_or_path, version)?,
-			RepoInfo::Http(base_url) => self.get_origin_http_url(base_url, stream_or_path, version)?,
-		};
+	/// Returns the url of the origin package `.tar.gz` (for reporting).
+	pub async fn get_origin_url(&self, stream_or_path: &str, version: &Version) -> Result<String> {
+		let backend = new_repo_backend(&self.install_repo).await?;
+		let gz_key = self.origin_package_key(stream_or_path, version, &format!("{}.tar.gz", self.bin_name));
+		Ok(backend.key_url(&gz_key))
+	}
 
-		Ok(url)
+	/// The key of a package version file (e.g., `cool_cli/x86_64-unknown-linux-gnu/main/0.1.2/cool_cli.tar.gz`).
+	fn origin_package_key(&self, stream_or_path: &str, version: &Version, file_name: &str) -> String {
+		format!(
+			"{}/{}/{}",
+			self.origin_bin_target_uri(stream_or_path),
+			get_version_part(version),
+			file_name
+		)
 	}
 }
 
 // region:    BinRepo path function helpers
-fn make_bin_temp_dir(bin_name: &str) -> Result<PathBuf> {
+
+/// Temporary dir (under `~/.binst/tmp/`) removed when dropped, on success or on error.
+pub struct TmpDir(PathBuf);
+
+impl Deref for TmpDir {
+	type Target = Path;
+
+	fn deref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl Drop for TmpDir {
+	fn drop(&mut self) {
+		if let Err(ex) = safer_remove_dir(&self.0) {
+			println!("WARNING - could not remove tmp dir {} - {}", self.0.to_string_lossy(), ex);
+		}
+	}
+}
+
+fn make_bin_temp_dir(bin_name: &str) -> Result<TmpDir> {
 	let start = SystemTime::now().duration_since(UNIX_EPOCH).expect("time anomaly?").as_millis();
 
-	let path = binst_tmp_dir(Some(&format!("{}-{}", bin_name, start)))?;
-	Ok(path)
+	let path = binst_tmp_dir(Some(&format!("{}-{}-{}", bin_name, start, std::process::id())))?;
+	Ok(TmpDir(path))
 }
 
 fn get_release_bin(name: &str, target: &Option<String>) -> Result<PathBuf> {
@@ -172,14 +544,14 @@ pub fn extract_stream(version: &Version) -> String {
 
 // region:    Self/Install/Update helpers
 
-//// Returns version path part.
+/// Returns version path part.
 pub fn get_version_part(version: &Version) -> String {
 	version.to_string()
 }
 
 pub fn create_bin_symlink(bin_name: &str, unpacked_bin: &Path) -> Result<PathBuf> {
 	// make sure the .binst/bin/ directory exists
-	let bin_dir = binst_bin_dir();
+	let bin_dir = binst_bin_dir()?;
 	if !bin_dir.is_dir() {
 		create_dir_all(&bin_dir)?;
 	}
@@ -189,29 +561,175 @@ pub fn create_bin_symlink(bin_name: &str, unpacked_bin: &Path) -> Result<PathBuf
 			unpacked_bin.to_string_lossy().to_string(),
 		));
 	}
-	let bin_symlink_path = binst_bin_dir().join(bin_name);
-	if bin_symlink_path.is_file() {
-		remove_file(&bin_symlink_path)?;
-	}
-	sym_link(unpacked_bin, &bin_symlink_path)?;
+	let bin_symlink_path = bin_dir.join(bin_name);
+	check_bin_replaceable(&bin_symlink_path)?;
+
+	// Create the new symlink under a temporary name, and rename it over the current one (atomic).
+	// This way, the bin is never missing, and the previous one stays active if anything fails.
+	replace_symlink(&bin_link_target(&bin_dir, unpacked_bin)?, &bin_symlink_path)?;
+
 	Ok(bin_symlink_path)
 }
 
-pub fn create_install_toml(package_dir: &Path, repo: &str, stream: &str, version: &Version) -> Result<()> {
-	let install_content = create_install_toml_content(repo, stream, version);
+/// Make the package version the active one: the `packages/<bin>/active` link, and the `bin/<bin>` symlink,
+/// or the `bin/<bin>` shim in shim mode (config `defaults.shims = true`, see `cmd/shim.rs`).
+/// Returns the `bin/<bin>` path.
+pub fn activate_package(bin_name: &str, version: &Version) -> Result<PathBuf> {
+	let package_dir = binst_package_dir(bin_name)?;
+	let unpacked_bin = package_dir.join(get_version_part(version)).join("unpacked").join(bin_name);
+	if !unpacked_bin.is_file() {
+		return Err(Error::UnpackedBinFileNotFound(
+			unpacked_bin.to_string_lossy().to_string(),
+		));
+	}
+
+	// Note: binst itself is never shimmed (it runs the shims)
+	let bin_path = if bin_name != "binst" && Config::load()?.shims() {
+		create_bin_shim(bin_name)?
+	} else {
+		create_bin_symlink(bin_name, &unpacked_bin)?
+	};
+
+	// the active link is relative to the package dir (e.g., `active -> 0.4.1`)
+	replace_symlink(Path::new(&get_version_part(version)), &package_dir.join(ACTIVE_LINK))?;
+
+	Ok(bin_path)
+}
+
+/// Write the `bin/<bin>` shim script, which runs the `binst shim-exec <bin>` to select the version.
+fn create_bin_shim(bin_name: &str) -> Result<PathBuf> {
+	let bin_dir = binst_bin_dir()?;
+	if !bin_dir.is_dir() {
+		create_dir_all(&bin_dir)?;
+	}
+
+	// Note: with the binst dir as --root, so that it works for --root, --system, and BINST_HOME installs
+	let content = format!(
+		"#!/bin/sh\n{SHIM_MARKER}, the version is selected by 'binst shim-exec'\nexec {} --root {} shim-exec {} \"$@\"\n",
+		sh_quote(&shim_binst_exe(&bin_dir)?.to_string_lossy()),
+		sh_quote(&binst_dir()?.to_string_lossy()),
+		sh_quote(bin_name)
+	);
+
+	let shim_path = bin_dir.join(bin_name);
+	check_bin_replaceable(&shim_path)?;
+	let tmp_shim_path = bin_dir.join(format!(".{}.tmp-{}", bin_name, std::process::id()));
+	write(&tmp_shim_path, content)?;
+	set_permissions(&tmp_shim_path, Permissions::from_mode(0o755))?;
+	if let Err(ex) = rename(&tmp_shim_path, &shim_path) {
+		let _ = remove_file(&tmp_shim_path);
+		return Err(ex.into());
+	}
+
+	Ok(shim_path)
+}
+
+/// The binst path run by the shims, stable across the binst updates: the `bin/binst` if present, otherwise the current binst,
+/// through its package `active` link when it is a package version (e.g., `packages/binst/0.1.6/unpacked/binst`, removed by an update).
+fn shim_binst_exe(bin_dir: &Path) -> Result<PathBuf> {
+	let binst_bin = bin_dir.join("binst");
+	if binst_bin.is_file() {
+		return Ok(binst_bin);
+	}
+
+	let exe = std::env::current_exe()?;
+	let package_dir = exe
+		.parent()
+		.filter(|dir| dir.ends_with("unpacked"))
+		.and_then(|unpacked_dir| unpacked_dir.parent()?.parent())
+		.filter(|dir| dir.ends_with("packages/binst"));
+	match package_dir {
+		Some(package_dir) => {
+			let active_exe = package_dir.join(ACTIVE_LINK).join("unpacked").join("binst");
+			match active_exe.is_file() {
+				true => Ok(active_exe),
+				false => Err(Error::ShimNoStableBinst(exe.to_string_lossy().to_string())),
+			}
+		}
+		None => Ok(exe),
+	}
+}
+
+/// Single quote the value for `/bin/sh` (the `'` as `'\''`), e.g., for the paths in the shims.
+fn sh_quote(value: &str) -> String {
+	format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Fails if the `bin/<bin>` exists and was not installed by binst (e.g., a distro binary in `/usr/local/bin` for `--system`).
+/// Replaceable: missing, a link into the binst packages dir, or a binst shim.
+fn check_bin_replaceable(bin_path: &Path) -> Result<()> {
+	let Ok(metadata) = bin_path.symlink_metadata() else {
+		return Ok(());
+	};
+
+	let from_binst = if metadata.file_type().is_symlink() {
+		// Note: resolved lexically, as the link can be dangling (e.g., package dir removed by hand)
+		let link_dir = bin_path.parent().unwrap_or_else(|| Path::new(""));
+		let mut target = PathBuf::new();
+		for comp in link_dir.join(read_link(bin_path)?).components() {
+			match comp {
+				Component::ParentDir => {
+					target.pop();
+				}
+				Component::CurDir => (),
+				comp => target.push(comp),
+			}
+		}
+		target.starts_with(binst_dir()?.join("packages"))
+	} else {
+		metadata.is_file() && read_to_string(bin_path).is_ok_and(|content| content.contains(SHIM_MARKER))
+	};
+
+	match from_binst {
+		true => Ok(()),
+		false => Err(Error::BinNotFromBinst(bin_path.to_string_lossy().to_string())),
+	}
+}
+
+/// Create the symlink under a temporary name, and rename it over the eventual current one (atomic).
+fn replace_symlink(original: &Path, link: &Path) -> Result<()> {
+	let file_name = link.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+	let tmp_link = link.with_file_name(format!(".{}.tmp-{}", file_name, std::process::id()));
+	if tmp_link.symlink_metadata().is_ok() {
+		remove_file(&tmp_link)?;
+	}
+	sym_link(original, &tmp_link)?;
+	if let Err(ex) = rename(&tmp_link, link) {
+		let _ = remove_file(&tmp_link);
+		return Err(ex.into());
+	}
+	Ok(())
+}
+
+/// The symlink target for the unpacked bin, relative to the bin dir (`../packages/...`) when the bin dir is
+/// the binst dir `bin/`, so that the binst dir can be moved (e.g., `--root` image dir mounted somewhere else).
+/// Absolute otherwise (e.g., `/usr/local/bin` for `--system`).
+fn bin_link_target(bin_dir: &Path, unpacked_bin: &Path) -> Result<PathBuf> {
+	let binst_dir = binst_dir()?;
+	if bin_dir == binst_dir.join("bin") {
+		if let Ok(rel_path) = unpacked_bin.strip_prefix(&binst_dir) {
+			return Ok(Path::new("..").join(rel_path));
+		}
+	}
+	Ok(unpacked_bin.to_path_buf())
+}
+
+pub fn create_install_toml(package_dir: &Path, repo: &str, stream: &str, version: &Version, target: &str) -> Result<()> {
+	let install_content = create_install_toml_content(repo, stream, version, target);
 	let install_path = package_dir.join("install.toml");
 	File::create(install_path)?.write_all(install_content.as_bytes())?;
 	Ok(())
 }
 
-fn create_install_toml_content(repo: &str, stream: &str, version: &Version) -> String {
+fn create_install_toml_content(repo: &str, stream: &str, version: &Version, target: &str) -> String {
 	format!(
 		r#"[install]		
 repo = "{}"
 stream = "{}"
 version = "{}"
+target = "{}"
 "#,
-		repo, stream, version
+		repo, stream, version, target
 	)
 }
 
@@ -221,6 +739,57 @@ version = "{}"
 mod tests {
 	use super::*;
 
+	#[test]
+	fn test_sh_quote() {
+		assert_eq!("'/opt/binst'", sh_quote("/opt/binst"));
+		assert_eq!(r#"'/a $b `c` "d" '\''e'\'''"#, sh_quote(r#"/a $b `c` "d" 'e'"#));
+	}
+
+	#[test]
+	fn test_check_bin_replaceable() -> Result<()> {
+		let test_dir = backend::tests::set_test_binst_root("bin-replaceable")?;
+		let bin_dir = test_dir.join("bin");
+		create_dir_all(&bin_dir)?;
+		let package_bin = binst_package_dir("cool_cli")?.join("0.1.0/unpacked/cool_cli");
+
+		// -- missing, links into the packages dir (even dangling), and shims are replaceable
+		assert!(check_bin_replaceable(&bin_dir.join("cool_cli")).is_ok());
+		sym_link(&package_bin, &bin_dir.join("abs_link"))?;
+		assert!(check_bin_replaceable(&bin_dir.join("abs_link")).is_ok());
+		let rel_package_bin = Path::new("../..").join(package_bin.strip_prefix(binst_dir()?).unwrap());
+		sym_link(&rel_package_bin, &bin_dir.join("rel_link"))?;
+		assert!(check_bin_replaceable(&bin_dir.join("rel_link")).is_ok());
+		write(bin_dir.join("shim"), format!("#!/bin/sh\n{SHIM_MARKER}\n"))?;
+		assert!(check_bin_replaceable(&bin_dir.join("shim")).is_ok());
+
+		// -- other files and links are not (e.g., a distro binary)
+		write(bin_dir.join("other_bin"), "ELF")?;
+		assert!(matches!(check_bin_replaceable(&bin_dir.join("other_bin")), Err(Error::BinNotFromBinst(_))));
+		sym_link(Path::new("/usr/bin/env"), &bin_dir.join("other_link"))?;
+		assert!(matches!(check_bin_replaceable(&bin_dir.join("other_link")), Err(Error::BinNotFromBinst(_))));
+		sym_link(&binst_dir()?.join("packages/../other"), &bin_dir.join("escaping_link"))?;
+		assert!(matches!(check_bin_replaceable(&bin_dir.join("escaping_link")), Err(Error::BinNotFromBinst(_))));
+
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_repo_http_auth_env() {
+		let env_var = |name: &str| (name == HTTP_TOKEN_ENV).then(|| "env-token".to_string());
+
+		// -- the env only for a config repo, and the config repo auth first
+		assert!(repo_http_auth(None, env_var).is_none());
+		let auth = repo_http_auth(Some(&RepoConfig::default()), env_var);
+		assert!(matches!(auth, Some(HttpAuth::Bearer(token)) if token == "env-token"));
+		let repo_config = RepoConfig {
+			user: Some("ci-user".to_string()),
+			..Default::default()
+		};
+		let auth = repo_http_auth(Some(&repo_config), env_var);
+		assert!(matches!(auth, Some(HttpAuth::Basic(user, None)) if user == "ci-user"));
+	}
+
 	#[test]
 	fn test_extract_stream() {
 		fn run(v: &str) -> String {
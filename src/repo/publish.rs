@@ -1,19 +1,18 @@
+use super::backend::new_repo_backend;
 use crate::cmd::CARGO_TOML;
-use crate::repo::s3w::new_repo_bucket;
-use crate::repo::{extract_stream, get_release_bin, get_version_part, make_bin_temp_dir, BinRepo, RepoInfo, S3Info};
-use crate::repo::{Error, Result};
-use crate::utils::{clean_path, exec_cmd_args, get_toml_value_as_string, safer_remove_dir};
+use crate::repo::{extract_stream, get_release_bin, get_version_part, make_bin_temp_dir, BinRepo, LATEST_TOML};
+use crate::repo::Result;
+use crate::utils::{clean_path, exec_cmd_args, get_toml_value_as_string, sha256_file};
 use libflate::gzip::Encoder;
 use semver::Version;
-use std::fs::{copy, create_dir, create_dir_all, read_to_string, write, File};
+use std::fs::{copy, create_dir, read_to_string, write, File};
 use std::io::{BufReader, Write};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use tar::Builder;
 use toml::Value;
 
 #[derive(Debug)]
 struct UploadRec {
-	latest_toml: PathBuf,
 	gz: PathBuf,
 	package_toml: PathBuf,
 	version: Version,
@@ -26,7 +25,7 @@ impl BinRepo {
 	pub async fn publish(&self, at_path: Option<String>) -> Result<()> {
 		let bin_name = &self.bin_name;
 
-		// create the temp dir
+		// create the temp dir (removed on drop, even on error)
 		let tmp_dir = make_bin_temp_dir(bin_name)?;
 
 		// read the Cargo.toml version
@@ -62,15 +61,6 @@ impl BinRepo {
 		let to_pack_file = to_pack_dir.join(bin_name);
 		copy(&bin_file, &to_pack_file)?;
 
-		// create the latest file
-		let latest_toml_path = tmp_dir.join("latest.toml");
-		write(&latest_toml_path, create_latest_toml_content(&version))?;
-
-		// create the package file
-		let package_toml_path = tmp_dir.join("package.toml");
-		let package_content = create_package_toml_content(bin_name, &stream, &at_path, &version);
-		write(&package_toml_path, package_content)?;
-
 		println!("   packing: {}", to_pack_file.to_string_lossy());
 		// create tar
 		let tar_name = format!("{}.tar", bin_name);
@@ -78,6 +68,7 @@ impl BinRepo {
 		let tar_file = File::create(&tar_path).unwrap();
 		let mut tar_file = Builder::new(tar_file);
 		tar_file.append_file(&self.bin_name, &mut File::open(bin_file)?)?;
+		tar_file.finish()?;
 
 		// create gz
 		let gz_name = format!("{}.gz", &tar_name);
@@ -91,9 +82,14 @@ impl BinRepo {
 		gz_file.write_all(&encoded_data)?;
 		println!("    packed: {}", gz_path.to_string_lossy());
 
+		// create the package file (with the gz checksum)
+		let sha256 = sha256_file(&gz_path)?;
+		let package_toml_path = tmp_dir.join("package.toml");
+		let package_content = create_package_toml_content(bin_name, &stream, &at_path, &version, &sha256);
+		write(&package_toml_path, package_content)?;
+
 		// start the upload
 		let rec = UploadRec {
-			latest_toml: latest_toml_path,
 			gz: gz_path,
 			version,
 			stream: stream.to_string(),
@@ -101,133 +97,92 @@ impl BinRepo {
 			at_path,
 		};
 
-		match &self.publish_repo {
-			RepoInfo::Local(local_repo) => self.upload_to_local(local_repo, rec)?,
-			RepoInfo::S3(s3_info) => self.upload_to_s3(s3_info, rec).await?,
-			RepoInfo::Http(_) => return Err(Error::HttpProtocolNotSupportedForPublish),
-		};
-
-		// TODO - needds to make sure clean dir even if error above. Wrap in function.
-		safer_remove_dir(&tmp_dir)?;
+		self.upload(rec).await?;
 
 		Ok(())
 	}
 }
 
-// upload to local
+// upload to the publish repo
 impl BinRepo {
-	fn upload_to_local(&self, origin_repo: &str, upload_rec: UploadRec) -> Result<()> {
+	async fn upload(&self, upload_rec: UploadRec) -> Result<()> {
 		let UploadRec {
 			version,
-			latest_toml,
 			gz: gz_file_path,
 			stream,
 			package_toml,
 			at_path,
 		} = upload_rec;
 
-		let is_at_path = at_path.is_some();
-		let path_or_stream = at_path.unwrap_or(stream);
-		let origin_target_dir = Path::new(origin_repo).join(self.origin_bin_target_uri(&path_or_stream));
-
-		if !origin_target_dir.is_file() {
-			create_dir_all(&origin_target_dir)?;
-		}
-
-		//// copy the latest.toml
-		if !is_at_path {
-			let origin_info_path = origin_target_dir.join("latest.toml");
-			copy(latest_toml, &origin_info_path)?;
-			println!("    copied: {}", origin_info_path.to_string_lossy());
-		}
-
-		//// build the package dir for version or at_path
-		let package_dir = if is_at_path {
-			origin_target_dir
-		} else {
-			origin_target_dir.join(get_version_part(&version))
-		};
-		if !package_dir.is_file() {
-			create_dir_all(&package_dir)?;
-		}
-
-		//// copy the gz file
-		let origin_gz_path = package_dir.join(format!("{}.tar.gz", self.bin_name));
-		copy(gz_file_path, &origin_gz_path)?;
-		println!("    copied: {}", origin_gz_path.to_string_lossy());
-
-		//// copy the package toml
-		let origin_package_path = package_dir.join(format!("{}.toml", self.bin_name));
-		copy(package_toml, &origin_package_path)?;
-		println!("    copied: {}", origin_package_path.to_string_lossy());
-
-		Ok(())
-	}
-}
-
-// upload to s3
-impl BinRepo {
-	async fn upload_to_s3(&self, s3_info: &S3Info, upload_rec: UploadRec) -> Result<()> {
-		let bin_name = &self.bin_name;
-
-		let UploadRec {
-			version,
-			latest_toml,
-			gz: gz_file_path,
-			stream,
-			package_toml: package_toml_path,
-			at_path,
-		} = upload_rec;
-
 		let is_at_path = at_path.is_some();
 		let path_or_stream = at_path.unwrap_or(stream);
 		let origin_target_key = self.origin_bin_target_uri(&path_or_stream);
 
-		//// Create the bucket client
-		// let bucket = build_new_aws_bucket_client(bucket_name, profile).await?;
-
-		let bucket = new_repo_bucket(s3_info.profile.clone()).await?;
-
-		//// Upload latest.toml
-		if !is_at_path {
-			let latest_key = clean_path(format!("{}/latest.toml", origin_target_key));
-			let content = read_to_string(latest_toml)?;
-
-			let s3_url = bucket.upload_text(s3_info, &latest_key, content, None).await?;
-			println!("  uploaded: {s3_url}");
-		}
+		let backend = new_repo_backend(&self.publish_repo).await?;
 
 		//// build the package key
 		let package_key = if is_at_path {
-			origin_target_key
+			origin_target_key.clone()
 		} else {
 			format!("{}/{}", origin_target_key, get_version_part(&version))
 		};
 
-		//// Upload the package gz
-		let gz_key = clean_path(format!("{}/{}.tar.gz", package_key, bin_name));
-		// TODO: need to stream content
-		let url = bucket.upload_file(s3_info, &gz_key, &gz_file_path).await?;
-		println!("  uploaded: {url}");
+		//// Upload the package gz and toml (the package dir)
+		let package_key = clean_path(package_key);
+		let gz_name = format!("{}.tar.gz", self.bin_name);
+		let toml_name = format!("{}.toml", self.bin_name);
+		let gz_key = format!("{}/{}", package_key, gz_name);
+		let is_new = !backend.exists(&gz_key).await?;
+		if !is_new {
+			println!(" replacing: {}", backend.key_url(&gz_key));
+		}
+		// Note: For the backends uploading one key at a time (see `RepoBackend::put_dir`), the toml first for a new version,
+		//       so that the gz is never there without its sha256.
+		let files = match is_new {
+			true => [(toml_name.as_str(), package_toml.as_path()), (gz_name.as_str(), gz_file_path.as_path())],
+			false => [(gz_name.as_str(), gz_file_path.as_path()), (toml_name.as_str(), package_toml.as_path())],
+		};
+		// Note: The at-path dir can have other files (not published by binst), so only its package files are replaced.
+		if is_at_path {
+			for (file_name, file) in files.iter() {
+				backend.put_file(&format!("{}/{}", package_key, file_name), file).await?;
+			}
+		} else {
+			backend.put_dir(&package_key, &files).await?;
+		}
+		for (file_name, _) in files.iter() {
+			println!("  uploaded: {}", backend.key_url(&format!("{}/{}", package_key, file_name)));
+		}
+
+		//// Upload latest.toml last (when the package is uploaded)
+		if !is_at_path {
+			let latest_key = clean_path(format!("{}/{}", origin_target_key, LATEST_TOML));
+			backend.put_text(&latest_key, &create_latest_toml_content(&version)).await?;
+			println!("  uploaded: {}", backend.key_url(&latest_key));
+		}
 
-		//// Upload the package toml
-		let package_key = clean_path(format!("{}/{}.toml", package_key, bin_name));
-		let content = read_to_string(package_toml_path)?;
-		let url = bucket.upload_text(s3_info, &package_key, content, None).await?;
-		println!("  uploaded: {url}");
+		// Note: e.g., the git commit and push of the package files (all or nothing)
+		backend.flush().await?;
 
 		Ok(())
 	}
 }
 
-fn create_package_toml_content(bin_name: &str, stream: &str, path: &Option<String>, version: &Version) -> String {
+fn create_package_toml_content(
+	bin_name: &str,
+	stream: &str,
+	path: &Option<String>,
+	version: &Version,
+	sha256: &str,
+) -> String {
 	let mut content = format!(
 		r#"[package]
 name = "{}"		
 stream = "{}"
 version = "{}"
+sha256 = "{}"
 "#,
-		bin_name, stream, version
+		bin_name, stream, version, sha256
 	);
 
 	if let Some(path) = path {
@@ -240,3 +195,59 @@ version = "{}"
 fn create_latest_toml_content(version: &Version) -> String {
 	format!("[latest]\nversion = \"{}\"", version)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::repo::backend::{LocalBackend, RepoBackend};
+	use crate::repo::RepoInfo;
+	use std::sync::{Mutex, OnceLock};
+
+	#[tokio::test]
+	async fn test_upload_at_path_keeps_other_files() -> Result<()> {
+		let test_dir = super::super::backend::tests::set_test_binst_root("publish-at-path")?;
+		let repo_dir = test_dir.join("repo").to_string_lossy().to_string();
+		let repo = LocalBackend::new(&repo_dir);
+		repo.put_text("cool_cli/x86/tools/README.md", "other file").await?;
+		repo.put_text("cool_cli/x86/tools/sub/notes.txt", "other sub file").await?;
+
+		let (gz, package_toml) = (test_dir.join("cool_cli.tar.gz"), test_dir.join("cool_cli.toml"));
+		std::fs::write(&gz, "fake gz")?;
+		std::fs::write(&package_toml, "[package]")?;
+		let bin_repo = BinRepo {
+			bin_name: "cool_cli".to_string(),
+			install_repo: RepoInfo::Local(repo_dir.clone()),
+			install_mirrors: Vec::new(),
+			trust_mirrors: false,
+			failed_install_repos: Mutex::new(Vec::new()),
+			publish_repo: RepoInfo::Local(repo_dir.clone()),
+			target: Some("x86".to_string()),
+			targets: vec!["x86".to_string()],
+			resolved_target: OnceLock::new(),
+			offline: false,
+		};
+		let upload_rec = || UploadRec {
+			gz: gz.clone(),
+			package_toml: package_toml.clone(),
+			version: Version::parse("0.1.0").unwrap(),
+			stream: "main".to_string(),
+			at_path: Some("tools".to_string()),
+		};
+
+		// -- new, then replaced, the other files of the at-path dir are kept
+		bin_repo.upload(upload_rec()).await?;
+		bin_repo.upload(upload_rec()).await?;
+		assert_eq!(
+			vec![
+				"cool_cli/x86/tools/README.md",
+				"cool_cli/x86/tools/cool_cli.tar.gz",
+				"cool_cli/x86/tools/cool_cli.toml",
+				"cool_cli/x86/tools/sub/notes.txt"
+			],
+			repo.list("cool_cli/").await?
+		);
+
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
+}
@@ -1,15 +1,27 @@
-use crate::repo::Result;
-use crate::repo::{S3Info, BINST_REPO_AWS_PROFILE, BINST_REPO_BUCKET, BINST_REPO_URL};
+use crate::config::{Config, RepoConfig};
+use crate::repo::{Error, Result};
+use crate::repo::{GitInfo, GithubInfo, HttpInfo, OciInfo, S3Info, SftpInfo, BINST_REPO_AWS_PROFILE, BINST_REPO_BUCKET, BINST_REPO_URL};
 use crate::utils::clean_path;
+use dirs::home_dir;
+use std::env;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum RepoInfo {
-	// local path dir
+	// local dir path (absolute)
 	Local(String),
 	// S3, only support via profile for now
 	S3(S3Info),
-	// http/https, only for install
-	Http(String),
+	// http/https, publish with PUT (e.g., WebDAV server)
+	Http(HttpInfo),
+	// sftp, with the sftp command
+	Sftp(SftpInfo),
+	// OCI registry, with the registry http api
+	Oci(OciInfo),
+	// git repo, with the git command
+	Git(GitInfo),
+	// GitHub (or Gitea) release assets, install only
+	Github(GithubInfo),
 }
 
 impl RepoInfo {
@@ -17,7 +29,11 @@ impl RepoInfo {
 		match self {
 			RepoInfo::Local(url) => url,
 			RepoInfo::S3(s3_info) => &s3_info.url,
-			RepoInfo::Http(url) => url,
+			RepoInfo::Http(http_info) => &http_info.url,
+			RepoInfo::Sftp(sftp_info) => &sftp_info.url,
+			RepoInfo::Oci(oci_info) => &oci_info.url,
+			RepoInfo::Git(git_info) => &git_info.url,
+			RepoInfo::Github(github_info) => &github_info.url,
 		}
 	}
 }
@@ -35,18 +51,213 @@ impl RepoInfo {
 	}
 
 	pub fn binst_install_repo() -> RepoInfo {
-		RepoInfo::Http(clean_path(BINST_REPO_URL))
+		RepoInfo::Http(HttpInfo::from_public_url(&clean_path(BINST_REPO_URL)))
 	}
 
+	/// Build the RepoInfo from a repo name of the config (e.g., `-r internal`), or from a repo url
+	/// (with the config of the repo with this url if any, e.g., the recorded repo of an install).
+	/// Note: The `profile` argument overrides the config repo profile.
 	pub fn from_repo_string(repo: &str, profile: Option<&str>) -> Result<RepoInfo> {
+		let config = Config::load()?;
+		if let Some(repo_config) = config.repo(repo).or_else(|| config.repo_with_url(repo)) {
+			let profile = profile.or(repo_config.profile.as_deref());
+			return Self::from_repo_url(&repo_config.url, profile, Some(&repo_config));
+		}
+		Self::from_repo_url(repo, profile, None)
+	}
+
+	/// Build the mirrors of the config repo (name or url, as for `from_repo_string`), in order.
+	/// Empty if the repo is not in the config, or has no `mirrors`.
+	pub fn mirrors_from_repo_string(repo: &str) -> Result<Vec<RepoInfo>> {
+		let config = Config::load()?;
+		let repo_config = config.repo(repo).or_else(|| config.repo_with_url(repo));
+		let mirrors = repo_config.map(|c| c.mirrors).unwrap_or_default();
+		mirrors.iter().map(|mirror| Self::from_repo_string(mirror, None)).collect()
+	}
+
+	/// Note: The `repo_config` is the config repo of the url if named (e.g., for the http auth).
+	fn from_repo_url(repo: &str, profile: Option<&str>, repo_config: Option<&RepoConfig>) -> Result<RepoInfo> {
 		let repo_info = if repo.starts_with("s3://") {
 			RepoInfo::S3(S3Info::from_s3_url(repo, profile)?)
 		} else if repo.starts_with("http://") || repo.starts_with("https://") {
-			RepoInfo::Http(clean_path(repo))
+			RepoInfo::Http(HttpInfo::from_http_url(&clean_path(repo), repo_config))
+		} else if repo.starts_with("sftp://") {
+			RepoInfo::Sftp(SftpInfo::from_sftp_url(repo, repo_config)?)
+		} else if repo.starts_with("oci://") {
+			RepoInfo::Oci(OciInfo::from_oci_url(repo, repo_config)?)
+		} else if repo.starts_with("git+") {
+			RepoInfo::Git(GitInfo::from_git_url(repo, repo_config)?)
+		} else if repo.starts_with("github:") {
+			RepoInfo::Github(GithubInfo::from_github_url(repo, repo_config)?)
+		} else if let Some(rest) = repo.strip_prefix("file://") {
+			let path = file_url_path(rest).ok_or_else(|| Error::RepoInvalidFile(repo.to_string()))?;
+			RepoInfo::Local(local_repo_path(path)?)
+		} else if let Some(scheme) = url_scheme(repo) {
+			return Err(Error::RepoUnsupportedScheme(scheme.to_string(), repo.to_string()));
 		} else {
-			RepoInfo::Local(clean_path(repo))
+			RepoInfo::Local(local_repo_path(repo)?)
 		};
 
 		Ok(repo_info)
 	}
 }
+
+// region:    --- RepoInfo Private Utils
+/// Returns the scheme of a `scheme://...` repo string (e.g., `gs` for `gs://bucket`).
+fn url_scheme(repo: &str) -> Option<&str> {
+	let (scheme, _) = repo.split_once("://")?;
+	let mut chars = scheme.chars();
+	let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+	valid.then_some(scheme)
+}
+
+/// Returns the path of a `file://` url (the part after the scheme), None if the host is not empty or `localhost`
+/// (e.g., `file://tmp/repo` is the host `tmp`, not the relative path `tmp/repo`).
+pub(super) fn file_url_path(rest: &str) -> Option<&str> {
+	let path = rest.strip_prefix("localhost").unwrap_or(rest);
+	path.starts_with('/').then_some(path)
+}
+
+/// Returns the absolute local dir path, with the `~` expanded, and relative paths from the current dir
+/// (so that the `install.toml` repo is valid from any dir, e.g., for `binst update`).
+pub(super) fn local_repo_path(path: &str) -> Result<String> {
+	let path = match path.strip_prefix('~') {
+		Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+			let home_dir = home_dir().ok_or_else(|| Error::RepoNoHomeDir(path.to_string()))?;
+			home_dir.join(rest.trim_start_matches('/'))
+		}
+		_ => PathBuf::from(path),
+	};
+	let path = match path.is_absolute() {
+		true => path,
+		false => env::current_dir()?.join(path),
+	};
+
+	// Note: normalizes the redundant `/`, the `./`, and the trailing `/`
+	let path: PathBuf = path.components().collect();
+	Ok(path.to_string_lossy().to_string())
+}
+// endregion: --- RepoInfo Private Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn local(repo: &str) -> Result<String> {
+		match RepoInfo::from_repo_url(repo, None, None)? {
+			RepoInfo::Local(path) => Ok(path),
+			other => panic!("{} should be local, but was {:?}", repo, other),
+		}
+	}
+
+	#[test]
+	fn test_repo_info_local() -> Result<()> {
+		let current_dir = env::current_dir()?;
+
+		assert_eq!("/mnt/repo", local("/mnt/repo")?);
+		assert_eq!("/mnt/repo", local("/mnt//repo/")?);
+		assert_eq!("/mnt/repo", local("file:///mnt/repo")?);
+		assert_eq!(current_dir.join("repo").to_string_lossy(), local("./repo/")?);
+		assert_eq!("/mnt/repo", local("file://localhost/mnt/repo/")?);
+		assert!(matches!(RepoInfo::from_repo_url("file://tmp/repo", None, None), Err(Error::RepoInvalidFile(_))));
+		assert!(matches!(RepoInfo::from_repo_url("file://host/mnt/repo", None, None), Err(Error::RepoInvalidFile(_))));
+		if let Some(home_dir) = home_dir() {
+			assert_eq!(home_dir.join("repo").to_string_lossy(), local("~/repo")?);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_repo_info_s3() -> Result<()> {
+		assert!(matches!(RepoInfo::from_repo_url("s3://bucket/base", None, None)?, RepoInfo::S3(_)));
+		Ok(())
+	}
+
+	#[test]
+	fn test_repo_info_http() -> Result<()> {
+		assert!(matches!(RepoInfo::from_repo_url("https://repo.binst.io/", None, None)?, RepoInfo::Http(info) if info.url == "https://repo.binst.io"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_repo_info_unsupported_scheme() {
+		assert!(matches!(
+			RepoInfo::from_repo_url("gs://bucket/base", None, None),
+			Err(Error::RepoUnsupportedScheme(scheme, _)) if scheme == "gs"
+		));
+		assert!(matches!(RepoInfo::from_repo_url("ssh://host/base", None, None), Err(Error::RepoUnsupportedScheme(..))));
+	}
+
+	#[test]
+	fn test_repo_info_sftp() -> Result<()> {
+		let RepoInfo::Sftp(sftp) = RepoInfo::from_repo_url("sftp://deploy@host.example.com:2222/srv/repo/", None, None)? else {
+			panic!("should be sftp");
+		};
+		assert_eq!(("deploy@host.example.com", Some(2222), "/srv/repo"), (sftp.destination.as_str(), sftp.port, sftp.base.as_str()));
+		let RepoInfo::Sftp(sftp) = RepoInfo::from_repo_url("sftp://host/~/repo", None, None)? else {
+			panic!("should be sftp");
+		};
+		assert_eq!(("host", None, "repo"), (sftp.destination.as_str(), sftp.port, sftp.base.as_str()));
+		assert!(matches!(RepoInfo::from_repo_url("sftp://@host/x", None, None), Err(Error::RepoInvalidSftp(_))));
+		assert!(matches!(RepoInfo::from_repo_url("sftp://host:abc/x", None, None), Err(Error::RepoInvalidSftp(_))));
+		Ok(())
+	}
+
+	#[test]
+	fn test_repo_info_oci() -> Result<()> {
+		let RepoInfo::Oci(oci) = RepoInfo::from_repo_url("oci://ghcr.io/My-Org/binst/", None, None)? else {
+			panic!("should be oci");
+		};
+		assert_eq!(("https://ghcr.io", "my-org/binst"), (oci.registry_url.as_str(), oci.namespace.as_str()));
+		let RepoInfo::Oci(oci) = RepoInfo::from_repo_url("oci://localhost:5000/binst", None, None)? else {
+			panic!("should be oci");
+		};
+		assert_eq!("http://localhost:5000", oci.registry_url);
+		assert!(matches!(RepoInfo::from_repo_url("oci://ghcr.io", None, None), Err(Error::RepoInvalidOci(_))));
+		Ok(())
+	}
+
+	#[test]
+	fn test_repo_info_github() -> Result<()> {
+		let RepoInfo::Github(github) = RepoInfo::from_repo_url("github:BurntSushi/ripgrep", None, None)? else {
+			panic!("should be github");
+		};
+		assert_eq!(("https://api.github.com", "BurntSushi/ripgrep"), (github.api_url.as_str(), github.owner_repo.as_str()));
+		let repo_config = RepoConfig {
+			api_url: Some("https://gitea.example.com/api/v1/".to_string()),
+			..Default::default()
+		};
+		let RepoInfo::Github(github) = RepoInfo::from_repo_url("github:tools/cool_cli", None, Some(&repo_config))? else {
+			panic!("should be github");
+		};
+		assert_eq!("https://gitea.example.com/api/v1", github.api_url);
+		assert!(matches!(RepoInfo::from_repo_url("github:ripgrep", None, None), Err(Error::RepoInvalidGithub(_))));
+		Ok(())
+	}
+
+	#[test]
+	fn test_repo_info_git() -> Result<()> {
+		let RepoInfo::Git(git) = RepoInfo::from_repo_url("git+ssh://git@host:2222/team/repo.git#binst", None, None)? else {
+			panic!("should be git");
+		};
+		assert_eq!(("ssh://git@host:2222/team/repo.git", "binst"), (git.remote.as_str(), git.branch.as_str()));
+		assert_eq!("git+ssh://git@host:2222/team/repo.git#binst", git.url);
+		let repo_config = RepoConfig {
+			branch: Some("releases".to_string()),
+			..Default::default()
+		};
+		let RepoInfo::Git(git) = RepoInfo::from_repo_url("git+file://localhost/srv/repo.git/", None, Some(&repo_config))? else {
+			panic!("should be git");
+		};
+		assert_eq!(("file:///srv/repo.git", "releases"), (git.remote.as_str(), git.branch.as_str()));
+		let RepoInfo::Git(git) = RepoInfo::from_repo_url("git+file:///srv/repo.git", None, None)? else {
+			panic!("should be git");
+		};
+		assert_eq!(("file:///srv/repo.git", "main"), (git.remote.as_str(), git.branch.as_str()));
+		assert!(matches!(RepoInfo::from_repo_url("git+file://tmp/repo.git", None, None), Err(Error::RepoInvalidGit(_))));
+		assert!(matches!(RepoInfo::from_repo_url("git+https://host/repo.git", None, None), Err(Error::RepoInvalidGit(_))));
+		assert!(matches!(RepoInfo::from_repo_url("git+ssh://host/repo.git#", None, None), Err(Error::RepoInvalidGit(_))));
+		Ok(())
+	}
+}
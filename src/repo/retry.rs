@@ -0,0 +1,225 @@
+//! Retry policy for the transient network and S3 failures.
+
+use crate::config::Config;
+use crate::repo::Result;
+use crate::utils::is_verbose;
+use std::env;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ENV_RETRY_ATTEMPTS: &str = "BINST_RETRY_ATTEMPTS";
+const ENV_RETRY_DELAY_MS: &str = "BINST_RETRY_DELAY_MS";
+const ENV_RETRY_MAX_DELAY_MS: &str = "BINST_RETRY_MAX_DELAY_MS";
+
+const CONFIG_RETRY_ATTEMPTS: &str = "retry_attempts";
+const CONFIG_RETRY_DELAY_MS: &str = "retry_delay_ms";
+const CONFIG_RETRY_MAX_DELAY_MS: &str = "retry_max_delay_ms";
+
+const DEFAULT_ATTEMPTS: u32 = 4;
+const DEFAULT_DELAY_MS: u64 = 500;
+const DEFAULT_MAX_DELAY_MS: u64 = 8_000;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+	/// Total number of attempts (1 means no retry).
+	pub attempts: u32,
+	/// Delay before the first retry, doubled on each following retry.
+	pub delay: Duration,
+	/// Upper bound of the delay between two attempts.
+	pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		RetryPolicy {
+			attempts: DEFAULT_ATTEMPTS,
+			delay: Duration::from_millis(DEFAULT_DELAY_MS),
+			max_delay: Duration::from_millis(DEFAULT_MAX_DELAY_MS),
+		}
+	}
+}
+
+/// Builders
+impl RetryPolicy {
+	/// Default policy, overridden by the config `defaults.retry_...` values, and then by the
+	/// `BINST_RETRY_...` environment variables if present.
+	pub fn load() -> Self {
+		// Note: an invalid config file is reported by the command itself, the retries then use the defaults
+		let config = Config::load().unwrap_or_default();
+		RetryPolicy::from_sources(|name| config.default_u64(name), env_u64)
+	}
+
+	/// Default policy, overridden by the `config_u64` values (config names), and then by the `env_u64` ones (env names).
+	fn from_sources(config_u64: impl Fn(&str) -> Option<u64>, env_u64: impl Fn(&str) -> Option<u64>) -> Self {
+		let default = RetryPolicy::default();
+		let value = |env_name: &str, config_name: &str| env_u64(env_name).or_else(|| config_u64(config_name));
+
+		let attempts = value(ENV_RETRY_ATTEMPTS, CONFIG_RETRY_ATTEMPTS)
+			.map(|v| v.max(1) as u32)
+			.unwrap_or(default.attempts);
+		let delay = value(ENV_RETRY_DELAY_MS, CONFIG_RETRY_DELAY_MS)
+			.map(Duration::from_millis)
+			.unwrap_or(default.delay);
+		let max_delay = value(ENV_RETRY_MAX_DELAY_MS, CONFIG_RETRY_MAX_DELAY_MS)
+			.map(Duration::from_millis)
+			.unwrap_or(default.max_delay);
+
+		RetryPolicy {
+			attempts,
+			delay,
+			max_delay,
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// Run the async operation, and retry it while it fails with a retryable error (see `Error::is_retryable`).
+	/// The `label` is only used for the verbose logging (e.g., "GET https://...").
+	pub async fn run<T, F, Fut>(&self, label: &str, mut op: F) -> Result<T>
+	where
+		F: FnMut() -> Fut,
+		Fut: Future<Output = Result<T>>,
+	{
+		let mut attempt = 1;
+		loop {
+			match op().await {
+				Ok(val) => return Ok(val),
+				Err(err) if err.is_retryable() && attempt < self.attempts => {
+					let delay = self.delay_for(attempt);
+					if is_verbose() {
+						println!(
+							"  retry {}/{} '{}' in {}ms - cause: {}",
+							attempt,
+							self.attempts - 1,
+							label,
+							delay.as_millis(),
+							err
+						);
+					}
+					tokio::time::sleep(delay).await;
+					attempt += 1;
+				}
+				Err(err) => return Err(err),
+			}
+		}
+	}
+
+	/// Exponential backoff delay for the given attempt (starting at 1), with jitter.
+	/// The returned delay is between half and the full exponential delay.
+	pub fn delay_for(&self, attempt: u32) -> Duration {
+		let exp = self.delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+		let delay = exp.min(self.max_delay);
+		let half = delay / 2;
+		half + half.mul_f64(jitter_ratio())
+	}
+}
+
+// region:    --- Retry Private Utils
+fn env_u64(name: &str) -> Option<u64> {
+	match env::var(name).map(|v| v.trim().parse::<u64>()) {
+		Ok(Ok(v)) => Some(v),
+		Ok(Err(_)) => {
+			println!("WARNING - ignoring invalid {} value (must be a positive number)", name);
+			None
+		}
+		Err(_) => None,
+	}
+}
+
+/// Cheap pseudo random ratio between 0.0 and 1.0 (good enough for jitter).
+fn jitter_ratio() -> f64 {
+	let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+	(nanos % 1000) as f64 / 1000.
+}
+// endregion: --- Retry Private Utils
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::repo::Error;
+
+	fn test_policy(attempts: u32) -> RetryPolicy {
+		RetryPolicy {
+			attempts,
+			delay: Duration::from_millis(1),
+			max_delay: Duration::from_millis(2),
+		}
+	}
+
+	#[test]
+	fn test_retry_delay_for() {
+		let policy = RetryPolicy {
+			attempts: 5,
+			delay: Duration::from_millis(100),
+			max_delay: Duration::from_millis(300),
+		};
+
+		let delay = policy.delay_for(1);
+		assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+		let delay = policy.delay_for(2);
+		assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+		// capped by max_delay
+		let delay = policy.delay_for(10);
+		assert!(delay >= Duration::from_millis(150) && delay <= Duration::from_millis(300));
+	}
+
+	#[test]
+	fn test_retry_from_sources() {
+		let config_u64 = |name: &str| match name {
+			CONFIG_RETRY_ATTEMPTS => Some(6),
+			CONFIG_RETRY_DELAY_MS => Some(200),
+			_ => None,
+		};
+		let env_u64 = |name: &str| (name == ENV_RETRY_ATTEMPTS).then_some(2);
+
+		// -- the env over the config, over the defaults
+		let policy = RetryPolicy::from_sources(config_u64, env_u64);
+		assert_eq!(2, policy.attempts);
+		assert_eq!(Duration::from_millis(200), policy.delay);
+		assert_eq!(Duration::from_millis(DEFAULT_MAX_DELAY_MS), policy.max_delay);
+
+		let policy = RetryPolicy::from_sources(config_u64, |_| None);
+		assert_eq!(6, policy.attempts);
+	}
+
+	#[tokio::test]
+	async fn test_retry_run() {
+		// retryable errors are retried up to the attempts
+		let mut count = 0;
+		let res: Result<()> = test_policy(3)
+			.run("test", || {
+				count += 1;
+				async { Err(Error::HttpServerError("http://repo/x".to_string(), 503)) }
+			})
+			.await;
+		assert!(res.is_err());
+		assert_eq!(3, count);
+
+		// non retryable errors are returned right away
+		let mut count = 0;
+		let res: Result<()> = test_policy(3)
+			.run("test", || {
+				count += 1;
+				async { Err(Error::OriginTarGzNotFound("http://repo/x".to_string())) }
+			})
+			.await;
+		assert!(res.is_err());
+		assert_eq!(1, count);
+
+		// success after a failure
+		let mut count = 0;
+		let res = test_policy(3)
+			.run("test", || {
+				count += 1;
+				let fail = count < 2;
+				async move {
+					match fail {
+						true => Err(Error::HttpServerError("http://repo/x".to_string(), 502)),
+						false => Ok(count),
+					}
+				}
+			})
+			.await;
+		assert_eq!(2, res.unwrap());
+	}
+}
@@ -0,0 +1,166 @@
+//! Package versions run without install (`binst run`), unpacked under `~/.binst/run/`
+//!
+//! Layout: `run/<bin_name>/<target>/<version>/<sha256>/<bin_name>` (the unpacked package)
+//!
+//! Only the most recently run package versions of a bin are kept (`RUN_KEEP`), the others are removed on each run.
+
+use super::unpack::unpack_tar_gz;
+use super::{get_version_part, make_bin_temp_dir, BinRepo};
+use crate::paths::binst_run_dir;
+use crate::repo::{Error, Result};
+use crate::utils::{safer_remove_dir, sha256_file};
+use semver::Version;
+use std::cmp::Reverse;
+use std::fs::{create_dir_all, read_dir, remove_dir, rename, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The run dirs kept per bin (the most recently run ones)
+const RUN_KEEP: usize = 3;
+
+impl BinRepo {
+	/// Fetch (through the package cache) and unpack the package version (latest of the stream if None)
+	/// in the run dir, and returns the (version, unpacked bin path).
+	/// Note: Does not change the installed packages (no `bin/` symlink, no `install.toml`).
+	pub async fn fetch_run_bin(&self, stream: &str, version: Option<&Version>) -> Result<(Version, PathBuf)> {
+		let tmp_dir = make_bin_temp_dir(&self.bin_name)?;
+		let (_, version, tmp_gz) = self.fetch_package(stream, version, None, &tmp_dir).await?;
+		let sha256 = sha256_file(&tmp_gz)?;
+
+		let run_dir = binst_run_dir()?
+			.join(&self.bin_name)
+			.join(self.bin_target())
+			.join(get_version_part(&version))
+			.join(&sha256);
+		let run_bin = run_dir.join(&self.bin_name);
+
+		// unpack in the tmp dir, and then move it in place (never half unpacked)
+		if !run_bin.is_file() {
+			let unpacked_dir = tmp_dir.join("unpacked");
+			unpack_tar_gz(&tmp_gz, &unpacked_dir)?;
+			if !unpacked_dir.join(&self.bin_name).is_file() {
+				return Err(Error::UnpackedBinFileNotFound(
+					unpacked_dir.join(&self.bin_name).to_string_lossy().to_string(),
+				));
+			}
+			if let Some(parent) = run_dir.parent() {
+				create_dir_all(parent)?;
+			}
+			// Note: e.g., the bin removed by hand, the stale run dir would fail the rename
+			if run_dir.exists() {
+				safer_remove_dir(&run_dir)?;
+			}
+			rename(&unpacked_dir, &run_dir)?;
+		}
+
+		// Note: the run dir modified time is its last run (for the prune)
+		File::open(&run_dir)?.set_modified(SystemTime::now())?;
+		prune_run_dirs(&binst_run_dir()?.join(&self.bin_name), &run_dir)?;
+
+		Ok((version, run_bin))
+	}
+}
+
+/// Remove the run dirs of the bin (`<target>/<version>/<sha256>`) but the `RUN_KEEP` most recently run ones,
+/// and the `keep` one (the current run).
+fn prune_run_dirs(bin_run_dir: &Path, keep: &Path) -> Result<()> {
+	let mut run_dirs = Vec::new();
+	for target_dir in sub_dirs(bin_run_dir)? {
+		for version_dir in sub_dirs(&target_dir)? {
+			for run_dir in sub_dirs(&version_dir)? {
+				let modified = run_dir.metadata()?.modified()?;
+				run_dirs.push((run_dir, modified));
+			}
+		}
+	}
+
+	// most recent first
+	run_dirs.sort_by_key(|(_, modified)| Reverse(*modified));
+	let stale_dirs = run_dirs.into_iter().map(|(dir, _)| dir).filter(|dir| dir != keep).skip(RUN_KEEP.saturating_sub(1));
+	for stale_dir in stale_dirs {
+		safer_remove_dir(&stale_dir)?;
+		// and the emptied version and target dirs
+		for parent in stale_dir.ancestors().skip(1).take(2) {
+			if read_dir(parent)?.next().is_none() {
+				remove_dir(parent)?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn sub_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+	let mut dirs = Vec::new();
+	if dir.is_dir() {
+		for entry in read_dir(dir)? {
+			let path = entry?.path();
+			if path.is_dir() {
+				dirs.push(path);
+			}
+		}
+	}
+	Ok(dirs)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::repo::backend::{LocalBackend, RepoBackend};
+	use crate::repo::RepoInfo;
+	use std::sync::{Mutex, OnceLock};
+
+	#[tokio::test]
+	async fn test_fetch_run_bin_stale_run_dir() -> Result<()> {
+		let test_dir = super::super::backend::tests::set_test_binst_root("run")?;
+
+		// -- the repo with the cool_cli package
+		let gz_file = test_dir.join("cool_cli.tar.gz");
+		let mut builder = tar::Builder::new(libflate::gzip::Encoder::new(std::fs::File::create(&gz_file)?)?);
+		let mut header = tar::Header::new_gnu();
+		header.set_size(2);
+		header.set_mode(0o755);
+		header.set_cksum();
+		builder.append_data(&mut header, "cool_cli", &b"ok"[..])?;
+		builder.into_inner()?.finish().into_result()?;
+		let repo_dir = test_dir.join("repo").to_string_lossy().to_string();
+		let backend = LocalBackend::new(&repo_dir);
+		backend.put_text("cool_cli/x86/main/latest.toml", "[latest]\nversion = \"0.1.0\"").await?;
+		backend.put_file("cool_cli/x86/main/0.1.0/cool_cli.tar.gz", &gz_file).await?;
+
+		let bin_repo = BinRepo {
+			bin_name: "cool_cli".to_string(),
+			install_repo: RepoInfo::Local(repo_dir.clone()),
+			install_mirrors: Vec::new(),
+			trust_mirrors: false,
+			failed_install_repos: Mutex::new(Vec::new()),
+			publish_repo: RepoInfo::Local(repo_dir),
+			target: None,
+			targets: vec!["x86".to_string()],
+			resolved_target: OnceLock::new(),
+			offline: false,
+		};
+
+		// -- run, remove the bin by hand (the run dir not empty), and run again (the stale run dir is replaced)
+		let (_, run_bin) = bin_repo.fetch_run_bin("main", None).await?;
+		std::fs::remove_file(&run_bin)?;
+		std::fs::write(run_bin.with_file_name("other_file"), "stale")?;
+		let (version, run_bin) = bin_repo.fetch_run_bin("main", None).await?;
+		assert_eq!("0.1.0", version.to_string());
+		assert!(run_bin.is_file());
+		assert!(!run_bin.with_file_name("other_file").exists());
+
+		// -- only the RUN_KEEP most recently run versions are kept
+		for version in ["0.1.1", "0.1.2", "0.1.3"] {
+			backend.put_file(&format!("cool_cli/x86/main/{version}/cool_cli.tar.gz"), &gz_file).await?;
+			bin_repo.fetch_run_bin("main", Some(&Version::parse(version).unwrap())).await?;
+		}
+		let version_dirs = sub_dirs(&binst_run_dir()?.join("cool_cli/x86"))?;
+		let mut versions: Vec<String> = version_dirs.iter().filter_map(|d| Some(d.file_name()?.to_string_lossy().to_string())).collect();
+		versions.sort();
+		assert_eq!(vec!["0.1.1", "0.1.2", "0.1.3"], versions);
+
+		std::fs::remove_dir_all(&test_dir)?;
+		Ok(())
+	}
+}
@@ -1,12 +1,14 @@
 //! AWS S3 wrapper
 
 use self::cred::new_aws_client;
+use super::download::PartialDownload;
+use super::retry::RetryPolicy;
 use super::S3Info;
 use crate::prelude::*;
-use crate::repo::Result;
-use aws_sdk_s3::types::ByteStream;
+use crate::repo::{Error, Result};
+use crate::utils::is_verbose;
+use aws_sdk_s3::types::{ByteStream, SdkError};
 use aws_sdk_s3::Client;
-use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use tokio::io::{AsyncReadExt, BufReader};
@@ -16,45 +18,91 @@ mod cred;
 
 pub struct Bucket {
 	client: Client,
+	retry: RetryPolicy,
 }
 
 pub async fn new_repo_bucket(profile: Option<String>) -> Result<Bucket> {
 	let client = new_aws_client(profile).await?;
-	Ok(Bucket { client })
+	let retry = RetryPolicy::load();
+	Ok(Bucket { client, retry })
 }
 
 impl Bucket {
 	/// Get the key object body as string
 	pub async fn download_to_string(&self, s3_info: &S3Info, key: &str) -> Result<String> {
-		let (key, _) = get_full_key_and_s3_url(s3_info, key);
-		let req = self.client.get_object().bucket(s3_info.bucket.clone()).key(key);
-		let res = req.send().await?;
-		let stream = res.body;
+		let (key, s3_url) = get_full_key_and_s3_url(s3_info, key);
+
+		self.retry
+			.run(&f!("GET {s3_url}"), || async {
+				let req = self.client.get_object().bucket(s3_info.bucket.clone()).key(&key);
+				let res = req.send().await?;
+				let stream = res.body;
 
-		// --- Read the buffer all at once (assume small content)
-		let mut buf_reader = BufReader::new(stream.into_async_read());
-		let mut content = String::new();
-		buf_reader.read_to_string(&mut content).await?;
+				// --- Read the buffer all at once (assume small content)
+				let mut buf_reader = BufReader::new(stream.into_async_read());
+				let mut content = String::new();
+				buf_reader.read_to_string(&mut content).await?;
+
+				Ok(content)
+			})
+			.await
+	}
 
-		Ok(content)
+	/// Get the key object body as string, or None if the key does not exist.
+	pub async fn download_to_string_opt(&self, s3_info: &S3Info, key: &str) -> Result<Option<String>> {
+		match self.download_to_string(s3_info, key).await {
+			Ok(content) => Ok(Some(content)),
+			Err(Error::AwsServiceError(code)) if code == "NoSuchKey" => Ok(None),
+			Err(ex) => Err(ex),
+		}
 	}
 
 	/// Download a key relative to the bucket/root, to a file_path
+	/// Interrupted downloads are resumed with a ranged GetObject if the object ETag did not change.
 	/// Returns the resolved S3 URL
 	pub async fn download_to_file(&self, s3_info: &S3Info, key: &str, file_path: &Path) -> Result<String> {
 		let (key, s3_url) = get_full_key_and_s3_url(s3_info, key);
 
-		let req = self.client.get_object().bucket(s3_info.bucket.clone()).key(&key);
-		let res = req.send().await?;
-		let mut data: ByteStream = res.body;
+		self.retry
+			.run(&f!("GET {s3_url}"), || async {
+				let partial = PartialDownload::new(&s3_url)?;
 
-		// Streaming
-		let file = File::create(file_path)?;
-		let mut buf_writer = BufWriter::new(file);
-		while let Some(bytes) = data.try_next().await? {
-			buf_writer.write_all(&bytes)?;
-		}
-		buf_writer.flush()?;
+				let head = self.client.head_object().bucket(&s3_info.bucket).key(&key).send().await?;
+				let etag = head.e_tag().map(|s| s.to_string());
+				let length = head.content_length().max(0) as u64;
+
+				let offset = match (partial.resume_point(), &etag) {
+					(Some((validator, offset)), Some(etag)) if &validator == etag && offset < length => offset,
+					_ => 0,
+				};
+
+				let mut req = self.client.get_object().bucket(&s3_info.bucket).key(&key);
+				let file = if offset > 0 {
+					if is_verbose() {
+						println!("  resuming download at byte {offset} - {s3_url}");
+					}
+					req = req.range(f!("bytes={offset}-"));
+					if let Some(etag) = &etag {
+						req = req.if_match(etag);
+					}
+					partial.append()?
+				} else {
+					partial.start(etag.as_deref())?
+				};
+				let res = req.send().await?;
+				let mut data: ByteStream = res.body;
+
+				// Streaming
+				let mut buf_writer = BufWriter::new(file);
+				while let Some(bytes) = data.try_next().await? {
+					buf_writer.write_all(&bytes)?;
+				}
+				buf_writer.flush()?;
+				drop(buf_writer);
+
+				partial.complete(file_path)
+			})
+			.await?;
 
 		Ok(s3_url)
 	}
@@ -68,19 +116,25 @@ impl Bucket {
 	) -> Result<String> {
 		let (key, s3_url) = get_full_key_and_s3_url(s3_info, key);
 		let content_type = content_type.unwrap_or("text/plain");
-		let body = ByteStream::from(content.into_bytes());
 
-		// BUILD - aws s3 put request
-		let builder = self
-			.client
-			.put_object()
-			.key(&key)
-			.bucket(&s3_info.bucket)
-			.body(body)
-			.content_type(content_type);
+		self.retry
+			.run(&f!("PUT {s3_url}"), || async {
+				let body = ByteStream::from(content.clone().into_bytes());
+
+				// BUILD - aws s3 put request
+				let builder = self
+					.client
+					.put_object()
+					.key(&key)
+					.bucket(&s3_info.bucket)
+					.body(body)
+					.content_type(content_type);
 
-		// EXECUTE - aws request
-		builder.send().await?;
+				// EXECUTE - aws request
+				builder.send().await?;
+				Ok(())
+			})
+			.await?;
 
 		Ok(s3_url)
 	}
@@ -89,23 +143,117 @@ impl Bucket {
 		let (key, s3_url) = get_full_key_and_s3_url(s3_info, key);
 		let mime_type = mime_guess::from_path(file_path).first_or_octet_stream().to_string();
 		let file_path = PathBuf::from(file_path);
-		let body = ByteStream::from_path(&file_path).await?;
-		// BUILD - aws s3 put request
-		let builder = self
-			.client
-			.put_object()
-			.key(&key)
-			.bucket(&s3_info.bucket)
-			.body(body)
-			.content_type(mime_type);
-
-		// EXECUTE - aws request
-		builder.send().await?;
+
+		self.retry
+			.run(&f!("PUT {s3_url}"), || async {
+				let body = ByteStream::from_path(&file_path).await?;
+				// BUILD - aws s3 put request
+				let builder = self
+					.client
+					.put_object()
+					.key(&key)
+					.bucket(&s3_info.bucket)
+					.body(body)
+					.content_type(&mime_type);
+
+				// EXECUTE - aws request
+				builder.send().await?;
+				Ok(())
+			})
+			.await?;
 
 		Ok(s3_url)
 	}
 }
 
+impl Bucket {
+	/// Returns true if the key object exists.
+	pub async fn exists(&self, s3_info: &S3Info, key: &str) -> Result<bool> {
+		let (key, s3_url) = get_full_key_and_s3_url(s3_info, key);
+
+		self.retry
+			.run(&f!("HEAD {s3_url}"), || async {
+				match self.client.head_object().bucket(&s3_info.bucket).key(&key).send().await {
+					Ok(_) => Ok(true),
+					Err(SdkError::ServiceError(se)) if se.err().is_not_found() => Ok(false),
+					Err(ex) => Err(ex.into()),
+				}
+			})
+			.await
+	}
+
+	/// Returns all the keys (relative to the repo base) starting with the prefix.
+	pub async fn list(&self, s3_info: &S3Info, prefix: &str) -> Result<Vec<String>> {
+		let (full_prefix, s3_url) = get_full_key_and_s3_url(s3_info, prefix);
+		let base_prefix = if s3_info.base.is_empty() {
+			String::new()
+		} else {
+			f!("{}/", s3_info.base)
+		};
+
+		let mut keys = Vec::new();
+		let mut continuation_token: Option<String> = None;
+		loop {
+			let res = self
+				.retry
+				.run(&f!("LIST {s3_url}"), || async {
+					let req = self
+						.client
+						.list_objects_v2()
+						.bucket(&s3_info.bucket)
+						.prefix(&full_prefix)
+						.set_continuation_token(continuation_token.clone());
+					Ok(req.send().await?)
+				})
+				.await?;
+
+			let objects = res.contents().unwrap_or_default();
+			keys.extend(
+				objects
+					.iter()
+					.filter_map(|o| o.key())
+					.filter_map(|k| k.strip_prefix(&base_prefix))
+					.map(|k| k.to_string()),
+			);
+
+			match res.next_continuation_token().filter(|_| res.is_truncated()) {
+				Some(token) => continuation_token = Some(token.to_string()),
+				None => break,
+			}
+		}
+
+		Ok(keys)
+	}
+
+	/// Server side copy of the key object to the `to_key`.
+	pub async fn copy(&self, s3_info: &S3Info, key: &str, to_key: &str) -> Result<String> {
+		let (key, _) = get_full_key_and_s3_url(s3_info, key);
+		let (to_key, s3_url) = get_full_key_and_s3_url(s3_info, to_key);
+		let copy_source = f!("{}/{key}", s3_info.bucket);
+
+		self.retry
+			.run(&f!("COPY {s3_url}"), || async {
+				let req = self.client.copy_object().bucket(&s3_info.bucket).key(&to_key).copy_source(&copy_source);
+				req.send().await?;
+				Ok(())
+			})
+			.await?;
+
+		Ok(s3_url)
+	}
+
+	pub async fn delete(&self, s3_info: &S3Info, key: &str) -> Result<()> {
+		let (key, s3_url) = get_full_key_and_s3_url(s3_info, key);
+
+		self.retry
+			.run(&f!("DELETE {s3_url}"), || async {
+				self.client.delete_object().bucket(&s3_info.bucket).key(&key).send().await?;
+				Ok(())
+			})
+			.await
+	}
+}
+
 pub fn get_full_key_and_s3_url(s3_info: &S3Info, key: &str) -> (String, String) {
 	let full_key = if s3_info.base.is_empty() {
 		key.to_string()
@@ -0,0 +1,190 @@
+//! Safe .tar.gz extraction
+//!
+//! Entries are unpacked one by one, and any entry that could write outside of the destination dir
+//! (absolute or `..` paths, links pointing outside, device files, ...) fails the whole unpack.
+//! Paths and link targets going through a symlink of the archive are refused as well, since they are only checked lexically
+//! (e.g., `a/b -> ..` then `x -> a/b/..` would point outside).
+
+use crate::repo::{Error, Result};
+use libflate::gzip::Decoder;
+use std::collections::HashSet;
+use std::fs::{create_dir_all, File};
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use tar::{Archive, EntryType};
+
+/// Unpack the gz_path tar.gz file into the dest_dir.
+/// Note: Executable permission bits are kept (but not setuid/setgid/sticky).
+pub fn unpack_tar_gz(gz_path: &Path, dest_dir: &Path) -> Result<()> {
+	let gz_file = File::open(gz_path)?;
+	let dec = Decoder::new(BufReader::new(gz_file))?;
+	unpack_tar(dec, dest_dir)
+}
+
+fn unpack_tar(reader: impl Read, dest_dir: &Path) -> Result<()> {
+	if !dest_dir.is_dir() {
+		create_dir_all(dest_dir)?;
+	}
+
+	// the symlinks unpacked so far (relative to the dest_dir)
+	let mut symlinks: HashSet<PathBuf> = HashSet::new();
+
+	let mut archive = Archive::new(reader);
+	for entry in archive.entries()? {
+		let mut entry = entry?;
+		let path = entry.path()?.to_path_buf();
+
+		let rel_path = normalize_inside(Path::new(""), &path, false, &symlinks)
+			.ok_or_else(|| unsafe_entry(&path, "path outside of the package dir, or through a symlink"))?;
+
+		match entry.header().entry_type() {
+			EntryType::Regular | EntryType::Continuous | EntryType::Directory => (),
+			// symlink target is relative to the link parent dir
+			EntryType::Symlink => {
+				let link = entry
+					.link_name()?
+					.ok_or_else(|| unsafe_entry(&path, "symlink without target"))?
+					.to_path_buf();
+				let link_parent = rel_path.parent().unwrap_or_else(|| Path::new(""));
+				if normalize_inside(link_parent, &link, true, &symlinks).is_none() {
+					return Err(unsafe_entry(&path, "symlink pointing outside of the package dir, or through a symlink"));
+				}
+				symlinks.insert(rel_path);
+			}
+			// hard link target is relative to the archive root
+			EntryType::Link => {
+				let link = entry
+					.link_name()?
+					.ok_or_else(|| unsafe_entry(&path, "hard link without target"))?
+					.to_path_buf();
+				if normalize_inside(Path::new(""), &link, false, &symlinks).is_none() {
+					return Err(unsafe_entry(&path, "hard link pointing outside of the package dir, or through a symlink"));
+				}
+			}
+			other => return Err(unsafe_entry(&path, &format!("unsupported entry type {:?}", other))),
+		}
+
+		// Note: unpack_in also refuses to write outside of dest_dir (second line of defense).
+		entry.unpack_in(dest_dir)?;
+	}
+
+	Ok(())
+}
+
+/// Resolve the `path` relative to the `base` (both relative to the package dir) without touching the filesystem.
+/// Returns None if the path is absolute, resolves outside of the package dir, or goes through one of the `symlinks`.
+/// When `allow_parent` is false, any `..` component is refused.
+fn normalize_inside(base: &Path, path: &Path, allow_parent: bool, symlinks: &HashSet<PathBuf>) -> Option<PathBuf> {
+	let mut parts: Vec<Component> = base.components().collect();
+	for comp in path.components() {
+		match comp {
+			Component::Normal(_) => {
+				parts.push(comp);
+				if symlinks.contains(&parts.iter().collect::<PathBuf>()) {
+					return None;
+				}
+			}
+			Component::CurDir => (),
+			Component::ParentDir if allow_parent => {
+				parts.pop()?;
+			}
+			Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+		}
+	}
+	Some(parts.iter().collect())
+}
+
+fn unsafe_entry(path: &Path, reason: &str) -> Error {
+	Error::UnsafeArchiveEntry(path.to_string_lossy().to_string(), reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::remove_dir_all;
+	use std::os::unix::fs::PermissionsExt;
+	use tar::{Builder, Header};
+
+	fn tar_data(entries: &[(&str, EntryType, &str)]) -> Vec<u8> {
+		let mut builder = Builder::new(Vec::new());
+		for (path, entry_type, link_or_content) in entries {
+			let mut header = Header::new_gnu();
+			// Note: write the raw name, as `set_path` already refuses `..` and absolute paths.
+			header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+			header.set_entry_type(*entry_type);
+			header.set_mode(0o755);
+			let content = match entry_type {
+				EntryType::Symlink | EntryType::Link => {
+					header.set_link_name(link_or_content).unwrap();
+					""
+				}
+				_ => link_or_content,
+			};
+			header.set_size(content.len() as u64);
+			header.set_cksum();
+			builder.append(&header, content.as_bytes()).unwrap();
+		}
+		builder.into_inner().unwrap()
+	}
+
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("binst-test-unpack-{}-{}", name, std::process::id()));
+		let _ = remove_dir_all(&dir);
+		dir
+	}
+
+	#[test]
+	fn test_unpack_tar_valid() -> Result<()> {
+		let dir = test_dir("valid");
+		let data = tar_data(&[
+			("cool_cli", EntryType::Regular, "#!/bin/sh"),
+			("lib/", EntryType::Directory, ""),
+			("lib/cool_lib", EntryType::Regular, "lib"),
+			("lib/link", EntryType::Symlink, "../cool_cli"),
+		]);
+
+		unpack_tar(data.as_slice(), &dir)?;
+
+		let mode = dir.join("cool_cli").metadata()?.permissions().mode();
+		assert_eq!(0o755, mode & 0o777);
+		assert!(dir.join("lib/cool_lib").is_file());
+		assert!(dir.join("lib/link").is_file());
+
+		remove_dir_all(&dir)?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_unpack_tar_unsafe() {
+		let cases: &[(&str, EntryType, &str)] = &[
+			("../evil", EntryType::Regular, "evil"),
+			("lib/../../evil", EntryType::Regular, "evil"),
+			("/tmp/evil", EntryType::Regular, "evil"),
+			("link", EntryType::Symlink, "/etc/passwd"),
+			("lib/link", EntryType::Symlink, "../../etc/passwd"),
+			("hard", EntryType::Link, "../outside"),
+			("dev", EntryType::Char, ""),
+			("fifo", EntryType::Fifo, ""),
+		];
+		let cases: Vec<Vec<(&str, EntryType, &str)>> = cases.iter().map(|case| vec![*case]).collect();
+
+		// -- through a symlink of the archive (each one inside lexically)
+		let chains: &[&[(&str, EntryType, &str)]] = &[
+			&[("a/b", EntryType::Symlink, ".."), ("x", EntryType::Symlink, "a/b/..")],
+			&[("a/b", EntryType::Symlink, ".."), ("a/b/evil", EntryType::Regular, "evil")],
+			&[("a/b", EntryType::Symlink, ".."), ("hard", EntryType::Link, "a/b/cool_cli")],
+		];
+		let cases = cases.into_iter().chain(chains.iter().map(|chain| chain.to_vec()));
+
+		for (idx, case) in cases.enumerate() {
+			let dir = test_dir(&format!("unsafe-{idx}"));
+			let res = unpack_tar(tar_data(&case).as_slice(), &dir);
+			assert!(
+				matches!(res, Err(Error::UnsafeArchiveEntry(..))),
+				"entries {:?} should be refused",
+				case.iter().map(|entry| entry.0).collect::<Vec<_>>()
+			);
+			let _ = remove_dir_all(&dir);
+		}
+	}
+}
@@ -1,6 +1,11 @@
+use crate::paths::binst_dir;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::BufReader;
 use std::os::unix::fs::symlink;
 use std::process::Command;
 use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{fs::remove_dir_all, path::Path};
 use toml::Value;
 
@@ -30,6 +35,18 @@ impl Error {
 	}
 }
 
+// region:    --- Verbose
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_verbose(verbose: bool) {
+	VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+pub fn is_verbose() -> bool {
+	VERBOSE.load(Ordering::Relaxed)
+}
+// endregion: --- Verbose
+
 pub fn get_toml_value<'v>(root: &'v Value, arr: &[&str]) -> Result<&'v Value, Error> {
 	let mut value: &Value = root;
 	for name in arr {
@@ -49,6 +66,24 @@ pub fn get_toml_value_as_string(root: &Value, arr: &[&str]) -> Result<String, Er
 	}
 }
 
+/// Returns the lowercase hex sha256 of the file content.
+pub fn sha256_file(path: &Path) -> Result<String, Error> {
+	let mut reader = BufReader::new(File::open(path)?);
+	let mut hasher = Sha256::new();
+	std::io::copy(&mut reader, &mut hasher)?;
+	Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the lowercase hex sha256 of the string.
+pub fn sha256_str(content: &str) -> String {
+	sha256_bytes(content.as_bytes())
+}
+
+/// Returns the lowercase hex sha256 of the bytes.
+pub fn sha256_bytes(content: &[u8]) -> String {
+	format!("{:x}", Sha256::digest(content))
+}
+
 pub fn sym_link(original: &Path, link: &Path) -> Result<(), std::io::Error> {
 	// TODO: add windows support
 	symlink(original, link)
@@ -57,7 +92,12 @@ pub fn sym_link(original: &Path, link: &Path) -> Result<(), std::io::Error> {
 // some small but still additional precaution when deleting directory
 pub fn safer_remove_dir(dir: &Path) -> Result<(), Error> {
 	let path_str = dir.to_string_lossy(); // good enough for contains below
-	if !path_str.contains("binst") {
+	// Note: the binst dir can be an alternate root (e.g., `--root ./image-root`)
+	let in_binst_dir = match binst_dir() {
+		Ok(binst_dir) => dir.starts_with(&binst_dir) && dir != binst_dir,
+		Err(_) => false,
+	};
+	if !path_str.contains("binst") && !in_binst_dir {
 		return Err(Error::DirNotSafeToDelete(path_str.to_string()));
 	}
 
@@ -66,7 +106,7 @@ pub fn safer_remove_dir(dir: &Path) -> Result<(), Error> {
 	Ok(())
 }
 
-//// Remove redundant / as well as start and end /
+/// Remove redundant / as well as start and end /
 pub fn clean_path(uri: impl AsRef<str>) -> String {
 	fn cleaner(s: &str) -> String {
 		s.split('/').filter(|p| !p.is_empty()).collect::<Vec<&str>>().join("/")
@@ -112,4 +152,12 @@ mod tests {
 		);
 		assert_eq!("example.net/foo/bar", clean_path("example.net////foo/bar"));
 	}
+
+	#[test]
+	fn test_sha256_str() {
+		assert_eq!(
+			"2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+			sha256_str("hello")
+		);
+	}
 }
@@ -0,0 +1,20 @@
+//! Helpers of the integration tests.
+
+use libflate::gzip::Encoder;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Write a package `.tar.gz` with the bin file (`name`, executable).
+pub fn write_tar_gz(path: &Path, name: &str, content: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+	let mut header = tar::Header::new_gnu();
+	header.set_size(content.len() as u64);
+	header.set_mode(0o755);
+	header.set_cksum();
+	let mut builder = tar::Builder::new(Vec::new());
+	builder.append_data(&mut header, name, content)?;
+	let mut encoder = Encoder::new(Vec::new())?;
+	encoder.write_all(&builder.into_inner()?)?;
+	File::create(path)?.write_all(&encoder.finish().into_result()?)?;
+	Ok(())
+}
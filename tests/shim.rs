@@ -0,0 +1,50 @@
+//! Run a `bin/<bin>` shim, generated by a shim mode install, with the binst args in the bin args.
+
+mod common;
+
+use common::write_tar_gz;
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::process::Command;
+
+const TARGET: &str = "x86_64-unknown-linux-gnu";
+
+#[test]
+fn test_shim_args_as_is() -> Result<(), Box<dyn std::error::Error>> {
+	let test_dir = std::env::temp_dir().join(format!("binst-test-shim-{}", std::process::id()));
+	// Note: the root quoted in the shim script
+	let root = test_dir.join("root $HOME `x` \"q\" 'a'");
+	let repo = test_dir.join("repo");
+
+	// -- the repo, with a cool_cli printing its args (one per line)
+	let version_dir = repo.join("cool_cli").join(TARGET).join("main");
+	create_dir_all(version_dir.join("0.1.0"))?;
+	write(version_dir.join("latest.toml"), "[latest]\nversion = \"0.1.0\"")?;
+	write_tar_gz(
+		&version_dir.join("0.1.0/cool_cli.tar.gz"),
+		"cool_cli",
+		b"#!/bin/sh\nfor arg in \"$@\"; do echo \"$arg\"; done\n",
+	)?;
+
+	// -- install in shim mode
+	create_dir_all(&root)?;
+	write(root.join("config.toml"), "[defaults]\nshims = true\n")?;
+	let binst = |args: &[&str]| {
+		Command::new(env!("CARGO_BIN_EXE_binst"))
+			.env("HOME", &test_dir)
+			.arg("--root")
+			.arg(&root)
+			.args(args)
+			.output()
+	};
+	let output = binst(&["install", "cool_cli", "-r", &repo.to_string_lossy(), "--target", TARGET])?;
+	assert!(root.join("bin/cool_cli").is_file(), "install failed: {}", String::from_utf8_lossy(&output.stdout));
+
+	// -- the shim args are the bin args, as is
+	let args = ["-v", "x", "--system", "--", "z", "--root", "/x", "y"];
+	let output = Command::new(root.join("bin/cool_cli")).env("HOME", &test_dir).args(args).output()?;
+	let bin_args: Vec<String> = String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect();
+	assert_eq!(args.to_vec(), bin_args);
+
+	remove_dir_all(&test_dir)?;
+	Ok(())
+}
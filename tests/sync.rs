@@ -0,0 +1,66 @@
+//! Sync a project with a `binst.lock` written on another platform (another target).
+
+mod common;
+
+use common::write_tar_gz;
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+use std::process::Command;
+
+const TARGET: &str = "x86_64-unknown-linux-gnu";
+const OTHER_TARGET: &str = "aarch64-apple-darwin";
+
+#[test]
+fn test_sync_lock_other_target() -> Result<(), Box<dyn std::error::Error>> {
+	let test_dir = std::env::temp_dir().join(format!("binst-test-sync-{}", std::process::id()));
+	let root = test_dir.join("root");
+	let repo = test_dir.join("repo");
+	let project = test_dir.join("project");
+
+	// -- the repo, with cool_cli 0.1.0 for TARGET only
+	let version_dir = repo.join("cool_cli").join(TARGET).join("main");
+	create_dir_all(version_dir.join("0.1.0"))?;
+	write(version_dir.join("latest.toml"), "[latest]\nversion = \"0.1.0\"")?;
+	write_tar_gz(&version_dir.join("0.1.0/cool_cli.tar.gz"), "cool_cli", b"#!/bin/sh\necho cool\n")?;
+
+	// -- the project, locked on another platform
+	create_dir_all(&project)?;
+	create_dir_all(&root)?;
+	let repo_path = repo.to_string_lossy();
+	write(project.join("binst.toml"), format!("[tools.cool_cli]\nrepo = \"{repo_path}\"\n"))?;
+	let other_lock = format!(
+		"[tools.cool_cli]\nversion = \"0.1.0\"\nrepo = \"{repo_path}\"\nstream = \"main\"\n\n[tools.cool_cli.targets.{OTHER_TARGET}]\nsha256 = \"other\"\n"
+	);
+	write(project.join("binst.lock"), &other_lock)?;
+
+	let binst = |args: &[&str]| {
+		Command::new(env!("CARGO_BIN_EXE_binst"))
+			.current_dir(&project)
+			.env("HOME", &test_dir)
+			.env("BINST_TARGETS", TARGET)
+			.arg("--root")
+			.arg(&root)
+			.args(args)
+			.output()
+	};
+
+	// -- --locked fails (no sha256 locked for this target), and does not install the other target
+	let output = binst(&["sync", "--locked"])?;
+	assert!(String::from_utf8_lossy(&output.stdout).contains("cool_cli is not locked"));
+	assert!(!root.join("bin/cool_cli").exists());
+	assert_eq!(other_lock, read_to_string(project.join("binst.lock"))?);
+
+	// -- sync installs the locked version for this target, and adds it to the lock (keeping the other one)
+	let output = binst(&["sync"])?;
+	assert!(root.join("bin/cool_cli").exists(), "sync failed: {}", String::from_utf8_lossy(&output.stdout));
+	let lock: toml::Table = read_to_string(project.join("binst.lock"))?.parse()?;
+	let targets = lock["tools"]["cool_cli"]["targets"].as_table().ok_or("no targets")?;
+	assert_eq!(Some("other"), targets[OTHER_TARGET]["sha256"].as_str());
+	assert_eq!(64, targets[TARGET]["sha256"].as_str().map(|s| s.len()).unwrap_or_default());
+
+	// -- then --locked passes
+	let output = binst(&["sync", "--locked"])?;
+	assert!(String::from_utf8_lossy(&output.stdout).contains("up to date"));
+
+	remove_dir_all(&test_dir)?;
+	Ok(())
+}